@@ -0,0 +1,121 @@
+//! MCP23S08 on a Linux spidev bus, shared by two threads.
+//!
+//! ```text
+//! cargo run --example spidev --features linux -- /dev/spidev0.0
+//! cargo run --example spidev --features linux -- sim
+//! ```
+//!
+//! A worker thread blinks the LED on P0 while the main thread watches the
+//! buttons on P4..P7 (pull-ups, pressed = low) and mirrors P4 on P1. With
+//! `sim` the expander is the crate's simulator with P4 held down, so the
+//! example also runs without hardware.
+
+use std::error::Error as StdError;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use embedded_hal::digital::{OutputPin, StatefulOutputPin};
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+use mcp23s08_io::expander::IoExpander;
+use mcp23s08_io::mcp23s08::{Error, Mcp23s08, Pin, Reg};
+use mcp23s08_io::shared::SharedMcp23s08;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+const BLINKS: u32 = 20;
+const PERIOD: Duration = Duration::from_millis(250);
+
+fn main() -> Result<(), Box<dyn StdError>> {
+    let target = std::env::args().nth(1).unwrap_or_else(|| "sim".into());
+    if target == "sim" {
+        let sim = SimBus::new();
+        sim.set_inputs(0xEF);
+        run(sim, BLINKS, PERIOD)
+    } else {
+        open_and_run(&target)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_and_run(path: &str) -> Result<(), Box<dyn StdError>> {
+    let spi = mcp23s08_io::linux::SpidevDevice::open(path, 1_000_000)?;
+    run(spi, BLINKS, PERIOD)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_and_run(_path: &str) -> Result<(), Box<dyn StdError>> {
+    Err("spidev is only available on Linux; pass `sim`".into())
+}
+
+/// Blinks P0 from a worker thread while this thread polls the buttons.
+fn run<S>(spi: S, blinks: u32, period: Duration) -> Result<(), Box<dyn StdError>>
+where
+    S: SpiDevice + Send + 'static,
+    S::Error: Send + Sync + 'static,
+{
+    let mut io = SharedMcp23s08::new(Mcp23s08::new(spi, 0)?);
+    io.set_port_direction(0xF0)?;
+    io.set_port_pullups(0xF0)?;
+
+    let mut led = io.pin(Pin::P0);
+    let blinker = thread::spawn(move || -> Result<(), Error<S::Error>> {
+        for _ in 0..blinks {
+            led.toggle()?;
+            thread::sleep(period);
+        }
+        led.set_low()
+    });
+
+    let mut last = None;
+    while !blinker.is_finished() {
+        let pressed = !io.read_port()? & 0xF0;
+        if last != Some(pressed) {
+            println!("buttons pressed: {pressed:#010b}");
+            last = Some(pressed);
+        }
+        // Each call locks the driver, so the blinker's P0 is never lost.
+        io.write_pin(Pin::P1, pressed & Pin::P4.mask() != 0)?;
+        thread::sleep(period / 10);
+    }
+    blinker.join().expect("blink thread panicked")?;
+    Ok(())
+}
+
+/// The simulator behind a mutex, so that it can move between threads.
+#[derive(Clone)]
+struct SimBus(Arc<Mutex<Mcp23s08Sim>>);
+
+impl SimBus {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(Mcp23s08Sim::new())))
+    }
+
+    fn set_inputs(&self, levels: u8) {
+        self.0.lock().unwrap().set_inputs(0, levels);
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn reg(&self, reg: Reg) -> u8 {
+        self.0.lock().unwrap().reg(0, reg)
+    }
+}
+
+impl ErrorType for SimBus {
+    type Error = ErrorKind;
+}
+
+impl SpiDevice for SimBus {
+    fn transaction(&mut self, ops: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.0.lock().unwrap().device().transaction(ops)
+    }
+}
+
+#[test]
+fn runs_on_the_simulator() {
+    let sim = SimBus::new();
+    sim.set_inputs(0xEF);
+    run(sim.clone(), 3, Duration::from_millis(5)).unwrap();
+    // LED off after blinking, P1 follows the pressed P4.
+    assert_eq!(sim.reg(Reg::OLAT), 0x02);
+    assert_eq!(sim.reg(Reg::GPPU), 0xF0);
+}
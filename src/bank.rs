@@ -0,0 +1,333 @@
+//! Several expanders presented as one wide virtual port.
+//!
+//! An [`ExpanderBank`] owns `N` [`IoExpander`]s, which may sit on separate
+//! chip-selects or share one with `IOCON.HAEN` addressing. Chip `i` holds
+//! bits `i * WIDTH ..` of a `u64` virtual port, so with four MCP23S08s
+//! channel 17 is pin `P1` of chip 2. Bulk writes are split per chip and only
+//! the chips whose latch actually changes are written.
+//!
+//! ```
+//! use mcp23s08_io::bank::ExpanderBank;
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Reg};
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::with_chips(&[0, 1, 2, 3]);
+//! let mut bank: ExpanderBank<_, 4> =
+//!     ExpanderBank::try_new(|i| Mcp23s08::new(sim.device(), i as u8)).unwrap();
+//! bank.set_port_direction(0).unwrap();
+//! bank.write_pin(17, true).unwrap();
+//! assert_eq!(sim.reg(2, Reg::OLAT), 0x02);
+//! assert_eq!(bank.output_latch(), 1 << 17);
+//! ```
+
+use core::fmt::Debug;
+
+use crate::expander::IoExpander;
+use crate::mcp23s08::Pin;
+
+/// Error of an [`ExpanderBank`] operation: which chip failed, and how.
+///
+/// Chips before `chip` have already been accessed; the ones after it have
+/// not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BankError<E> {
+    pub chip: usize,
+    pub error: E,
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for BankError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "chip {}: {}", self.chip, self.error)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for BankError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<E: embedded_hal::digital::Error> embedded_hal::digital::Error for BankError<E> {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        self.error.kind()
+    }
+}
+
+/// `N` expanders as one virtual port of `N * X::WIDTH` pins (at most 64).
+pub struct ExpanderBank<X, const N: usize> {
+    chips: [X; N],
+}
+
+impl<X: IoExpander, const N: usize> ExpanderBank<X, N> {
+    /// Number of pins in the virtual port.
+    pub const PINS: usize = N * X::WIDTH as usize;
+
+    /// Bank of already constructed drivers; `chips[0]` holds the lowest
+    /// pins.
+    pub fn new(chips: [X; N]) -> Self {
+        const { assert!(Self::PINS <= 64, "an expander bank has at most 64 pins") };
+        Self { chips }
+    }
+
+    /// Builds chip `i` with `f(i)`. Every chip is constructed; the first
+    /// failure is returned.
+    pub fn try_new<E>(mut f: impl FnMut(usize) -> Result<X, E>) -> Result<Self, BankError<E>> {
+        let results: [Result<X, E>; N] = core::array::from_fn(&mut f);
+        if let Some(chip) = results.iter().position(Result::is_err) {
+            let Some(Err(error)) = results.into_iter().nth(chip) else {
+                unreachable!()
+            };
+            return Err(BankError { chip, error });
+        }
+        Ok(Self::new(results.map(|result| match result {
+            Ok(chip) => chip,
+            Err(_) => unreachable!(),
+        })))
+    }
+
+    /// Chip and pin of global pin `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= PINS`.
+    pub fn locate(index: usize) -> (usize, Pin) {
+        assert!(
+            index < Self::PINS,
+            "pin {index} outside a {}-pin bank",
+            Self::PINS
+        );
+        let width = usize::from(X::WIDTH);
+        // `index % width` is below 8.
+        let pin = Pin::try_from((index % width) as u8).unwrap_or(Pin::P0);
+        (index / width, pin)
+    }
+
+    pub fn chip(&self, chip: usize) -> &X {
+        &self.chips[chip]
+    }
+
+    /// Direct access to one driver, e.g. for its interrupt setup.
+    pub fn chip_mut(&mut self, chip: usize) -> &mut X {
+        &mut self.chips[chip]
+    }
+
+    pub fn into_inner(self) -> [X; N] {
+        self.chips
+    }
+
+    /// Levels on all pins, one read per chip.
+    pub fn read_port(&mut self) -> Result<u64, BankError<X::Error>> {
+        self.gather(|chip| chip.read_port())
+    }
+
+    /// Output latches as last written, without bus traffic.
+    pub fn output_latch(&self) -> u64 {
+        self.chips.iter().enumerate().fold(0, |port, (i, chip)| {
+            port | Self::place(i, chip.output_latch())
+        })
+    }
+
+    /// Writes the whole virtual latch. Chips whose latch already holds
+    /// their part of `value` are skipped.
+    pub fn write_olat(&mut self, value: u64) -> Result<(), BankError<X::Error>> {
+        for (i, chip) in self.chips.iter_mut().enumerate() {
+            let bits = Self::slice(i, value);
+            if bits != chip.output_latch() {
+                chip.write_olat(bits)
+                    .map_err(|error| BankError { chip: i, error })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the latch bits in `mask` to the matching bits of `value`. Only
+    /// chips whose latch changes are written.
+    pub fn modify_port(&mut self, mask: u64, value: u64) -> Result<(), BankError<X::Error>> {
+        for (i, chip) in self.chips.iter_mut().enumerate() {
+            let mask = Self::slice(i, mask);
+            let value = Self::slice(i, value);
+            let latch = chip.output_latch();
+            if (latch & !mask) | (value & mask) != latch {
+                chip.modify_port(mask, value)
+                    .map_err(|error| BankError { chip: i, error })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the pins in `inputs` inputs and all others outputs.
+    pub fn set_port_direction(&mut self, inputs: u64) -> Result<(), BankError<X::Error>> {
+        self.scatter(|i, chip| chip.set_port_direction(Self::slice(i, inputs)))
+    }
+
+    /// Sets the direction of the pins in `mask` (`1` in `inputs` = input).
+    /// Chips without a pin in `mask` are not written.
+    pub fn modify_port_direction(
+        &mut self,
+        mask: u64,
+        inputs: u64,
+    ) -> Result<(), BankError<X::Error>> {
+        self.scatter(|i, chip| match Self::slice(i, mask) {
+            0 => Ok(()),
+            mask => chip.modify_port_direction(mask, Self::slice(i, inputs)),
+        })
+    }
+
+    /// Enables the pull-ups of the pins in `mask` and disables all others.
+    pub fn set_port_pullups(&mut self, mask: u64) -> Result<(), BankError<X::Error>> {
+        self.scatter(|i, chip| chip.set_port_pullups(Self::slice(i, mask)))
+    }
+
+    /// Pins with a pending interrupt.
+    pub fn read_interrupt_flags(&mut self) -> Result<u64, BankError<X::Error>> {
+        self.gather(|chip| chip.read_interrupt_flags())
+    }
+
+    /// Port levels captured when each chip's interrupt fired.
+    pub fn read_interrupt_capture(&mut self) -> Result<u64, BankError<X::Error>> {
+        self.gather(|chip| chip.read_interrupt_capture())
+    }
+
+    /// # Panics
+    ///
+    /// If `index >= PINS`, as for all methods taking a global pin index.
+    pub fn read_pin(&mut self, index: usize) -> Result<bool, BankError<X::Error>> {
+        self.with_pin(index, |chip, pin| chip.read_pin(pin))
+    }
+
+    pub fn write_pin(&mut self, index: usize, high: bool) -> Result<(), BankError<X::Error>> {
+        self.with_pin(index, |chip, pin| chip.write_pin(pin, high))
+    }
+
+    pub fn set_pin_direction(
+        &mut self,
+        index: usize,
+        input: bool,
+    ) -> Result<(), BankError<X::Error>> {
+        self.with_pin(index, |chip, pin| chip.set_pin_direction(pin, input))
+    }
+
+    pub fn set_pin_pullup(
+        &mut self,
+        index: usize,
+        enable: bool,
+    ) -> Result<(), BankError<X::Error>> {
+        self.with_pin(index, |chip, pin| chip.set_pin_pullup(pin, enable))
+    }
+
+    /// Handle for global pin `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= PINS`.
+    pub fn pin(&mut self, index: usize) -> BankPin<'_, X, N> {
+        Self::locate(index);
+        BankPin { bank: self, index }
+    }
+
+    /// Chip `chip`'s bits of the virtual port value `port`.
+    fn slice(chip: usize, port: u64) -> u8 {
+        let width = u32::from(X::WIDTH);
+        ((port >> (chip as u32 * width)) & ((1 << width) - 1)) as u8
+    }
+
+    /// `bits` of chip `chip` at their place in the virtual port.
+    fn place(chip: usize, bits: u8) -> u64 {
+        let width = u32::from(X::WIDTH);
+        (u64::from(bits) & ((1 << width) - 1)) << (chip as u32 * width)
+    }
+
+    /// Reads one value per chip and assembles them.
+    fn gather(
+        &mut self,
+        mut read: impl FnMut(&mut X) -> Result<u8, X::Error>,
+    ) -> Result<u64, BankError<X::Error>> {
+        let mut port = 0;
+        for (i, chip) in self.chips.iter_mut().enumerate() {
+            let bits = read(chip).map_err(|error| BankError { chip: i, error })?;
+            port |= Self::place(i, bits);
+        }
+        Ok(port)
+    }
+
+    /// Runs `write` on every chip in order.
+    fn scatter(
+        &mut self,
+        mut write: impl FnMut(usize, &mut X) -> Result<(), X::Error>,
+    ) -> Result<(), BankError<X::Error>> {
+        for (i, chip) in self.chips.iter_mut().enumerate() {
+            write(i, chip).map_err(|error| BankError { chip: i, error })?;
+        }
+        Ok(())
+    }
+
+    fn with_pin<T>(
+        &mut self,
+        index: usize,
+        f: impl FnOnce(&mut X, Pin) -> Result<T, X::Error>,
+    ) -> Result<T, BankError<X::Error>> {
+        let (chip, pin) = Self::locate(index);
+        f(&mut self.chips[chip], pin).map_err(|error| BankError { chip, error })
+    }
+}
+
+impl<X: Debug, const N: usize> Debug for ExpanderBank<X, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExpanderBank")
+            .field("chips", &self.chips)
+            .finish()
+    }
+}
+
+/// One pin of an [`ExpanderBank`] by its global index.
+pub struct BankPin<'a, X, const N: usize> {
+    bank: &'a mut ExpanderBank<X, N>,
+    index: usize,
+}
+
+impl<X, const N: usize> embedded_hal::digital::ErrorType for BankPin<'_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    type Error = BankError<X::Error>;
+}
+
+impl<X, const N: usize> embedded_hal::digital::InputPin for BankPin<'_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.bank.read_pin(self.index)
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
+
+impl<X, const N: usize> embedded_hal::digital::OutputPin for BankPin<'_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.bank.write_pin(self.index, true)
+    }
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.bank.write_pin(self.index, false)
+    }
+}
+
+impl<X, const N: usize> embedded_hal::digital::StatefulOutputPin for BankPin<'_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.bank.output_latch() & (1 << self.index) != 0)
+    }
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_set_high()?)
+    }
+}
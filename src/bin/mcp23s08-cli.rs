@@ -0,0 +1,189 @@
+//! Command-line access to an MCP23S08 on Linux spidev or on the simulator.
+//!
+//! ```text
+//! mcp23s08-cli --bus /dev/spidev0.0 dump
+//! mcp23s08-cli --bus /dev/spidev0.0 --addr 1 write 0x05 --mask 0x0f
+//! mcp23s08-cli --bus sim --inputs 0xa0 watch --samples 3
+//! ```
+//!
+//! Every run opens the chip afresh: the driver reads `IODIR` and `OLAT`
+//! back, so masked writes keep the pins set by earlier runs.
+
+use std::error::Error as StdError;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand};
+use embedded_hal::spi::SpiDevice;
+use mcp23s08_io::expander::IoExpander;
+use mcp23s08_io::linux::SpidevDevice;
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[derive(Parser)]
+#[command(version, about = "Read and drive an MCP23S08 SPI GPIO expander")]
+struct Cli {
+    /// spidev device, or `sim` for the built-in simulator.
+    #[arg(long, short, default_value = "/dev/spidev0.0")]
+    bus: String,
+    /// Hardware address set on A1:A0.
+    #[arg(long, short, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=3))]
+    addr: u8,
+    /// SPI clock in Hz.
+    #[arg(long, default_value_t = 1_000_000)]
+    speed: u32,
+    /// Simulator only: levels driven onto the pins from outside.
+    #[arg(long, value_parser = parse_byte, default_value = "0x00")]
+    inputs: u8,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print all registers, decoded.
+    Dump,
+    /// Read the pin levels, or the level of one pin (`1`/`0`).
+    Read {
+        #[arg(value_parser = parse_pin)]
+        pin: Option<Pin>,
+    },
+    /// Write the output latch; with `--mask` only those pins change.
+    Write {
+        #[arg(value_parser = parse_byte)]
+        value: u8,
+        #[arg(long, value_parser = parse_byte, default_value = "0xff")]
+        mask: u8,
+    },
+    /// Set pin directions, `1` = input; with `--mask` only those pins change.
+    SetDir {
+        #[arg(value_parser = parse_byte)]
+        inputs: u8,
+        #[arg(long, value_parser = parse_byte, default_value = "0xff")]
+        mask: u8,
+    },
+    /// Enable the pull-ups of the pins in MASK and disable all others.
+    Pullup {
+        #[arg(value_parser = parse_byte)]
+        mask: u8,
+    },
+    /// Print the pin levels at start and whenever they change.
+    Watch {
+        /// Poll period.
+        #[arg(long, default_value_t = 10)]
+        interval_ms: u64,
+        /// Stop after this many polls (default: run until interrupted).
+        #[arg(long)]
+        samples: Option<u64>,
+    },
+    /// Make PIN an output and invert its latch for MS milliseconds.
+    Pulse {
+        #[arg(value_parser = parse_pin)]
+        pin: Pin,
+        ms: u64,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = if cli.bus == "sim" {
+        let sim = Mcp23s08Sim::with_chips(&[cli.addr]);
+        sim.set_inputs(cli.addr, cli.inputs);
+        run(sim.device(), &cli)
+    } else {
+        match SpidevDevice::open(&cli.bus, cli.speed) {
+            Ok(spi) => run(spi, &cli),
+            Err(e) => Err(format!("cannot open {}: {e}", cli.bus).into()),
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run<S>(spi: S, cli: &Cli) -> Result<(), Box<dyn StdError>>
+where
+    S: SpiDevice,
+    S::Error: 'static,
+{
+    let mut dev = Mcp23s08::new(spi, cli.addr)?;
+    let mut out = io::stdout().lock();
+    match cli.command {
+        Command::Dump => write!(out, "{}", dev.dump()?)?,
+        Command::Read { pin: None } => {
+            let levels = dev.read_port()?;
+            writeln!(out, "{levels:#04x} {levels:#010b}")?;
+        }
+        Command::Read { pin: Some(pin) } => writeln!(out, "{}", u8::from(dev.read_pin(pin)?))?,
+        Command::Write { value, mask } => dev.modify_port(mask, value)?,
+        Command::SetDir { inputs, mask } => dev.modify_port_direction(mask, inputs)?,
+        Command::Pullup { mask } => dev.set_port_pullups(mask)?,
+        Command::Watch {
+            interval_ms,
+            samples,
+        } => {
+            let start = Instant::now();
+            let mut last = None;
+            for _ in 0..samples.unwrap_or(u64::MAX) {
+                let levels = dev.read_port()?;
+                if last != Some(levels) {
+                    write!(
+                        out,
+                        "{:>8} ms  {levels:#04x} {levels:#010b}",
+                        start.elapsed().as_millis()
+                    )?;
+                    let changed = last.map_or(0, |last| last ^ levels);
+                    for pin in Pin::ALL.into_iter().filter(|pin| changed & pin.mask() != 0) {
+                        write!(out, "  {pin:?}={}", u8::from(levels & pin.mask() != 0))?;
+                    }
+                    writeln!(out)?;
+                    out.flush()?;
+                    last = Some(levels);
+                }
+                thread::sleep(Duration::from_millis(interval_ms));
+            }
+        }
+        Command::Pulse { pin, ms } => {
+            let level = dev.output_latch() & pin.mask() == 0;
+            dev.set_pin_direction(pin, false)?;
+            dev.write_pin(pin, level)?;
+            thread::sleep(Duration::from_millis(ms));
+            dev.write_pin(pin, !level)?;
+            let level = if level { "high" } else { "low" };
+            writeln!(out, "{pin:?} {level} for {ms} ms")?;
+        }
+    }
+    Ok(())
+}
+
+/// `0x..`, `0b..` or decimal.
+fn parse_byte(s: &str) -> Result<u8, String> {
+    let parsed = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u8::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        u8::from_str_radix(&bin.replace('_', ""), 2)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|e| format!("`{s}` is not a byte: {e}"))
+}
+
+/// `0`..`7`, optionally written `P3` or `GP3`.
+fn parse_pin(s: &str) -> Result<Pin, String> {
+    let upper = s.to_ascii_uppercase();
+    let index = upper
+        .strip_prefix("GP")
+        .or_else(|| upper.strip_prefix('P'))
+        .unwrap_or(&upper);
+    index
+        .parse::<u8>()
+        .ok()
+        .and_then(|i| Pin::try_from(i).ok())
+        .ok_or_else(|| format!("`{s}` is not a pin 0..=7"))
+}
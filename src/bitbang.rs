@@ -0,0 +1,396 @@
+//! Bit-banged SPI and I²C masters on expander pins.
+//!
+//! For slow peripherals (DACs, digital potentiometers, EEPROMs) wired only
+//! to the expander. [`BitbangSpi`] implements `embedded_hal::spi::SpiBus`
+//! and [`BitbangI2c`] implements `embedded_hal::i2c::I2c`, both borrowing
+//! an [`IoExpander`] such as the [`Mcp23s08`](crate::mcp23s08::Mcp23s08).
+//!
+//! SPI changes data and clock together: each bit is two `OLAT` writes (data
+//! with the leading/trailing clock edge, then the other edge) plus one
+//! `GPIO` read when a MISO pin is configured. I²C lines are open-drain: the
+//! latch bits stay `0` and a line is pulled low by making the pin an output
+//! and released by making it an input, so every I²C edge is one `IODIR`
+//! write. Clock stretching is not supported.
+//!
+//! Every transition is at least one SPI transaction to the expander, so the
+//! bit rate is set by the expander's SPI clock. Use `with_delay` to slow it
+//! down further.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{NoAcknowledgeSource, Operation as I2cOperation, SevenBitAddress};
+use embedded_hal::spi::{Mode, Phase, Polarity};
+
+use crate::expander::IoExpander;
+use crate::mcp23s08::{NoDelay, Pin};
+
+/// SPI master on three expander pins. Chip-select is up to the caller.
+pub struct BitbangSpi<'a, X, T = NoDelay> {
+    dev: &'a mut X,
+    sck: Pin,
+    mosi: Pin,
+    miso: Option<Pin>,
+    mode: Mode,
+    delay: T,
+    half_period_us: u32,
+}
+
+impl<'a, X: IoExpander> BitbangSpi<'a, X> {
+    /// Makes `sck` and `mosi` outputs (clock at its idle level) and `miso`
+    /// an input. Without `miso` every bit reads as `0`.
+    pub fn new(
+        dev: &'a mut X,
+        sck: Pin,
+        mosi: Pin,
+        miso: Option<Pin>,
+        mode: Mode,
+    ) -> Result<Self, X::Error> {
+        let idle = if mode.polarity == Polarity::IdleHigh {
+            sck.mask()
+        } else {
+            0
+        };
+        dev.modify_port(sck | mosi, idle)?;
+        dev.modify_port_direction(sck | mosi, 0x00)?;
+        if let Some(miso) = miso {
+            dev.set_pin_direction(miso, true)?;
+        }
+        Ok(Self {
+            dev,
+            sck,
+            mosi,
+            miso,
+            mode,
+            delay: NoDelay,
+            half_period_us: 0,
+        })
+    }
+}
+
+impl<'a, X, T> BitbangSpi<'a, X, T>
+where
+    X: IoExpander,
+    T: DelayNs,
+{
+    /// Waits `half_period_us` after every clock edge.
+    pub fn with_delay<T2: DelayNs>(self, delay: T2, half_period_us: u32) -> BitbangSpi<'a, X, T2> {
+        BitbangSpi {
+            dev: self.dev,
+            sck: self.sck,
+            mosi: self.mosi,
+            miso: self.miso,
+            mode: self.mode,
+            delay,
+            half_period_us,
+        }
+    }
+
+    fn sck_level(&self, active: bool) -> u8 {
+        let idle_high = self.mode.polarity == Polarity::IdleHigh;
+        if active != idle_high {
+            self.sck.mask()
+        } else {
+            0
+        }
+    }
+
+    fn wait(&mut self) {
+        if self.half_period_us > 0 {
+            self.delay.delay_us(self.half_period_us);
+        }
+    }
+
+    /// Shifts one byte out MSB first and returns the byte shifted in.
+    fn transfer_byte(&mut self, out: u8) -> Result<u8, X::Error> {
+        let pins = self.sck | self.mosi;
+        // Mode 0/2: data goes out with the trailing edge of the previous bit
+        // and is sampled on the leading edge. Mode 1/3: data goes out with
+        // the leading edge and is sampled on the trailing edge.
+        let first_edge_active = self.mode.phase == Phase::CaptureOnSecondTransition;
+        let mut input = 0;
+        for bit in (0..8).rev() {
+            let data = if out & (1 << bit) != 0 {
+                self.mosi.mask()
+            } else {
+                0
+            };
+            self.dev
+                .modify_port(pins, data | self.sck_level(first_edge_active))?;
+            self.wait();
+            self.dev
+                .modify_port(self.sck, self.sck_level(!first_edge_active))?;
+            let sample = match self.miso {
+                Some(miso) => self.dev.read_pin(miso)?,
+                None => false,
+            };
+            self.wait();
+            input = (input << 1) | u8::from(sample);
+        }
+        Ok(input)
+    }
+
+    /// Puts the clock back to idle after a mode 0/2 transfer.
+    fn finish(&mut self) -> Result<(), X::Error> {
+        if self.mode.phase == Phase::CaptureOnFirstTransition {
+            self.dev.modify_port(self.sck, self.sck_level(false))?;
+        }
+        Ok(())
+    }
+}
+
+impl<X, T> embedded_hal::spi::ErrorType for BitbangSpi<'_, X, T>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::spi::Error,
+{
+    type Error = X::Error;
+}
+
+impl<X, T> embedded_hal::spi::SpiBus for BitbangSpi<'_, X, T>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::spi::Error,
+    T: DelayNs,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.transfer_byte(0x00)?;
+        }
+        self.finish()
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for &word in words {
+            self.transfer_byte(word)?;
+        }
+        self.finish()
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let input = self.transfer_byte(write.get(i).copied().unwrap_or(0x00))?;
+            if let Some(r) = read.get_mut(i) {
+                *r = input;
+            }
+        }
+        self.finish()
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.transfer_byte(*word)?;
+        }
+        self.finish()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Error of [`BitbangI2c`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2cError<E> {
+    /// Talking to the expander failed.
+    Expander(E),
+    /// The addressed device did not acknowledge.
+    NoAcknowledge(NoAcknowledgeSource),
+}
+
+impl<E> From<E> for I2cError<E> {
+    fn from(e: E) -> Self {
+        I2cError::Expander(e)
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for I2cError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            I2cError::Expander(e) => write!(f, "expander error: {e}"),
+            I2cError::NoAcknowledge(source) => write!(f, "no acknowledge ({source:?})"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for I2cError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            I2cError::Expander(e) => Some(e),
+            I2cError::NoAcknowledge(_) => None,
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> embedded_hal::i2c::Error for I2cError<E> {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        match self {
+            I2cError::Expander(_) => embedded_hal::i2c::ErrorKind::Other,
+            I2cError::NoAcknowledge(source) => embedded_hal::i2c::ErrorKind::NoAcknowledge(*source),
+        }
+    }
+}
+
+/// I²C master on two expander pins with external pull-ups.
+pub struct BitbangI2c<'a, X, T = NoDelay> {
+    dev: &'a mut X,
+    scl: Pin,
+    sda: Pin,
+    delay: T,
+    half_period_us: u32,
+}
+
+impl<'a, X: IoExpander> BitbangI2c<'a, X> {
+    /// Clears the latch bits of `scl`/`sda` and releases both lines.
+    pub fn new(dev: &'a mut X, scl: Pin, sda: Pin) -> Result<Self, X::Error> {
+        dev.modify_port_direction(scl | sda, 0xFF)?;
+        dev.modify_port(scl | sda, 0x00)?;
+        Ok(Self {
+            dev,
+            scl,
+            sda,
+            delay: NoDelay,
+            half_period_us: 0,
+        })
+    }
+}
+
+impl<'a, X, T> BitbangI2c<'a, X, T>
+where
+    X: IoExpander,
+    T: DelayNs,
+{
+    /// Waits `half_period_us` after every clock edge.
+    pub fn with_delay<T2: DelayNs>(self, delay: T2, half_period_us: u32) -> BitbangI2c<'a, X, T2> {
+        BitbangI2c {
+            dev: self.dev,
+            scl: self.scl,
+            sda: self.sda,
+            delay,
+            half_period_us,
+        }
+    }
+
+    fn wait(&mut self) {
+        if self.half_period_us > 0 {
+            self.delay.delay_us(self.half_period_us);
+        }
+    }
+
+    /// Releases (`true`) or pulls low (`false`) one line.
+    fn line(&mut self, pin: Pin, release: bool) -> Result<(), X::Error> {
+        self.dev
+            .modify_port_direction(pin, if release { 0xFF } else { 0x00 })
+    }
+
+    fn start(&mut self) -> Result<(), X::Error> {
+        // Also a repeated start: SCL is low after a byte.
+        self.line(self.sda, true)?;
+        self.line(self.scl, true)?;
+        self.wait();
+        self.line(self.sda, false)?;
+        self.wait();
+        self.line(self.scl, false)
+    }
+
+    fn stop(&mut self) -> Result<(), X::Error> {
+        self.line(self.sda, false)?;
+        self.line(self.scl, true)?;
+        self.wait();
+        self.line(self.sda, true)?;
+        self.wait();
+        Ok(())
+    }
+
+    fn clock_bit_out(&mut self, release: bool) -> Result<(), X::Error> {
+        self.line(self.sda, release)?;
+        self.line(self.scl, true)?;
+        self.wait();
+        self.line(self.scl, false)?;
+        self.wait();
+        Ok(())
+    }
+
+    fn clock_bit_in(&mut self) -> Result<bool, X::Error> {
+        self.line(self.scl, true)?;
+        self.wait();
+        let level = self.dev.read_pin(self.sda)?;
+        self.line(self.scl, false)?;
+        self.wait();
+        Ok(level)
+    }
+
+    /// Sends a byte and returns whether it was acknowledged.
+    fn write_byte(&mut self, byte: u8) -> Result<bool, X::Error> {
+        for bit in (0..8).rev() {
+            self.clock_bit_out(byte & (1 << bit) != 0)?;
+        }
+        self.line(self.sda, true)?;
+        Ok(!self.clock_bit_in()?)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, X::Error> {
+        self.line(self.sda, true)?;
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | u8::from(self.clock_bit_in()?);
+        }
+        self.clock_bit_out(!ack)?;
+        Ok(byte)
+    }
+
+    fn run(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [I2cOperation<'_>],
+    ) -> Result<(), I2cError<X::Error>> {
+        let mut reading = None;
+        for i in 0..operations.len() {
+            let is_read = matches!(operations[i], I2cOperation::Read(_));
+            if reading != Some(is_read) {
+                self.start()?;
+                if !self.write_byte((address << 1) | u8::from(is_read))? {
+                    return Err(I2cError::NoAcknowledge(NoAcknowledgeSource::Address));
+                }
+                reading = Some(is_read);
+            }
+            let next_is_read = matches!(operations.get(i + 1), Some(I2cOperation::Read(_)));
+            match &mut operations[i] {
+                I2cOperation::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        if !self.write_byte(byte)? {
+                            return Err(I2cError::NoAcknowledge(NoAcknowledgeSource::Data));
+                        }
+                    }
+                }
+                I2cOperation::Read(buf) => {
+                    let len = buf.len();
+                    for (j, byte) in buf.iter_mut().enumerate() {
+                        // NACK the last byte before a restart or stop.
+                        *byte = self.read_byte(j + 1 < len || next_is_read)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<X: IoExpander, T> embedded_hal::i2c::ErrorType for BitbangI2c<'_, X, T> {
+    type Error = I2cError<X::Error>;
+}
+
+impl<X, T> embedded_hal::i2c::I2c for BitbangI2c<'_, X, T>
+where
+    X: IoExpander,
+    T: DelayNs,
+{
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [I2cOperation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.run(address, operations);
+        // Always leave the bus idle, even after a NACK.
+        let stop = self.stop().map_err(I2cError::from);
+        result.and(stop)
+    }
+}
@@ -0,0 +1,609 @@
+//! Text command console for bring-up over a serial port.
+//!
+//! [`Console`] reads lines from any `embedded-io` port (a UART, USB CDC,
+//! an RTT channel, ...), lets the user edit them as they are typed and
+//! runs expander commands against a [`Mcp23s08`] with
+//! [`run`](Console::run) or a [`Mcp23s08async`] with
+//! [`run_async`](Console::run_async):
+//!
+//! ```text
+//! > io dir 0x0f
+//! ok
+//! > io set 2 1
+//! ok
+//! > io read
+//! 0xe4 0b11100100
+//! > io set 9 1
+//! error: no pin 9 (pins are 0..=7)
+//! > io pulse 2 250
+//! ok
+//! ```
+//!
+//! | Command               | Does                                            |
+//! |-----------------------|-------------------------------------------------|
+//! | `io read [pin]`       | read the port, or one pin as `0`/`1`            |
+//! | `io set <pin> <0\|1>` | write an output latch bit                       |
+//! | `io dir <mask>`       | set all directions, bit set = input             |
+//! | `io dump`             | print every register, decoded                   |
+//! | `io watch [ms]`       | print input changes until a key is pressed      |
+//! | `io pulse <pin> <ms>` | make the pin an output and invert it for `<ms>` |
+//! | `help`                | list the commands                               |
+//!
+//! Numbers are decimal, `0x..` or `0b..`; pins are `0..=7` or `P0..P7`.
+//! Backspace/DEL erases a character, Ctrl-U clears the line, Ctrl-C drops
+//! it, and terminal escape sequences (arrow keys) are ignored. Command
+//! errors are printed with the `Display` of [`ParseError`] or the driver's
+//! [`Error`] and the console carries on; only an error from the port
+//! itself ends `run`.
+//!
+//! Parsing is available on its own for other front ends:
+//!
+//! ```
+//! use mcp23s08_io::console::{Command, ParseError};
+//! use mcp23s08_io::mcp23s08::{InvalidPin, Pin};
+//!
+//! assert_eq!(Command::parse("io set 4 1"), Ok(Some(Command::Set(Pin::P4, true))));
+//! assert_eq!(Command::parse("io dir 0x0f"), Ok(Some(Command::Dir(0x0f))));
+//! assert_eq!(Command::parse("   "), Ok(None));
+//! assert_eq!(Command::parse("io read 9"), Err(ParseError::BadPin(InvalidPin(9))));
+//! ```
+
+use core::fmt::{self, Debug, Display, Write as _};
+
+use embedded_hal::delay::DelayNs;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::expander::{AsyncIoExpander, IoExpander};
+use crate::mcp23s08::{Error, InvalidPin, Mcp23s08, Pin, Reg};
+use crate::mcp23s08async::Mcp23s08async;
+use crate::transport::{AsyncTransport, Transport};
+
+const PROMPT: &str = "> ";
+
+const HELP: [&str; 7] = [
+    "io read [pin]        read the port, or one pin",
+    "io set <pin> <0|1>   write an output",
+    "io dir <mask>        set directions, 1 = input",
+    "io dump              print all registers",
+    "io watch [ms]        print input changes until a key is pressed",
+    "io pulse <pin> <ms>  invert an output for <ms>",
+    "help                 this text",
+];
+
+/// A parsed console line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// `help`, `io help` or `?`.
+    Help,
+    /// `io read [pin]`.
+    Read(Option<Pin>),
+    /// `io set <pin> <0|1>`.
+    Set(Pin, bool),
+    /// `io dir <mask>`; bit set = input.
+    Dir(u8),
+    /// `io dump`.
+    Dump,
+    /// `io watch [ms]`, with the poll period in milliseconds.
+    Watch(u32),
+    /// `io pulse <pin> <ms>`.
+    Pulse(Pin, u32),
+}
+
+/// Why a console line could not be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    /// The first words are not a known command.
+    UnknownCommand,
+    /// A required argument is missing; holds its name.
+    MissingArgument(&'static str),
+    /// More arguments than the command takes.
+    TooManyArguments,
+    /// An argument is not a number, or is out of range.
+    BadNumber,
+    /// A pin number outside `0..=7`.
+    BadPin(InvalidPin),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownCommand => f.write_str("unknown command, try `help`"),
+            ParseError::MissingArgument(name) => write!(f, "missing <{name}>"),
+            ParseError::TooManyArguments => f.write_str("too many arguments"),
+            ParseError::BadNumber => f.write_str("bad number"),
+            ParseError::BadPin(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl Command {
+    /// Poll period of `io watch` without an argument.
+    pub const WATCH_MS: u32 = 20;
+
+    /// Parses one line. Blank lines give `Ok(None)`.
+    pub fn parse(line: &str) -> Result<Option<Command>, ParseError> {
+        let mut words = line.split_ascii_whitespace();
+        let command = match words.next() {
+            None => return Ok(None),
+            Some("help" | "?") => Command::Help,
+            Some("io") => match words.next() {
+                None | Some("help") => Command::Help,
+                Some("read") => Command::Read(words.next().map(parse_pin).transpose()?),
+                Some("set") => {
+                    let pin = parse_pin(arg(&mut words, "pin")?)?;
+                    let high = match arg(&mut words, "0|1")? {
+                        "0" | "low" => false,
+                        "1" | "high" => true,
+                        _ => return Err(ParseError::BadNumber),
+                    };
+                    Command::Set(pin, high)
+                }
+                Some("dir") => {
+                    let mask = parse_number(arg(&mut words, "mask")?)?;
+                    Command::Dir(u8::try_from(mask).map_err(|_| ParseError::BadNumber)?)
+                }
+                Some("dump") => Command::Dump,
+                Some("watch") => match words.next() {
+                    Some(ms) => Command::Watch(parse_number(ms)?),
+                    None => Command::Watch(Self::WATCH_MS),
+                },
+                Some("pulse") => {
+                    let pin = parse_pin(arg(&mut words, "pin")?)?;
+                    Command::Pulse(pin, parse_number(arg(&mut words, "ms")?)?)
+                }
+                Some(_) => return Err(ParseError::UnknownCommand),
+            },
+            Some(_) => return Err(ParseError::UnknownCommand),
+        };
+        if words.next().is_some() {
+            return Err(ParseError::TooManyArguments);
+        }
+        Ok(Some(command))
+    }
+}
+
+fn arg<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+    name: &'static str,
+) -> Result<&'a str, ParseError> {
+    words.next().ok_or(ParseError::MissingArgument(name))
+}
+
+fn parse_number(word: &str) -> Result<u32, ParseError> {
+    let (digits, radix) = if let Some(hex) = word.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = word.strip_prefix("0b") {
+        (bin, 2)
+    } else {
+        (word, 10)
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(ParseError::BadNumber);
+    }
+    u32::from_str_radix(digits, radix).map_err(|_| ParseError::BadNumber)
+}
+
+fn parse_pin(word: &str) -> Result<Pin, ParseError> {
+    let digits = word
+        .strip_prefix('P')
+        .or_else(|| word.strip_prefix('p'))
+        .unwrap_or(word);
+    let index = u8::try_from(parse_number(digits)?).map_err(|_| ParseError::BadNumber)?;
+    Pin::try_from(index).map_err(ParseError::BadPin)
+}
+
+/// Where the line editor is inside the input stream.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// Just ended a line with CR; a following LF belongs to it.
+    AfterCr,
+    /// After ESC.
+    Escape,
+    /// Inside a CSI/SS3 sequence, up to its final byte.
+    Sequence,
+}
+
+/// Line editor and command interpreter; `N` is the longest line in bytes.
+pub struct Console<const N: usize = 64> {
+    line: [u8; N],
+    len: usize,
+    state: State,
+}
+
+impl<const N: usize> Default for Console<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Console<N> {
+    pub const fn new() -> Self {
+        Console {
+            line: [0; N],
+            len: 0,
+            state: State::Text,
+        }
+    }
+
+    /// Runs the console until the port reports end of input (a read of 0
+    /// bytes) or fails.
+    ///
+    /// `delay` times `io pulse` and `io watch`. `io watch` polls
+    /// `port.read_ready()` to notice the key that stops it.
+    pub fn run<P, B, D, E, T>(
+        &mut self,
+        port: &mut P,
+        dev: &mut Mcp23s08<B, D>,
+        delay: &mut T,
+    ) -> Result<(), P::Error>
+    where
+        P: Read + ReadReady + Write,
+        B: Transport<Error = E>,
+        D: DelayNs,
+        E: Debug,
+        T: DelayNs,
+    {
+        port.write_all(PROMPT.as_bytes())?;
+        port.flush()?;
+        loop {
+            let mut byte = [0];
+            if port.read(&mut byte)? == 0 {
+                return Ok(());
+            }
+            let mut echo = Out::new();
+            let line = self.feed(byte[0], &mut echo);
+            port.write_all(echo.bytes())?;
+            if let Some(line) = line {
+                match line {
+                    Ok(Some(command)) => execute(command, port, dev, delay)?,
+                    Ok(None) => {}
+                    Err(e) => say(port, format_args!("error: {e}"))?,
+                }
+                port.write_all(PROMPT.as_bytes())?;
+            }
+            port.flush()?;
+        }
+    }
+
+    /// See [`run`](Self::run).
+    pub async fn run_async<P, B, D, E, T>(
+        &mut self,
+        port: &mut P,
+        dev: &mut Mcp23s08async<B, D>,
+        delay: &mut T,
+    ) -> Result<(), P::Error>
+    where
+        P: embedded_io_async::Read + ReadReady + embedded_io_async::Write,
+        B: AsyncTransport<Error = E>,
+        D: embedded_hal_async::delay::DelayNs,
+        E: Debug,
+        T: embedded_hal_async::delay::DelayNs,
+    {
+        port.write_all(PROMPT.as_bytes()).await?;
+        port.flush().await?;
+        loop {
+            let mut byte = [0];
+            if port.read(&mut byte).await? == 0 {
+                return Ok(());
+            }
+            let mut echo = Out::new();
+            let line = self.feed(byte[0], &mut echo);
+            port.write_all(echo.bytes()).await?;
+            if let Some(line) = line {
+                match line {
+                    Ok(Some(command)) => execute_async(command, port, dev, delay).await?,
+                    Ok(None) => {}
+                    Err(e) => say_async(port, format_args!("error: {e}")).await?,
+                }
+                port.write_all(PROMPT.as_bytes()).await?;
+            }
+            port.flush().await?;
+        }
+    }
+
+    /// Applies one received byte to the line being edited. The echo goes to
+    /// `echo`; a finished line is parsed and returned.
+    fn feed(&mut self, byte: u8, echo: &mut Out) -> Option<Result<Option<Command>, ParseError>> {
+        let state = core::mem::replace(&mut self.state, State::Text);
+        match (state, byte) {
+            (State::Escape, b'[' | b'O') => self.state = State::Sequence,
+            (State::Escape, _) | (State::Sequence, 0x40..=0x7e) => {}
+            (State::Sequence, _) => self.state = State::Sequence,
+            (State::AfterCr, b'\n') => {}
+            (_, b'\r' | b'\n') => {
+                if byte == b'\r' {
+                    self.state = State::AfterCr;
+                }
+                echo.push("\r\n");
+                // Only printable ASCII gets into the buffer.
+                let line = core::str::from_utf8(&self.line[..self.len]).unwrap_or_default();
+                let parsed = Command::parse(line);
+                self.len = 0;
+                return Some(parsed);
+            }
+            (_, 0x1b) => self.state = State::Escape,
+            (_, 0x08 | 0x7f) if self.len > 0 => {
+                self.len -= 1;
+                echo.push("\x08 \x08");
+            }
+            // Ctrl-U: back to the start of the line and clear it.
+            (_, 0x15) => {
+                self.len = 0;
+                echo.push("\r\x1b[K");
+                echo.push(PROMPT);
+            }
+            // Ctrl-C
+            (_, 0x03) => {
+                self.len = 0;
+                echo.push("^C\r\n");
+                echo.push(PROMPT);
+            }
+            (_, 0x20..=0x7e) => {
+                if self.len < N {
+                    self.line[self.len] = byte;
+                    self.len += 1;
+                    echo.push_byte(byte);
+                } else {
+                    echo.push("\x07");
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Output is formatted into a line-sized buffer first, so the async console
+/// can hand whole lines to the port. Longer output is cut off.
+struct Out {
+    buf: [u8; 80],
+    len: usize,
+}
+
+impl Out {
+    fn new() -> Self {
+        Out {
+            buf: [0; 80],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, s: &str) {
+        let _ = self.write_str(s);
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.len < self.buf.len() {
+            self.buf[self.len] = byte;
+            self.len += 1;
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl fmt::Write for Out {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            self.push_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Writes one line of output.
+fn say<P: Write>(port: &mut P, args: fmt::Arguments<'_>) -> Result<(), P::Error> {
+    let mut out = Out::new();
+    let _ = out.write_fmt(args);
+    port.write_all(out.bytes())?;
+    port.write_all(b"\r\n")
+}
+
+async fn say_async<P: embedded_io_async::Write>(
+    port: &mut P,
+    args: fmt::Arguments<'_>,
+) -> Result<(), P::Error> {
+    let mut out = Out::new();
+    let _ = out.write_fmt(args);
+    port.write_all(out.bytes()).await?;
+    port.write_all(b"\r\n").await
+}
+
+/// Port levels as printed by `io read` and `io watch`.
+struct Levels(u8);
+
+impl Display for Levels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#04x} {:#010b}", self.0, self.0)
+    }
+}
+
+/// One `io watch` line: the levels, then every pin that changed.
+struct Change {
+    levels: u8,
+    changed: u8,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Levels(self.levels))?;
+        if self.changed != 0 {
+            f.write_str(" ")?;
+        }
+        for pin in Pin::ALL {
+            if self.changed & pin.mask() != 0 {
+                write!(f, " {pin:?}={}", u8::from(self.levels & pin.mask() != 0))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reply line for a command that either printed `value` or failed.
+fn reply<T: Display, E: Debug>(result: Result<T, Error<E>>, out: &mut Out) {
+    match result {
+        Ok(value) => {
+            let _ = write!(out, "{value}");
+        }
+        Err(e) => fail(e, out),
+    }
+}
+
+fn fail<E: Debug>(e: Error<E>, out: &mut Out) {
+    let _ = write!(out, "error: {e}");
+}
+
+fn execute<P, B, D, E, T>(
+    command: Command,
+    port: &mut P,
+    dev: &mut Mcp23s08<B, D>,
+    delay: &mut T,
+) -> Result<(), P::Error>
+where
+    P: Read + ReadReady + Write,
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+    T: DelayNs,
+{
+    let mut out = Out::new();
+    match command {
+        Command::Help => {
+            for line in HELP {
+                say(port, format_args!("{line}"))?;
+            }
+            return Ok(());
+        }
+        Command::Read(None) => reply(dev.read_port().map(Levels), &mut out),
+        Command::Read(Some(pin)) => reply(dev.read_pin(pin).map(u8::from), &mut out),
+        Command::Set(pin, high) => reply(dev.write_pin(pin, high).map(|()| "ok"), &mut out),
+        Command::Dir(inputs) => reply(dev.set_port_direction(inputs).map(|()| "ok"), &mut out),
+        Command::Dump => match dev.dump() {
+            Ok(dump) => {
+                for reg in Reg::ALL {
+                    say(port, format_args!("{}", dump.line(reg)))?;
+                }
+                return Ok(());
+            }
+            Err(e) => fail(e, &mut out),
+        },
+        Command::Watch(period_ms) => {
+            say(port, format_args!("watching, press any key to stop"))?;
+            let mut last = None;
+            loop {
+                match dev.read_port() {
+                    Ok(levels) if last != Some(levels) => {
+                        let changed = last.map_or(0, |last| last ^ levels);
+                        say(port, format_args!("{}", Change { levels, changed }))?;
+                        last = Some(levels);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        fail(e, &mut out);
+                        break;
+                    }
+                }
+                if port.read_ready()? {
+                    port.read(&mut [0])?;
+                    return Ok(());
+                }
+                delay.delay_ms(period_ms);
+            }
+        }
+        Command::Pulse(pin, ms) => {
+            let result = (|| {
+                let high = dev.output_latch() & pin.mask() == 0;
+                dev.set_pin_direction(pin, false)?;
+                dev.write_pin(pin, high)?;
+                delay.delay_ms(ms);
+                dev.write_pin(pin, !high)
+            })();
+            reply(result.map(|()| "ok"), &mut out);
+        }
+    }
+    port.write_all(out.bytes())?;
+    port.write_all(b"\r\n")
+}
+
+async fn execute_async<P, B, D, E, T>(
+    command: Command,
+    port: &mut P,
+    dev: &mut Mcp23s08async<B, D>,
+    delay: &mut T,
+) -> Result<(), P::Error>
+where
+    P: embedded_io_async::Read + ReadReady + embedded_io_async::Write,
+    B: AsyncTransport<Error = E>,
+    D: embedded_hal_async::delay::DelayNs,
+    E: Debug,
+    T: embedded_hal_async::delay::DelayNs,
+{
+    let mut out = Out::new();
+    match command {
+        Command::Help => {
+            for line in HELP {
+                say_async(port, format_args!("{line}")).await?;
+            }
+            return Ok(());
+        }
+        Command::Read(None) => reply(dev.read_port().await.map(Levels), &mut out),
+        Command::Read(Some(pin)) => reply(dev.read_pin(pin).await.map(u8::from), &mut out),
+        Command::Set(pin, high) => reply(dev.write_pin(pin, high).await.map(|()| "ok"), &mut out),
+        Command::Dir(inputs) => reply(
+            dev.set_port_direction(inputs).await.map(|()| "ok"),
+            &mut out,
+        ),
+        Command::Dump => match dev.dump().await {
+            Ok(dump) => {
+                for reg in Reg::ALL {
+                    say_async(port, format_args!("{}", dump.line(reg))).await?;
+                }
+                return Ok(());
+            }
+            Err(e) => fail(e, &mut out),
+        },
+        Command::Watch(period_ms) => {
+            say_async(port, format_args!("watching, press any key to stop")).await?;
+            let mut last = None;
+            loop {
+                match dev.read_port().await {
+                    Ok(levels) if last != Some(levels) => {
+                        let changed = last.map_or(0, |last| last ^ levels);
+                        say_async(port, format_args!("{}", Change { levels, changed })).await?;
+                        last = Some(levels);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        fail(e, &mut out);
+                        break;
+                    }
+                }
+                if port.read_ready()? {
+                    port.read(&mut [0]).await?;
+                    return Ok(());
+                }
+                delay.delay_ms(period_ms).await;
+            }
+        }
+        Command::Pulse(pin, ms) => {
+            let result = async {
+                let high = dev.output_latch() & pin.mask() == 0;
+                dev.set_pin_direction(pin, false).await?;
+                dev.write_pin(pin, high).await?;
+                delay.delay_ms(ms).await;
+                dev.write_pin(pin, !high).await
+            }
+            .await;
+            reply(result.map(|()| "ok"), &mut out);
+        }
+    }
+    port.write_all(out.bytes()).await?;
+    port.write_all(b"\r\n").await
+}
@@ -0,0 +1,398 @@
+//! Chip-agnostic port expander interface.
+//!
+//! [`IoExpander`] describes an expander with one port of up to eight pins:
+//! an input register, an output latch, direction and pull-up registers and
+//! interrupt status. [`AsyncIoExpander`] is the async counterpart.
+//! [`Mcp23s08`](crate::mcp23s08::Mcp23s08) and
+//! [`Mcp23s08async`](crate::mcp23s08async::Mcp23s08async) implement them,
+//! and so does each port of the 16-bit drivers through
+//! [`Mcp23s17::port`](crate::mcp23s17::Mcp23s17::port). The pin handles and
+//! the higher-level modules (bit-banged buses, PWM, schedulers, shift
+//! registers, steppers) only use these traits, so they run on any other
+//! expander or on a fake in tests.
+//!
+//! ```
+//! use embedded_hal::digital::OutputPin;
+//! use mcp23s08_io::expander::{ExpanderPin, IoExpander};
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Pin};
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! /// Lights an LED on any expander.
+//! fn led_on<X: IoExpander>(dev: &mut X, pin: Pin) -> Result<(), X::Error>
+//! where
+//!     X::Error: embedded_hal::digital::Error,
+//! {
+//!     dev.set_pin_direction(pin, false)?;
+//!     ExpanderPin::new(dev, pin).set_high()
+//! }
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! led_on(&mut dev, Pin::P2).unwrap();
+//! assert_eq!(dev.output_latch(), 0x04);
+//! ```
+
+use core::fmt::Debug;
+
+use crate::mcp23s08::{Pin, PinSet};
+
+/// Blocking port expander.
+///
+/// Port values are bit masks with bit `n` for pin `n`; bits at or above
+/// [`WIDTH`](Self::WIDTH) are ignored. A `1` in a direction mask makes the
+/// pin an input, as in the MCP23S08's `IODIR`.
+pub trait IoExpander {
+    type Error: Debug;
+
+    /// Number of pins in the port, at most 8.
+    const WIDTH: u8;
+
+    /// Levels on the pins.
+    fn read_port(&mut self) -> Result<u8, Self::Error>;
+
+    /// Output latch as last written, without bus traffic.
+    fn output_latch(&self) -> u8;
+
+    /// Replaces the whole output latch.
+    fn write_olat(&mut self, value: u8) -> Result<(), Self::Error>;
+
+    /// Sets the latch bits in `mask` to the matching bits of `value` and
+    /// leaves the others as they are.
+    fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Self::Error>;
+
+    /// Makes the pins in `inputs` inputs and all others outputs.
+    fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Self::Error>;
+
+    /// Sets the direction of the pins in `mask` (`1` in `inputs` = input)
+    /// and leaves the others as they are.
+    fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Self::Error>;
+
+    /// Enables the pull-ups of the pins in `mask` and disables all others.
+    fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Self::Error>;
+
+    fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Self::Error>;
+
+    /// Pins with a pending interrupt.
+    fn read_interrupt_flags(&mut self) -> Result<u8, Self::Error>;
+
+    /// Port levels captured when the interrupt fired.
+    fn read_interrupt_capture(&mut self) -> Result<u8, Self::Error>;
+
+    fn read_pin(&mut self, pin: Pin) -> Result<bool, Self::Error> {
+        Ok(self.read_port()? & pin.mask() != 0)
+    }
+
+    fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Self::Error> {
+        self.modify_port(pin, if high { 0xFF } else { 0x00 })
+    }
+
+    fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Self::Error> {
+        self.modify_port_direction(pin, if input { 0xFF } else { 0x00 })
+    }
+}
+
+impl<X: IoExpander + ?Sized> IoExpander for &mut X {
+    type Error = X::Error;
+
+    const WIDTH: u8 = X::WIDTH;
+
+    fn read_port(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_port()
+    }
+
+    fn output_latch(&self) -> u8 {
+        (**self).output_latch()
+    }
+
+    fn write_olat(&mut self, value: u8) -> Result<(), Self::Error> {
+        (**self).write_olat(value)
+    }
+
+    fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Self::Error> {
+        (**self).modify_port(mask, value)
+    }
+
+    fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Self::Error> {
+        (**self).set_port_direction(inputs)
+    }
+
+    fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Self::Error> {
+        (**self).modify_port_direction(mask, inputs)
+    }
+
+    fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Self::Error> {
+        (**self).set_port_pullups(mask)
+    }
+
+    fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Self::Error> {
+        (**self).set_pin_pullup(pin, enable)
+    }
+
+    fn read_interrupt_flags(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_interrupt_flags()
+    }
+
+    fn read_interrupt_capture(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_interrupt_capture()
+    }
+
+    fn read_pin(&mut self, pin: Pin) -> Result<bool, Self::Error> {
+        (**self).read_pin(pin)
+    }
+
+    fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Self::Error> {
+        (**self).write_pin(pin, high)
+    }
+
+    fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Self::Error> {
+        (**self).set_pin_direction(pin, input)
+    }
+}
+
+/// Async counterpart of [`IoExpander`].
+#[allow(async_fn_in_trait)]
+pub trait AsyncIoExpander {
+    type Error: Debug;
+
+    /// See [`IoExpander::WIDTH`].
+    const WIDTH: u8;
+
+    /// See [`IoExpander::read_port`].
+    async fn read_port(&mut self) -> Result<u8, Self::Error>;
+
+    /// See [`IoExpander::output_latch`].
+    fn output_latch(&self) -> u8;
+
+    /// See [`IoExpander::write_olat`].
+    async fn write_olat(&mut self, value: u8) -> Result<(), Self::Error>;
+
+    /// See [`IoExpander::modify_port`].
+    async fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Self::Error>;
+
+    /// See [`IoExpander::set_port_direction`].
+    async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Self::Error>;
+
+    /// See [`IoExpander::modify_port_direction`].
+    async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Self::Error>;
+
+    /// See [`IoExpander::set_port_pullups`].
+    async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Self::Error>;
+
+    async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Self::Error>;
+
+    /// See [`IoExpander::read_interrupt_flags`].
+    async fn read_interrupt_flags(&mut self) -> Result<u8, Self::Error>;
+
+    /// See [`IoExpander::read_interrupt_capture`].
+    async fn read_interrupt_capture(&mut self) -> Result<u8, Self::Error>;
+
+    async fn read_pin(&mut self, pin: Pin) -> Result<bool, Self::Error> {
+        Ok(self.read_port().await? & pin.mask() != 0)
+    }
+
+    async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Self::Error> {
+        self.modify_port(pin, if high { 0xFF } else { 0x00 }).await
+    }
+
+    async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Self::Error> {
+        self.modify_port_direction(pin, if input { 0xFF } else { 0x00 })
+            .await
+    }
+}
+
+impl<X: AsyncIoExpander + ?Sized> AsyncIoExpander for &mut X {
+    type Error = X::Error;
+
+    const WIDTH: u8 = X::WIDTH;
+
+    async fn read_port(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_port().await
+    }
+
+    fn output_latch(&self) -> u8 {
+        (**self).output_latch()
+    }
+
+    async fn write_olat(&mut self, value: u8) -> Result<(), Self::Error> {
+        (**self).write_olat(value).await
+    }
+
+    async fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Self::Error> {
+        (**self).modify_port(mask, value).await
+    }
+
+    async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Self::Error> {
+        (**self).set_port_direction(inputs).await
+    }
+
+    async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Self::Error> {
+        (**self).modify_port_direction(mask, inputs).await
+    }
+
+    async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Self::Error> {
+        (**self).set_port_pullups(mask).await
+    }
+
+    async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Self::Error> {
+        (**self).set_pin_pullup(pin, enable).await
+    }
+
+    async fn read_interrupt_flags(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_interrupt_flags().await
+    }
+
+    async fn read_interrupt_capture(&mut self) -> Result<u8, Self::Error> {
+        (**self).read_interrupt_capture().await
+    }
+
+    async fn read_pin(&mut self, pin: Pin) -> Result<bool, Self::Error> {
+        (**self).read_pin(pin).await
+    }
+
+    async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Self::Error> {
+        (**self).write_pin(pin, high).await
+    }
+
+    async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Self::Error> {
+        (**self).set_pin_direction(pin, input).await
+    }
+}
+
+/// One pin of an [`IoExpander`] as an `embedded_hal` digital pin. The
+/// direction is not changed; configure it on the expander first.
+///
+/// `X` is usually `&mut` an expander, or a port view that borrows one.
+pub struct ExpanderPin<X> {
+    dev: X,
+    pin: Pin,
+}
+
+impl<X> ExpanderPin<X> {
+    pub fn new(dev: X, pin: Pin) -> Self {
+        Self { dev, pin }
+    }
+
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+}
+
+impl<X: IoExpander> ExpanderPin<X> {
+    /// Drives the pin to the inverse of its current level.
+    pub fn toggle(&mut self) -> Result<(), X::Error> {
+        let current = self.dev.read_pin(self.pin)?;
+
+        self.dev.write_pin(self.pin, !current)
+    }
+}
+
+impl<X> embedded_hal::digital::ErrorType for ExpanderPin<X>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    type Error = X::Error;
+}
+
+impl<X> embedded_hal::digital::InputPin for ExpanderPin<X>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.dev.read_pin(self.pin)
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
+
+impl<X> embedded_hal::digital::OutputPin for ExpanderPin<X>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.dev.write_pin(self.pin, true)
+    }
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.dev.write_pin(self.pin, false)
+    }
+}
+
+impl<X> embedded_hal::digital::StatefulOutputPin for ExpanderPin<X>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.dev.output_latch() & self.pin.mask() != 0)
+    }
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_set_high()?)
+    }
+}
+
+/// One pin of an [`AsyncIoExpander`], see [`ExpanderPin`].
+pub struct AsyncExpanderPin<X> {
+    dev: X,
+    pin: Pin,
+}
+
+impl<X> AsyncExpanderPin<X> {
+    pub fn new(dev: X, pin: Pin) -> Self {
+        Self { dev, pin }
+    }
+
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+}
+
+impl<X: AsyncIoExpander> AsyncExpanderPin<X> {
+    pub async fn is_high(&mut self) -> Result<bool, X::Error> {
+        self.dev.read_pin(self.pin).await
+    }
+
+    pub async fn is_low(&mut self) -> Result<bool, X::Error> {
+        Ok(!self.is_high().await?)
+    }
+
+    pub async fn set_high(&mut self) -> Result<(), X::Error> {
+        self.dev.write_pin(self.pin, true).await
+    }
+
+    pub async fn set_low(&mut self) -> Result<(), X::Error> {
+        self.dev.write_pin(self.pin, false).await
+    }
+
+    pub async fn is_set_high(&mut self) -> Result<bool, X::Error> {
+        Ok(self.dev.output_latch() & self.pin.mask() != 0)
+    }
+
+    pub async fn is_set_low(&mut self) -> Result<bool, X::Error> {
+        Ok(!self.is_set_high().await?)
+    }
+
+    /// See [`ExpanderPin::toggle`].
+    pub async fn toggle(&mut self) -> Result<(), X::Error> {
+        let current = self.dev.read_pin(self.pin).await?;
+        self.dev.write_pin(self.pin, !current).await
+    }
+}
@@ -0,0 +1,152 @@
+//! Detection of chip resets and brown-outs.
+//!
+//! After a supply dip the expander comes back with its power-on register
+//! values (all pins inputs) while the MCU keeps running. With a
+//! [`HealthCheck`] installed the driver compares one register against its
+//! cached copy, either on every register access or on an explicit
+//! `check()`, and re-applies the last known configuration when they differ.
+
+use crate::mcp23s08::Reg;
+
+/// Which register is compared against the cache to spot a reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetDetect {
+    /// Compare `IODIR`. Only sees a reset while at least one pin is an
+    /// output, because `IODIR` powers up as `0xFF`.
+    Iodir,
+    /// Keep this non-zero value in `DEFVAL` and compare it. Use it when no
+    /// pin relies on compare-to-default interrupts.
+    Canary(u8),
+}
+
+impl ResetDetect {
+    #[inline]
+    pub(crate) fn reg(self) -> Reg {
+        match self {
+            ResetDetect::Iodir => Reg::IODIR,
+            ResetDetect::Canary(_) => Reg::DEFVAL,
+        }
+    }
+}
+
+/// Reset detection settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HealthCheck {
+    pub detect: ResetDetect,
+    /// Check before every register access instead of only in `check()`.
+    pub on_access: bool,
+    /// Re-apply the cached configuration when a reset is seen. When `false`
+    /// the driver returns [`Error::UnexpectedReset`](crate::mcp23s08::Error::UnexpectedReset)
+    /// until `restore()` is called.
+    pub recover: bool,
+}
+
+impl HealthCheck {
+    /// Periodic checks with automatic recovery.
+    pub const fn new(detect: ResetDetect) -> Self {
+        Self {
+            detect,
+            on_access: false,
+            recover: true,
+        }
+    }
+
+    pub const fn on_access(mut self, on_access: bool) -> Self {
+        self.on_access = on_access;
+        self
+    }
+
+    pub const fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+}
+
+/// Reset detection state kept by a driver.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Health {
+    pub(crate) config: Option<HealthCheck>,
+    pub(crate) pending_event: bool,
+    pub(crate) resets: u32,
+}
+
+impl Health {
+    #[inline]
+    pub(crate) fn on_access(&self) -> bool {
+        matches!(
+            self.config,
+            Some(HealthCheck {
+                on_access: true,
+                ..
+            })
+        )
+    }
+
+    pub(crate) fn record_reset(&mut self) {
+        self.pending_event = true;
+        self.resets = self.resets.wrapping_add(1);
+    }
+}
+
+/// Software copy of the chip's register file.
+///
+/// Every successful write and every read of a configuration register lands
+/// here, so the full configuration can be written back after a reset.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Shadow {
+    regs: [u8; 11],
+}
+
+impl Shadow {
+    /// Configuration registers in the order they are written back: `IODIR`
+    /// last, so outputs come up with the right latch value.
+    pub(crate) const RESTORE_ORDER: [Reg; 8] = [
+        Reg::IOCON,
+        Reg::IPOL,
+        Reg::GPINTEN,
+        Reg::DEFVAL,
+        Reg::INTCON,
+        Reg::GPPU,
+        Reg::OLAT,
+        Reg::IODIR,
+    ];
+
+    /// Power-on register values.
+    pub(crate) const fn new() -> Self {
+        let mut regs = [0x00; 11];
+        regs[Reg::IODIR as usize] = 0xFF;
+        Self { regs }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, reg: Reg) -> u8 {
+        self.regs[reg as usize]
+    }
+
+    #[inline]
+    pub(crate) fn olat(&self) -> u8 {
+        self.get(Reg::OLAT)
+    }
+
+    #[inline]
+    pub(crate) fn iodir(&self) -> u8 {
+        self.get(Reg::IODIR)
+    }
+
+    pub(crate) fn record_write(&mut self, reg: Reg, val: u8) {
+        match reg {
+            Reg::GPIO => self.regs[Reg::OLAT as usize] = val,
+            Reg::INTF | Reg::INTCAP => {}
+            _ => self.regs[reg as usize] = val,
+        }
+    }
+
+    pub(crate) fn record_read(&mut self, reg: Reg, val: u8) {
+        match reg {
+            Reg::GPIO | Reg::INTF | Reg::INTCAP => {}
+            _ => self.regs[reg as usize] = val,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Output interlocks.
+//!
+//! An [`Interlock`] is a static table of [`Rule`]s attached to a driver with
+//! `set_interlock()`. Every output write (`write_pin`, `write_port`,
+//! `write_olat`, `modify_port`) is checked against the table before it goes
+//! on the bus; a forbidden latch value is refused with
+//! [`Error::Interlock`](crate::mcp23s08::Error::Interlock).
+//!
+//! ```
+//! use mcp23s08_io::interlock::{Interlock, Rule};
+//! use mcp23s08_io::mcp23s08::{Pin, PinSet};
+//!
+//! // Forward/reverse relays on P0/P1, brake release P2 needs the enable P3.
+//! static RULES: [Rule; 2] = [
+//!     Rule::Exclusive(PinSet::EMPTY.with(Pin::P0).with(Pin::P1)),
+//!     Rule::Requires { pin: Pin::P2, needs: Pin::P3 },
+//! ];
+//! let interlock = Interlock::new(&RULES).break_before_make(20_000);
+//! assert!(interlock.check(0b0000_0011).is_err());
+//! ```
+//!
+//! Rules look at the output latch (`OLAT`) only. A pin is *active* when its
+//! latch bit is `1`, or `0` for pins listed in [`Interlock::active_low`].
+
+use crate::mcp23s08::{Pin, PinSet};
+
+/// One interlock rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rule {
+    /// At most one pin of the set may be active.
+    Exclusive(PinSet),
+    /// `pin` may only be active while `needs` is active too.
+    Requires { pin: Pin, needs: Pin },
+    /// No more than this many pins may be active at once.
+    MaxActive(u8),
+}
+
+/// What happens to a write that is allowed but switches between pins of an
+/// exclusive group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Resolve {
+    /// Write the new value in one go.
+    #[default]
+    Direct,
+    /// First release the pins that turn off, wait `dead_time_us`, then
+    /// activate the new ones. The wait uses the driver's delay (see
+    /// `with_delay`).
+    BreakBeforeMake { dead_time_us: u32 },
+}
+
+/// The rule a refused write would have broken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Violation {
+    /// More than one pin of the group would be active.
+    Exclusive { active: PinSet },
+    /// `pin` would be active without `needs`.
+    Requires { pin: Pin, needs: Pin },
+    /// `active` pins would be active, more than `max`.
+    MaxActive { active: u8, max: u8 },
+}
+
+/// Interlock table of a driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Interlock {
+    rules: &'static [Rule],
+    active_low: PinSet,
+    resolve: Resolve,
+}
+
+impl Interlock {
+    /// Active-high outputs, writes applied directly.
+    pub const fn new(rules: &'static [Rule]) -> Self {
+        Self {
+            rules,
+            active_low: PinSet::EMPTY,
+            resolve: Resolve::Direct,
+        }
+    }
+
+    /// Pins that are active while their latch bit is `0`.
+    pub const fn active_low(mut self, pins: PinSet) -> Self {
+        self.active_low = pins;
+        self
+    }
+
+    /// Switch exclusive groups break-before-make with the given dead-time.
+    pub const fn break_before_make(mut self, dead_time_us: u32) -> Self {
+        self.resolve = Resolve::BreakBeforeMake { dead_time_us };
+        self
+    }
+
+    pub fn rules(&self) -> &'static [Rule] {
+        self.rules
+    }
+
+    pub fn resolve(&self) -> Resolve {
+        self.resolve
+    }
+
+    /// Pins that would be active with this latch value.
+    pub fn active(&self, olat: u8) -> PinSet {
+        PinSet::from_bits(olat ^ self.active_low.bits())
+    }
+
+    /// Checks a latch value against every rule, in table order.
+    pub fn check(&self, olat: u8) -> Result<(), Violation> {
+        let active = self.active(olat);
+        for rule in self.rules {
+            match *rule {
+                Rule::Exclusive(group) => {
+                    let on = active & group;
+                    if on.len() > 1 {
+                        return Err(Violation::Exclusive { active: on });
+                    }
+                }
+                Rule::Requires { pin, needs } => {
+                    if active.contains(pin) && !active.contains(needs) {
+                        return Err(Violation::Requires { pin, needs });
+                    }
+                }
+                Rule::MaxActive(max) => {
+                    let count = active.len() as u8;
+                    if count > max {
+                        return Err(Violation::MaxActive { active: count, max });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the move from `old` to `new`. Returns the intermediate latch
+    /// value and dead-time when the move has to go break-before-make.
+    pub(crate) fn plan(&self, old: u8, new: u8) -> Result<Option<(u8, u32)>, Violation> {
+        self.check(new)?;
+        let Resolve::BreakBeforeMake { dead_time_us } = self.resolve else {
+            return Ok(None);
+        };
+        let before = self.active(old);
+        let after = self.active(new);
+        let on = after - before;
+        let off = before - after;
+        let switches = self.rules.iter().any(|rule| match *rule {
+            Rule::Exclusive(group) => !(on & group).is_empty() && !(off & group).is_empty(),
+            _ => false,
+        });
+        if !switches {
+            return Ok(None);
+        }
+        // Only pins active both before and after stay on in between.
+        let step = (before & after).bits() ^ self.active_low.bits();
+        Ok(Some((step, dead_time_us)))
+    }
+}
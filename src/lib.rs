@@ -1,6 +1,40 @@
 #![no_std]
 #![forbid(unsafe_code)]
-pub mod mcp23s08;
-pub mod mcp23s08async;
 
+#[cfg(feature = "std")]
+extern crate std;
 
+pub mod bank;
+pub mod bitbang;
+#[cfg(feature = "console")]
+pub mod console;
+pub mod expander;
+pub mod health;
+pub mod interlock;
+#[cfg(all(feature = "linux", target_os = "linux"))]
+pub mod linux;
+pub mod mcp23s08;
+pub mod mcp23s08async;
+pub mod mcp23s17;
+pub mod mcp23s17async;
+pub mod mcp23s18;
+pub mod mcp23s18async;
+pub mod parallel;
+mod policy;
+pub mod pwm;
+pub mod retry;
+pub mod scheduler;
+pub mod sequencer;
+#[cfg(any(
+    feature = "critical-section",
+    feature = "embassy-sync",
+    feature = "std"
+))]
+pub mod shared;
+pub mod shiftreg;
+pub mod sim;
+pub mod stats;
+pub mod stepper;
+pub mod transport;
+pub mod verify;
+pub mod watchdog;
@@ -0,0 +1,131 @@
+//! Linux `spidev` bus for running the drivers on a host (feature `linux`).
+//!
+//! [`SpidevDevice`] implements `embedded_hal::spi::SpiDevice` on a
+//! `/dev/spidevX.Y` file, in the shape of linux-embedded-hal 0.4's type of
+//! the same name: each transaction is one `SPI_IOC_MESSAGE` ioctl, so the
+//! kernel holds chip-select for all of its operations.
+//!
+//! ```no_run
+//! use mcp23s08_io::linux::SpidevDevice;
+//! use mcp23s08_io::mcp23s08::Mcp23s08;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0", 1_000_000).unwrap();
+//! let mut dev = Mcp23s08::new(spi, 0).unwrap();
+//! println!("{}", dev.dump().unwrap());
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::vec::Vec;
+
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+
+pub struct SpidevDevice(Spidev);
+
+impl SpidevDevice {
+    /// Opens `path` in SPI mode 0 (the MCP23S08 also accepts mode 3) with
+    /// the clock limited to `max_speed_hz` (10 MHz at most for the chip).
+    pub fn open(path: impl AsRef<Path>, max_speed_hz: u32) -> io::Result<Self> {
+        let mut spi = Spidev::open(path)?;
+        spi.configure(
+            &SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(max_speed_hz)
+                .mode(SpiModeFlags::SPI_MODE_0)
+                .build(),
+        )?;
+        Ok(Self(spi))
+    }
+
+    pub fn inner(&self) -> &Spidev {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Spidev {
+        self.0
+    }
+}
+
+/// Failed spidev ioctl.
+pub struct SpidevError(pub io::Error);
+
+/// Shows the OS error, e.g. inside `Error::Bus { source, .. }`.
+impl fmt::Debug for SpidevError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for SpidevError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for SpidevError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Maps the errno of the failed ioctl: `EOVERFLOW` is an overrun, while
+/// `EINVAL`/`EOPNOTSUPP` mean the controller rejected the word size, mode or
+/// clock of the transfer.
+impl embedded_hal::spi::Error for SpidevError {
+    fn kind(&self) -> ErrorKind {
+        match self.0.raw_os_error() {
+            Some(libc::EOVERFLOW) => ErrorKind::Overrun,
+            Some(libc::EINVAL | libc::EOPNOTSUPP) => ErrorKind::FrameFormat,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl ErrorType for SpidevDevice {
+    type Error = SpidevError;
+}
+
+impl SpiDevice for SpidevDevice {
+    fn transaction(&mut self, ops: &mut [Operation<'_, u8>]) -> Result<(), SpidevError> {
+        // spidev needs separate, equally long transmit buffers.
+        let tx: Vec<Vec<u8>> = ops
+            .iter()
+            .map(|op| match op {
+                Operation::Transfer(read, write) => {
+                    let mut tx = write[..write.len().min(read.len())].to_vec();
+                    tx.resize(read.len(), 0);
+                    tx
+                }
+                Operation::TransferInPlace(buf) => buf.to_vec(),
+                _ => Vec::new(),
+            })
+            .collect();
+
+        let mut transfers = Vec::with_capacity(ops.len());
+        for (op, tx) in ops.iter_mut().zip(&tx) {
+            match op {
+                Operation::Read(buf) => transfers.push(SpidevTransfer::read(buf)),
+                Operation::Write(buf) => transfers.push(SpidevTransfer::write(buf)),
+                Operation::Transfer(read, write) => {
+                    let rest = &write[write.len().min(read.len())..];
+                    transfers.push(SpidevTransfer::read_write(tx, read));
+                    if !rest.is_empty() {
+                        transfers.push(SpidevTransfer::write(rest));
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    transfers.push(SpidevTransfer::read_write(tx, buf))
+                }
+                Operation::DelayNs(ns) => {
+                    let us = ns.div_ceil(1000).min(u32::from(u16::MAX)) as u16;
+                    transfers.push(SpidevTransfer::delay(us));
+                }
+            }
+        }
+        self.0
+            .transfer_multiple(&mut transfers)
+            .map_err(SpidevError)
+    }
+}
@@ -1,15 +1,123 @@
 use core::fmt::Debug;
+use core::ops::ControlFlow;
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::Error as DigitalError;
 use embedded_hal::digital::ErrorKind;
-use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_hal::i2c::NoAcknowledgeSource;
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal::spi::SpiDevice;
 
-#[derive(Debug)]
-pub enum Error<SpiE> {
-    Spi(SpiE),
+pub use crate::expander::{ExpanderPin, IoExpander};
+use crate::health::Shadow;
+pub use crate::health::{HealthCheck, ResetDetect};
+pub use crate::interlock::{Interlock, Resolve, Rule, Violation};
+use crate::policy::Policy;
+pub use crate::retry::{NoDelay, RetryPolicy, RetryStats};
+pub use crate::stats::{ErrorCounts, Stats};
+pub use crate::transport::{BusErrorKind, I2cBus, Transport};
+use crate::transport::{RegAccess, i2c_hw_addr};
+pub use crate::verify::{VerifyMode, VerifyPolicy};
+use crate::watchdog::Watchdog;
+pub use crate::watchdog::{WatchdogConfig, WatchdogFault};
+
+/// Driver error. Shared by the blocking and async drivers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<BusE> {
+    /// SPI or I²C transaction failed while accessing `reg`.
+    Bus {
+        reg: Reg,
+        access: Access,
+        source: BusE,
+    },
+    /// Hardware address is outside 0..=3 (MCP23S08) or 0..=7 (MCP23S17), or
+    /// the I²C address is outside 0x20..=0x27.
     BadAddress,
+    /// A register read back a different value than was just written.
+    VerifyFailed { reg: Reg, wrote: u8, read: u8 },
+    /// No chip answered at this address.
+    NotPresent,
+    /// The chip lost its configuration (power-on reset or brown-out).
+    UnexpectedReset,
+    /// The watchdog has tripped; output writes are refused until the fault
+    /// is cleared.
+    WatchdogFault(WatchdogFault),
+    /// The output write would break an interlock rule and was not sent.
+    Interlock(Violation),
+    /// `DEFVAL` holds the [`ResetDetect::Canary`] of the health check and
+    /// was not changed.
+    CanaryInUse,
+}
+
+/// Direction of the register access that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl<BusE> Error<BusE> {
+    /// Register involved in the failure, if any.
+    pub fn reg(&self) -> Option<Reg> {
+        match self {
+            Error::Bus { reg, .. } | Error::VerifyFailed { reg, .. } => Some(*reg),
+            _ => None,
+        }
+    }
+
+    /// Underlying bus error, if the failure came from the bus.
+    pub fn bus_error(&self) -> Option<&BusE> {
+        match self {
+            Error::Bus { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+
+    /// [`Error::NotPresent`] for a bus error that `kind` classifies as an
+    /// unacknowledged address, which is how a missing I²C chip shows. HALs
+    /// that cannot tell address from data NACKs report `Unknown`.
+    pub(crate) fn absent_on_nack(self, kind: fn(&BusE) -> BusErrorKind) -> Self {
+        match self.bus_error().map(kind) {
+            Some(BusErrorKind::NoAcknowledge(
+                NoAcknowledgeSource::Address | NoAcknowledgeSource::Unknown,
+            )) => Error::NotPresent,
+            _ => self,
+        }
+    }
 }
 
+impl<BusE: Debug> core::fmt::Display for Error<BusE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Bus {
+                reg,
+                access: Access::Read,
+                source,
+            } => write!(f, "bus error reading {reg:?}: {source:?}"),
+            Error::Bus {
+                reg,
+                access: Access::Write,
+                source,
+            } => write!(f, "bus error writing {reg:?}: {source:?}"),
+            Error::BadAddress => f.write_str("hardware address out of range"),
+            Error::VerifyFailed { reg, wrote, read } => write!(
+                f,
+                "verify failed on {reg:?}: wrote {wrote:#04x}, read {read:#04x}"
+            ),
+            Error::NotPresent => f.write_str("device not present"),
+            Error::UnexpectedReset => f.write_str("device reset unexpectedly"),
+            Error::WatchdogFault(fault) => write!(f, "watchdog fault latched: {fault:?}"),
+            Error::Interlock(violation) => write!(f, "interlock violation: {violation:?}"),
+            Error::CanaryInUse => f.write_str("DEFVAL holds the reset canary"),
+        }
+    }
+}
+
+impl<BusE: Debug> core::error::Error for Error<BusE> {}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Pin {
     P0,
     P1,
@@ -21,86 +129,502 @@ pub enum Pin {
     P7,
 }
 impl Pin {
+    /// All eight lines in GP0..GP7 order.
+    pub const ALL: [Pin; 8] = [
+        Pin::P0,
+        Pin::P1,
+        Pin::P2,
+        Pin::P3,
+        Pin::P4,
+        Pin::P5,
+        Pin::P6,
+        Pin::P7,
+    ];
+
+    /// Bit of this pin in the 8-bit port registers.
     #[inline]
-    fn bit(self) -> u8 {
+    pub const fn mask(self) -> u8 {
         1u8 << (self as u8)
     }
 }
 
+/// Returned when converting an index outside `0..=7` into a [`Pin`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidPin(pub u8);
+
+impl core::fmt::Display for InvalidPin {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "no pin {} (pins are 0..=7)", self.0)
+    }
+}
+
+impl core::error::Error for InvalidPin {}
+
+impl TryFrom<u8> for Pin {
+    type Error = InvalidPin;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Pin::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or(InvalidPin(index))
+    }
+}
+
+impl From<Pin> for u8 {
+    #[inline]
+    fn from(pin: Pin) -> u8 {
+        pin as u8
+    }
+}
+
+/// Set of expander pins, stored as a port mask (bit N = `Pin::PN`).
+///
+/// Every mask-taking method accepts `impl Into<PinSet>`, so plain `u8`
+/// masks keep working.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PinSet(u8);
+
+impl PinSet {
+    pub const EMPTY: PinSet = PinSet(0x00);
+    pub const ALL: PinSet = PinSet(0xFF);
+
+    #[inline]
+    pub const fn from_bits(bits: u8) -> Self {
+        PinSet(bits)
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, pin: Pin) -> bool {
+        self.0 & pin.mask() != 0
+    }
+
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    #[inline]
+    pub fn insert(&mut self, pin: Pin) {
+        self.0 |= pin.mask();
+    }
+
+    #[inline]
+    pub fn remove(&mut self, pin: Pin) {
+        self.0 &= !pin.mask();
+    }
+
+    #[inline]
+    pub const fn with(self, pin: Pin) -> Self {
+        PinSet(self.0 | pin.mask())
+    }
+
+    #[inline]
+    pub const fn without(self, pin: Pin) -> Self {
+        PinSet(self.0 & !pin.mask())
+    }
+
+    #[inline]
+    pub const fn union(self, other: PinSet) -> Self {
+        PinSet(self.0 | other.0)
+    }
+
+    #[inline]
+    pub const fn intersection(self, other: PinSet) -> Self {
+        PinSet(self.0 & other.0)
+    }
+
+    #[inline]
+    pub const fn difference(self, other: PinSet) -> Self {
+        PinSet(self.0 & !other.0)
+    }
+
+    #[inline]
+    pub const fn symmetric_difference(self, other: PinSet) -> Self {
+        PinSet(self.0 ^ other.0)
+    }
+
+    #[inline]
+    pub const fn complement(self) -> Self {
+        PinSet(!self.0)
+    }
+
+    #[inline]
+    pub const fn is_subset(self, other: PinSet) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    /// Pins in ascending order.
+    #[inline]
+    pub fn iter(self) -> PinSetIter {
+        PinSetIter(self.0)
+    }
+}
+
+impl Debug for PinSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PinSet {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u8:#010b}", self.0)
+    }
+}
+
+impl From<Pin> for PinSet {
+    #[inline]
+    fn from(pin: Pin) -> Self {
+        PinSet(pin.mask())
+    }
+}
+
+impl From<u8> for PinSet {
+    #[inline]
+    fn from(bits: u8) -> Self {
+        PinSet(bits)
+    }
+}
+
+impl From<PinSet> for u8 {
+    #[inline]
+    fn from(set: PinSet) -> u8 {
+        set.0
+    }
+}
+
+impl<const N: usize> From<[Pin; N]> for PinSet {
+    fn from(pins: [Pin; N]) -> Self {
+        pins.into_iter().collect()
+    }
+}
+
+impl FromIterator<Pin> for PinSet {
+    fn from_iter<I: IntoIterator<Item = Pin>>(iter: I) -> Self {
+        let mut set = PinSet::EMPTY;
+        for pin in iter {
+            set.insert(pin);
+        }
+        set
+    }
+}
+
+impl Extend<Pin> for PinSet {
+    fn extend<I: IntoIterator<Item = Pin>>(&mut self, iter: I) {
+        for pin in iter {
+            self.insert(pin);
+        }
+    }
+}
+
+impl IntoIterator for PinSet {
+    type Item = Pin;
+    type IntoIter = PinSetIter;
+
+    fn into_iter(self) -> PinSetIter {
+        self.iter()
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitOr<T> for PinSet {
+    type Output = PinSet;
+    fn bitor(self, rhs: T) -> PinSet {
+        self.union(rhs.into())
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitAnd<T> for PinSet {
+    type Output = PinSet;
+    fn bitand(self, rhs: T) -> PinSet {
+        self.intersection(rhs.into())
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitXor<T> for PinSet {
+    type Output = PinSet;
+    fn bitxor(self, rhs: T) -> PinSet {
+        self.symmetric_difference(rhs.into())
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::Sub<T> for PinSet {
+    type Output = PinSet;
+    fn sub(self, rhs: T) -> PinSet {
+        self.difference(rhs.into())
+    }
+}
+
+impl core::ops::Not for PinSet {
+    type Output = PinSet;
+    fn not(self) -> PinSet {
+        self.complement()
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitOrAssign<T> for PinSet {
+    fn bitor_assign(&mut self, rhs: T) {
+        *self = *self | rhs;
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitAndAssign<T> for PinSet {
+    fn bitand_assign(&mut self, rhs: T) {
+        *self = *self & rhs;
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::BitXorAssign<T> for PinSet {
+    fn bitxor_assign(&mut self, rhs: T) {
+        *self = *self ^ rhs;
+    }
+}
+
+impl<T: Into<PinSet>> core::ops::SubAssign<T> for PinSet {
+    fn sub_assign(&mut self, rhs: T) {
+        *self = *self - rhs;
+    }
+}
+
+impl core::ops::BitOr for Pin {
+    type Output = PinSet;
+    fn bitor(self, rhs: Pin) -> PinSet {
+        PinSet::from(self).with(rhs)
+    }
+}
+
+/// Iterator over the pins of a [`PinSet`], lowest first.
+#[derive(Clone, Debug)]
+pub struct PinSetIter(u8);
+
+impl Iterator for PinSetIter {
+    type Item = Pin;
+
+    fn next(&mut self) -> Option<Pin> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(Pin::ALL[index as usize])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl DoubleEndedIterator for PinSetIter {
+    fn next_back(&mut self) -> Option<Pin> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = 7 - self.0.leading_zeros() as u8;
+        self.0 &= !(1 << index);
+        Some(Pin::ALL[index as usize])
+    }
+}
+
+impl ExactSizeIterator for PinSetIter {}
+impl core::iter::FusedIterator for PinSetIter {}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Polarity {
     Normal,
     Inverted,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum InterruptMode {
     OnChange,
 
     CompareToDefault,
 }
 
-pub struct Mcp23s08<SPI> {
-    spi: SPI,
+pub struct Mcp23s08<B, D = NoDelay> {
+    bus: B,
     hw_addr: u8,
-    olat: u8,
-    iodir: u8,
+    shadow: Shadow,
+    policy: Policy,
+    delay: D,
+    interlock: Option<Interlock>,
 }
 
+/// MCP23008: the I²C sibling of the MCP23S08, with the same API.
+pub type Mcp23008<I2C, D = NoDelay> = Mcp23s08<I2cBus<I2C>, D>;
+
 impl<SPI, E> Mcp23s08<SPI>
 where
     SPI: SpiDevice<Error = E>,
+    E: SpiError,
 {
     pub fn new(spi: SPI, hw_addr: u8) -> Result<Self, Error<E>> {
         if hw_addr > 3 {
             return Err(Error::BadAddress);
         }
-        let mut this = Self {
-            spi,
+        Self::init(spi, hw_addr)
+    }
+
+    /// Enables HAEN on every chip sharing `spi` and probes the four hardware
+    /// addresses. Entry `n` is `true` if a chip answered at address `n`.
+    ///
+    /// `HAEN` is set by reading `IOCON` at address 0 and writing it back
+    /// with `HAEN` added, so a chip already configured at address 0 keeps
+    /// its other settings.
+    pub fn scan(spi: &mut SPI) -> Result<[bool; 4], Error<E>> {
+        let mut found = [false; 4];
+        let mut dev = Mcp23s08::detached(spi, 0);
+        dev.enable_addressing()?;
+        for (hw_addr, slot) in found.iter_mut().enumerate() {
+            dev.hw_addr = hw_addr as u8;
+            match dev.probe() {
+                Ok(()) => *slot = true,
+                Err(Error::NotPresent) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl<I2C, E> Mcp23008<I2C>
+where
+    I2C: embedded_hal::i2c::I2c<Error = E>,
+{
+    /// MCP23008 at the 7-bit I²C `address` (0x20..=0x27).
+    pub fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Self::init(I2cBus::new(i2c), hw_addr)
+    }
+}
+
+impl<B, E> Mcp23s08<B>
+where
+    B: Transport<Error = E>,
+{
+    /// Driver state for `bus` without touching the chip.
+    fn detached(bus: B, hw_addr: u8) -> Self {
+        Self {
+            bus,
             hw_addr,
-            olat: 0x00,
-            iodir: 0xFF,
-        };
+            shadow: Shadow::new(),
+            policy: Policy::new(),
+            delay: NoDelay,
+            interlock: None,
+        }
+    }
 
-        this.write_reg(Reg::IOCON, 0x00)?;
-        this.iodir = this.read_reg(Reg::IODIR)?;
-        this.olat = this.read_reg(Reg::OLAT)?;
+    fn init(bus: B, hw_addr: u8) -> Result<Self, Error<E>> {
+        let mut this = Self::detached(bus, hw_addr);
+        // A chip with HAEN clear only listens on address 0, so addressed
+        // SPI chips need HAEN set through a broadcast first. That write
+        // also reached this chip.
+        if hw_addr == 0 || !B::NEEDS_HAEN {
+            this.write_reg(Reg::IOCON, 0x00)?;
+        } else {
+            let iocon = this.enable_addressing()?;
+            this.shadow.record_write(Reg::IOCON, iocon);
+        }
+        this.read_reg(Reg::IODIR)?;
+        this.read_reg(Reg::OLAT)?;
         Ok(this)
     }
+}
+
+impl<B, D, E> Mcp23s08<B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+{
+    /// Checks that a chip really answers at this address.
+    ///
+    /// Writes two scratch patterns to `DEFVAL`, reads each back and restores
+    /// the original value, or the [`ResetDetect::Canary`] if one is
+    /// installed. A floating MISO line reads as `0x00`/`0xFF` and fails with
+    /// [`Error::NotPresent`], and so does an I²C chip that does not
+    /// acknowledge its address. A chip that answers without its canary is
+    /// handled as a reset, as by [`check`](Self::check).
+    pub fn probe(&mut self) -> Result<(), Error<E>> {
+        let saved = self
+            .read_reg(Reg::DEFVAL)
+            .map_err(|e| e.absent_on_nack(B::error_kind))?;
+        let mut result = Ok(());
+        for pattern in PROBE_PATTERNS {
+            self.write_reg(Reg::DEFVAL, pattern)?;
+            if self.read_reg(Reg::DEFVAL)? != pattern {
+                result = Err(Error::NotPresent);
+                break;
+            }
+        }
+        let canary = self.policy.canary();
+        self.write_reg(Reg::DEFVAL, canary.unwrap_or(saved))?;
+        let lost = canary.is_some_and(|canary| canary != saved);
+        if result.is_ok() && self.policy.reset_seen(lost)? {
+            self.restore()?;
+        }
+        result
+    }
 
     pub fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        let mut iodir = self.shadow.iodir();
         if input {
-            self.iodir |= pin.bit();
+            iodir |= pin.mask();
         } else {
-            self.iodir &= !pin.bit();
+            iodir &= !pin.mask();
         }
-        self.write_reg(Reg::IODIR, self.iodir)
+        self.write_reg(Reg::IODIR, iodir)
     }
 
-    pub fn set_port_direction(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.iodir = mask;
-        self.write_reg(Reg::IODIR, mask)
+    pub fn set_port_direction(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IODIR, mask.into().bits())
+    }
+
+    /// Sets the direction of the pins in `mask` (`1` in `inputs` = input)
+    /// and leaves the others as they are, in a single `IODIR` write.
+    pub fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let iodir = (self.shadow.iodir() & !mask) | (inputs & mask);
+        self.write_reg(Reg::IODIR, iodir)
     }
 
     pub fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
         let mut gppu = self.read_reg(Reg::GPPU)?;
         if enable {
-            gppu |= pin.bit();
+            gppu |= pin.mask();
         } else {
-            gppu &= !pin.bit();
+            gppu &= !pin.mask();
         }
         self.write_reg(Reg::GPPU, gppu)
     }
 
-    pub fn set_port_pullups(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.write_reg(Reg::GPPU, mask)
+    pub fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPPU, mask.into().bits())
     }
 
     pub fn set_pin_polarity(&mut self, pin: Pin, pol: Polarity) -> Result<(), Error<E>> {
         let mut ipol = self.read_reg(Reg::IPOL)?;
         match pol {
-            Polarity::Normal => ipol &= !pin.bit(),
-            Polarity::Inverted => ipol |= pin.bit(),
+            Polarity::Normal => ipol &= !pin.mask(),
+            Polarity::Inverted => ipol |= pin.mask(),
         }
         self.write_reg(Reg::IPOL, ipol)
     }
@@ -110,40 +634,47 @@ where
     }
 
     pub fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
-        Ok(self.read_port()? & pin.bit() != 0)
+        Ok(self.read_port()? & pin.mask() != 0)
     }
 
     pub fn write_port(&mut self, value: u8) -> Result<(), Error<E>> {
-        self.olat = value;
-        self.write_reg(Reg::GPIO, value)
+        self.write_outputs(Reg::GPIO, value)
     }
 
     pub fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        let mut olat = self.shadow.olat();
         if high {
-            self.olat |= pin.bit();
+            olat |= pin.mask();
         } else {
-            self.olat &= !pin.bit();
+            olat &= !pin.mask();
         }
-        self.write_reg(Reg::GPIO, self.olat)
+        self.write_outputs(Reg::GPIO, olat)
     }
 
     pub fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
-        self.olat = value;
-        self.write_reg(Reg::OLAT, value)
+        self.write_outputs(Reg::OLAT, value)
+    }
+
+    /// Sets the pins in `mask` to the matching bits of `value` and leaves
+    /// the others as they are, in a single `OLAT` write.
+    pub fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let olat = (self.shadow.olat() & !mask) | (value & mask);
+        self.write_outputs(Reg::OLAT, olat)
     }
 
     pub fn set_pin_interrupt_enable(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
         let mut gpinten = self.read_reg(Reg::GPINTEN)?;
         if enable {
-            gpinten |= pin.bit();
+            gpinten |= pin.mask();
         } else {
-            gpinten &= !pin.bit();
+            gpinten &= !pin.mask();
         }
         self.write_reg(Reg::GPINTEN, gpinten)
     }
 
-    pub fn set_port_interrupt_enable(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.write_reg(Reg::GPINTEN, mask)
+    pub fn set_port_interrupt_enable(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPINTEN, mask.into().bits())
     }
 
     pub fn set_pin_interrupt_mode(
@@ -153,17 +684,20 @@ where
     ) -> Result<(), Error<E>> {
         let mut intcon = self.read_reg(Reg::INTCON)?;
         match mode {
-            InterruptMode::OnChange => intcon &= !pin.bit(),
-            InterruptMode::CompareToDefault => intcon |= pin.bit(),
+            InterruptMode::OnChange => intcon &= !pin.mask(),
+            InterruptMode::CompareToDefault => intcon |= pin.mask(),
         }
         self.write_reg(Reg::INTCON, intcon)
     }
 
-    pub fn set_port_interrupt_mode(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.write_reg(Reg::INTCON, mask)
+    pub fn set_port_interrupt_mode(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::INTCON, mask.into().bits())
     }
 
+    /// Fails with [`Error::CanaryInUse`] while the health check keeps a
+    /// [`ResetDetect::Canary`] in `DEFVAL`.
     pub fn set_port_default_compare(&mut self, defval: u8) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
         self.write_reg(Reg::DEFVAL, defval)
     }
 
@@ -172,11 +706,27 @@ where
     }
 
     pub fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
-        self.read_reg(Reg::INTCAP)
+        let value = self.read_reg(Reg::INTCAP)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
     }
 
     pub fn clear_interrupts(&mut self) -> Result<u8, Error<E>> {
-        self.read_reg(Reg::GPIO)
+        let value = self.read_reg(Reg::GPIO)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// Reads every register in address order, e.g. for a diagnostic
+    /// printout. Reading `INTCAP` and `GPIO` clears a pending interrupt.
+    pub fn dump(&mut self) -> Result<RegisterDump, Error<E>> {
+        let mut regs = [0; Reg::ALL.len()];
+        for (value, reg) in regs.iter_mut().zip(Reg::ALL) {
+            *value = self.read_reg(reg)?;
+        }
+        Ok(RegisterDump(regs))
     }
 
     pub fn set_int_open_drain(&mut self, enable: bool) -> Result<(), Error<E>> {
@@ -201,44 +751,325 @@ where
         self.write_reg(Reg::IOCON, iocon)
     }
 
-    pub fn pin<'a>(&'a mut self, pin: Pin) -> GpioPin<'a, SPI> {
-        GpioPin { dev: self, pin }
+    /// Installs (or with `None` removes) reset detection.
+    ///
+    /// Reads the whole configuration once so it can be re-applied later and,
+    /// for [`ResetDetect::Canary`], writes the canary to `DEFVAL`.
+    pub fn set_health_check(&mut self, check: Option<HealthCheck>) -> Result<(), Error<E>> {
+        self.policy.health.config = None;
+        if let Some(cfg) = check {
+            for reg in Shadow::RESTORE_ORDER {
+                self.read_reg(reg)?;
+            }
+            if let ResetDetect::Canary(value) = cfg.detect {
+                self.write_reg(Reg::DEFVAL, value)?;
+            }
+        }
+        self.policy.health.config = check;
+        Ok(())
     }
 
-    pub fn into_inner(self) -> SPI {
-        self.spi
+    /// Compares the detection register with the cache.
+    ///
+    /// Returns `Ok(true)` if a reset was seen and the configuration was
+    /// re-applied, or [`Error::UnexpectedReset`] if recovery is disabled.
+    /// Without a health check installed this does nothing.
+    pub fn check(&mut self) -> Result<bool, Error<E>> {
+        let Some(reg) = self.policy.detect_reg() else {
+            return Ok(false);
+        };
+        let changed = self.read_raw(reg)? != self.shadow.get(reg);
+        if !self.policy.reset_seen(changed)? {
+            return Ok(false);
+        }
+        self.restore()?;
+        Ok(true)
     }
 
-    #[inline]
-    fn opcode_write(&self) -> u8 {
-        0x40 | ((self.hw_addr & 0x03) << 1) | 0
+    /// Writes the cached configuration and output latch back to the chip.
+    ///
+    /// For SPI chips at `hw_addr` 1..=3 `HAEN` is first re-enabled through
+    /// address 0, since a freshly reset chip only listens there.
+    pub fn restore(&mut self) -> Result<(), Error<E>> {
+        if B::NEEDS_HAEN && self.hw_addr != 0 {
+            self.enable_addressing()?;
+        }
+        for reg in Shadow::RESTORE_ORDER {
+            self.write_checked(reg, self.shadow.get(reg))?;
+        }
+        Ok(())
     }
-    #[inline]
-    fn opcode_read(&self) -> u8 {
-        0x40 | ((self.hw_addr & 0x03) << 1) | 1
+
+    /// Returns `true` once after each detected reset.
+    pub fn take_reset_event(&mut self) -> bool {
+        core::mem::take(&mut self.policy.health.pending_event)
+    }
+
+    /// Number of resets detected since the driver was created.
+    pub fn reset_count(&self) -> u32 {
+        self.policy.health.resets
+    }
+
+    /// Replaces the delay used for retry backoff.
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s08<B, D2> {
+        Mcp23s08 {
+            bus: self.bus,
+            hw_addr: self.hw_addr,
+            shadow: self.shadow,
+            policy: self.policy,
+            delay,
+            interlock: self.interlock,
+        }
+    }
+
+    /// Sets how failed bus transactions are retried. Backoff delays use the
+    /// delay installed with [`with_delay`](Self::with_delay).
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.policy.retry = policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.policy.retry
+    }
+
+    /// Retry counters since creation or the last `reset_retry_stats()`.
+    pub fn retry_stats(&self) -> RetryStats {
+        self.policy.retry_stats
+    }
+
+    pub fn reset_retry_stats(&mut self) {
+        self.policy.retry_stats = RetryStats::default();
+    }
+
+    /// Snapshot of the communication counters (`stats` feature).
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.policy.stats
+    }
+
+    /// Zeroes all communication counters (`stats` feature).
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.policy.stats = Stats::default();
+    }
+
+    /// Installs (or with `None` removes) the software watchdog. The timeout
+    /// starts counting at `now_ms`.
+    pub fn set_watchdog(&mut self, config: Option<WatchdogConfig>, now_ms: u64) {
+        self.policy.watchdog = Watchdog::new(config, now_ms);
+    }
+
+    /// Tells the watchdog the application is alive.
+    pub fn feed(&mut self, now_ms: u64) {
+        self.policy.watchdog.last_feed = now_ms;
+    }
+
+    /// Trips the watchdog if the last `feed()` is older than the timeout.
+    ///
+    /// Returns `Ok(true)` when it tripped on this call and the safe state was
+    /// written. The fault stays latched even if writing the safe state fails.
+    pub fn poll_watchdog(&mut self, now_ms: u64) -> Result<bool, Error<E>> {
+        let Some(cfg) = self.policy.poll_watchdog(now_ms) else {
+            return Ok(false);
+        };
+        self.drive_safe_state(cfg)?;
+        Ok(true)
+    }
+
+    /// The latched fault, if the watchdog has tripped.
+    pub fn watchdog_fault(&self) -> Option<WatchdogFault> {
+        self.policy.watchdog.fault
+    }
+
+    /// Releases the outputs again. The safe state stays on the pins until
+    /// the application writes new values.
+    pub fn clear_watchdog_fault(&mut self, now_ms: u64) {
+        self.policy.watchdog.clear(now_ms);
+    }
+
+    /// Installs (or with `None` removes) the output interlock table. The
+    /// current latch is not checked; the rules apply from the next write.
+    pub fn set_interlock(&mut self, interlock: Option<Interlock>) {
+        self.interlock = interlock;
+    }
+
+    pub fn interlock(&self) -> Option<Interlock> {
+        self.interlock
+    }
+
+    /// Sets which register writes are read back and how often a mismatch
+    /// is retried before [`Error::VerifyFailed`] is returned.
+    pub fn set_verify(&mut self, policy: VerifyPolicy) {
+        self.policy.verify = policy;
+    }
+
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        self.policy.verify
+    }
+
+    pub fn pin<'a>(&'a mut self, pin: Pin) -> GpioPin<'a, B, D> {
+        ExpanderPin::new(self, pin)
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Sets `HAEN` in `IOCON` at address 0, which every chip with `HAEN`
+    /// clear answers to. The other bits are read back from there first, so
+    /// a configured chip at address 0 keeps them. Returns the value written.
+    fn enable_addressing(&mut self) -> Result<u8, Error<E>> {
+        let hw_addr = self.hw_addr;
+        self.hw_addr = 0;
+        let result = match self.read_raw(Reg::IOCON) {
+            Ok(iocon) => self
+                .write_raw(Reg::IOCON, iocon | HAEN)
+                .map(|()| iocon | HAEN),
+            Err(e) => Err(e),
+        };
+        self.hw_addr = hw_addr;
+        result
+    }
+
+    /// Output latch write under the interlock table.
+    fn write_outputs(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        if let Some(interlock) = self.interlock {
+            let plan = interlock
+                .plan(self.shadow.olat(), val)
+                .map_err(Error::Interlock)?;
+            if let Some((step, dead_time_us)) = plan {
+                self.write_reg(reg, step)?;
+                if dead_time_us > 0 {
+                    self.delay.delay_us(dead_time_us);
+                }
+            }
+        }
+        self.write_reg(reg, val)
     }
 
     fn write_reg(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
-        let opcode = self.opcode_write();
-        let frame = [opcode, reg as u8, val];
+        self.policy.guard(reg)?;
+        let result = self.write_reg_unguarded(reg, val);
+        self.trip_on_bus_errors(result)
+    }
 
-        let mut ops = [Operation::Write(&frame)];
-        self.spi.transaction(&mut ops).map_err(Error::Spi)
+    fn write_reg_unguarded(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        self.write_checked(reg, val)?;
+        self.shadow.record_write(reg, val);
+        Ok(())
     }
 
     fn read_reg(&mut self, reg: Reg) -> Result<u8, Error<E>> {
-        let opcode = self.opcode_read();
-        let cmd = [opcode, reg as u8];
+        let result = self.read_reg_unguarded(reg);
+        self.trip_on_bus_errors(result)
+    }
+
+    fn read_reg_unguarded(&mut self, reg: Reg) -> Result<u8, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        let val = self.read_raw(reg)?;
+        self.shadow.record_read(reg, val);
+        Ok(val)
+    }
+
+    /// Drives the safe state if `result` trips the watchdog. `result` is
+    /// passed through unchanged.
+    fn trip_on_bus_errors<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        if let Some(cfg) = self.policy.trip_on_bus_errors(&result) {
+            // The bus is failing; the safe state is best effort.
+            let _ = self.drive_safe_state(cfg);
+        }
+        result
+    }
+
+    /// Writes the safe output state of a tripped watchdog.
+    fn drive_safe_state(&mut self, cfg: WatchdogConfig) -> Result<(), Error<E>> {
+        self.write_checked(Reg::OLAT, cfg.safe_olat)?;
+        self.shadow.record_write(Reg::OLAT, cfg.safe_olat);
+        if let Some(iodir) = cfg.safe_iodir {
+            self.write_checked(Reg::IODIR, iodir)?;
+            self.shadow.record_write(Reg::IODIR, iodir);
+        }
+        Ok(())
+    }
+
+    /// Writes `reg` and, if the verify policy covers it, reads it back.
+    fn write_checked(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        let Some(mut readback) = self.policy.readback(reg, IOCON_BITS) else {
+            return self.write_raw(reg, val);
+        };
+        loop {
+            self.write_raw(reg, val)?;
+            let read = self.read_raw(readback.from)?;
+            if let ControlFlow::Break(result) = self.policy.verify(&mut readback, &[val], &[read]) {
+                return result;
+            }
+        }
+    }
+
+    /// Runs one register access under the retry policy.
+    fn transact(&mut self, addr: u8, mut access: RegAccess<'_>) -> Result<(), E> {
+        let mut attempt = 0;
+        loop {
+            self.policy.begin(&access, B::frame_sizes);
+            match access.run(&mut self.bus, self.hw_addr, addr) {
+                Ok(()) => {
+                    self.policy.succeeded(attempt);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let Some(backoff_us) = self.policy.failed(attempt, B::error_kind(&e)) else {
+                        return Err(e);
+                    };
+                    if backoff_us > 0 {
+                        self.delay.delay_us(backoff_us);
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn write_raw(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        self.transact(reg as u8, RegAccess::Write(&[val]))
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Write,
+                source,
+            })
+    }
+
+    fn read_raw(&mut self, reg: Reg) -> Result<u8, Error<E>> {
         let mut byte = [0u8; 1];
-        let mut ops = [Operation::Write(&cmd), Operation::Read(&mut byte)];
-        self.spi.transaction(&mut ops).map_err(Error::Spi)?;
+        self.transact(reg as u8, RegAccess::Read(&mut byte))
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Read,
+                source,
+            })?;
         Ok(byte[0])
     }
 }
 
+/// `IOCON.HAEN`: hardware address pins enabled.
+pub(crate) const HAEN: u8 = 1 << 3;
+
+/// Implemented `IOCON` bits of the MCP23S08: 7..6 and 0 read as 0.
+pub(crate) const IOCON_BITS: u8 = 0x3E;
+
+/// Scratch patterns written to `DEFVAL` by `probe`.
+pub(crate) const PROBE_PATTERNS: [u8; 2] = [0xA5, 0x5A];
+
+/// MCP23S08 register addresses (names follow the datasheet).
 #[repr(u8)]
-#[derive(Clone, Copy)]
-enum Reg {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Reg {
     IODIR = 0x00,
     IPOL = 0x01,
     GPINTEN = 0x02,
@@ -252,73 +1083,210 @@ enum Reg {
     OLAT = 0x0A,
 }
 
+impl Reg {
+    /// All registers in address order.
+    pub const ALL: [Reg; 11] = [
+        Reg::IODIR,
+        Reg::IPOL,
+        Reg::GPINTEN,
+        Reg::DEFVAL,
+        Reg::INTCON,
+        Reg::IOCON,
+        Reg::GPPU,
+        Reg::INTF,
+        Reg::INTCAP,
+        Reg::GPIO,
+        Reg::OLAT,
+    ];
+
+    /// Datasheet name, e.g. `"IODIR"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Reg::IODIR => "IODIR",
+            Reg::IPOL => "IPOL",
+            Reg::GPINTEN => "GPINTEN",
+            Reg::DEFVAL => "DEFVAL",
+            Reg::INTCON => "INTCON",
+            Reg::IOCON => "IOCON",
+            Reg::GPPU => "GPPU",
+            Reg::INTF => "INTF",
+            Reg::INTCAP => "INTCAP",
+            Reg::GPIO => "GPIO",
+            Reg::OLAT => "OLAT",
+        }
+    }
+}
+
+/// Snapshot of all registers, from [`Mcp23s08::dump`].
+///
+/// `Display` prints one decoded line per register:
+///
+/// ```text
+/// IODIR   0xf0  inputs: P4 P5 P6 P7
+/// IOCON   0x08  HAEN
+/// GPIO    0xe1  high: P0 P5 P6 P7
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterDump(pub [u8; 11]);
+
+impl RegisterDump {
+    pub fn get(&self, reg: Reg) -> u8 {
+        self.0[reg as usize]
+    }
+
+    /// Decoded line for one register, without the trailing newline.
+    pub fn line(&self, reg: Reg) -> impl core::fmt::Display + '_ {
+        DumpLine(self, reg)
+    }
+}
+
+struct DumpLine<'a>(&'a RegisterDump, Reg);
+
+impl core::fmt::Display for DumpLine<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const IOCON_BITS: [(u8, &str); 5] = [
+            (1 << 5, "SEQOP"),
+            (1 << 4, "DISSLW"),
+            (HAEN, "HAEN"),
+            (1 << 2, "ODR"),
+            (1 << 1, "INTPOL"),
+        ];
+        let DumpLine(dump, reg) = *self;
+        let value = dump.get(reg);
+        write!(f, "{:<8}{value:#04x}", reg.name())?;
+        let label = match reg {
+            Reg::IODIR => "inputs",
+            Reg::IPOL => "inverted",
+            Reg::GPINTEN => "interrupt",
+            Reg::INTCON => "compare to DEFVAL",
+            Reg::GPPU => "pull-up",
+            Reg::INTF => "pending",
+            Reg::DEFVAL | Reg::INTCAP | Reg::GPIO | Reg::OLAT => "high",
+            Reg::IOCON => {
+                for (bit, name) in IOCON_BITS {
+                    if value & bit != 0 {
+                        write!(f, "  {name}")?;
+                    }
+                }
+                return Ok(());
+            }
+        };
+        write!(f, "  {label}:")?;
+        if value == 0 {
+            f.write_str(" -")?;
+        }
+        for pin in PinSet::from(value).iter() {
+            write!(f, " {pin:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for reg in Reg::ALL {
+            writeln!(f, "{}", self.line(reg))?;
+        }
+        Ok(())
+    }
+}
+
 impl<E: Debug> DigitalError for Error<E> {
+    /// `digital::ErrorKind` has no finer categories; use
+    /// [`embedded_hal::spi::Error::kind`] for the bus error kind.
     #[inline]
     fn kind(&self) -> ErrorKind {
         ErrorKind::Other
     }
 }
 
-pub struct GpioPin<'a, SPI> {
-    dev: &'a mut Mcp23s08<SPI>,
-    pin: Pin,
+impl<E: embedded_hal::spi::Error> embedded_hal::spi::Error for Error<E> {
+    #[inline]
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        match self {
+            Error::Bus { source, .. } => source.kind(),
+            _ => embedded_hal::spi::ErrorKind::Other,
+        }
+    }
 }
 
-impl<'a, SPI, E> embedded_hal::digital::ErrorType for GpioPin<'a, SPI>
-where
-    SPI: SpiDevice<Error = E>,
-    E: Debug,
-{
-    type Error = Error<E>;
+impl<E: embedded_hal::i2c::Error> embedded_hal::i2c::Error for Error<E> {
+    #[inline]
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        match self {
+            Error::Bus { source, .. } => source.kind(),
+            _ => embedded_hal::i2c::ErrorKind::Other,
+        }
+    }
 }
 
-impl<'a, SPI, E> embedded_hal::digital::InputPin for GpioPin<'a, SPI>
+/// Pin handle returned by [`Mcp23s08::pin`].
+pub type GpioPin<'a, B, D = NoDelay> = ExpanderPin<&'a mut Mcp23s08<B, D>>;
+
+impl<B, D, E> IoExpander for Mcp23s08<B, D>
 where
-    SPI: SpiDevice<Error = E>,
+    B: Transport<Error = E>,
+    D: DelayNs,
     E: Debug,
 {
-    fn is_high(&mut self) -> Result<bool, Self::Error> {
-        self.dev.read_pin(self.pin)
+    type Error = Error<E>;
+
+    const WIDTH: u8 = 8;
+
+    fn read_port(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08::read_port(self)
     }
-    fn is_low(&mut self) -> Result<bool, Self::Error> {
-        Ok(!self.is_high()?)
+
+    fn output_latch(&self) -> u8 {
+        self.shadow.olat()
     }
-}
 
-impl<'a, SPI, E> embedded_hal::digital::OutputPin for GpioPin<'a, SPI>
-where
-    SPI: SpiDevice<Error = E>,
-    E: Debug,
-{
-    fn set_high(&mut self) -> Result<(), Self::Error> {
-        self.dev.write_pin(self.pin, true)
+    fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
+        Mcp23s08::write_olat(self, value)
     }
-    fn set_low(&mut self) -> Result<(), Self::Error> {
-        self.dev.write_pin(self.pin, false)
+
+    fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Error<E>> {
+        Mcp23s08::modify_port(self, mask, value)
     }
-}
 
-impl<'a, SPI, E> embedded_hal::digital::StatefulOutputPin for GpioPin<'a, SPI>
-where
-    SPI: SpiDevice<Error = E>,
-    E: Debug,
-{
-    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
-        Ok(self.dev.olat & self.pin.bit() != 0)
+    fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Error<E>> {
+        Mcp23s08::set_port_direction(self, inputs)
     }
-    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
-        Ok(!self.is_set_high()?)
+
+    fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        Mcp23s08::modify_port_direction(self, mask, inputs)
     }
-}
 
-impl<'a, SPI, E> GpioPin<'a, SPI>
-where
-    SPI: SpiDevice<Error = E>,
-    E: Debug,
-{
-    pub fn toggle(&mut self) -> Result<(), Error<E>> {
-        let current = self.dev.read_pin(self.pin)?;
+    fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        Mcp23s08::set_port_pullups(self, mask)
+    }
+
+    fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
+        Mcp23s08::set_pin_pullup(self, pin, enable)
+    }
+
+    fn read_interrupt_flags(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08::read_interrupt_flags(self)
+    }
+
+    fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08::read_interrupt_capture(self)
+    }
+
+    fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
+        Mcp23s08::read_pin(self, pin)
+    }
+
+    fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        Mcp23s08::write_pin(self, pin, high)
+    }
 
-        self.dev.write_pin(self.pin, !current)
+    fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        Mcp23s08::set_pin_direction(self, pin, input)
     }
 }
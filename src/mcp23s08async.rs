@@ -1,111 +1,323 @@
 use core::fmt::Debug;
-
-use embedded_hal_async::spi::{Operation, SpiDevice};
-
-#[derive(Debug)]
-pub enum Error<SpiE> {
-    Spi(SpiE),
-    BadAddress,
+use core::ops::ControlFlow;
+
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::health::Shadow;
+pub use crate::interlock::{Interlock, Resolve, Rule, Violation};
+use crate::mcp23s08::{HAEN, IOCON_BITS, PROBE_PATTERNS};
+use crate::policy::Policy;
+use crate::transport::{RegAccess, i2c_hw_addr};
+use crate::watchdog::Watchdog;
+
+pub use crate::expander::{AsyncExpanderPin, AsyncIoExpander};
+pub use crate::health::{HealthCheck, ResetDetect};
+pub use crate::mcp23s08::{
+    Access, Error, InterruptMode, InvalidPin, Pin, PinSet, PinSetIter, Polarity, Reg,
+    RegisterDump,
+};
+pub use crate::retry::{NoDelay, RetryPolicy, RetryStats};
+pub use crate::stats::{ErrorCounts, Stats};
+pub use crate::transport::{AsyncTransport, BusErrorKind, I2cBus};
+pub use crate::verify::{VerifyMode, VerifyPolicy};
+pub use crate::watchdog::{WatchdogConfig, WatchdogFault};
+
+pub struct Mcp23s08async<B, D = NoDelay> {
+    bus: B,
+    hw_addr: u8,
+    shadow: Shadow,
+    policy: Policy,
+    delay: D,
+    interlock: Option<Interlock>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Pin {
-    P0,
-    P1,
-    P2,
-    P3,
-    P4,
-    P5,
-    P6,
-    P7,
-}
+/// Async counterpart of [`Mcp23008`](crate::mcp23s08::Mcp23008).
+pub type Mcp23008async<I2C, D = NoDelay> = Mcp23s08async<I2cBus<I2C>, D>;
 
-impl Pin {
-    #[inline]
-    fn bit(self) -> u8 {
-        1u8 << (self as u8)
+impl<SPI, E> Mcp23s08async<SPI>
+where
+    SPI: SpiDevice<Error = E>,
+    E: SpiError,
+{
+    pub async fn new(spi: SPI, hw_addr: u8) -> Result<Self, Error<E>> {
+        if hw_addr > 3 {
+            return Err(Error::BadAddress);
+        }
+        Self::init(spi, hw_addr).await
     }
-}
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Polarity {
-    Normal,
-    Inverted,
+    /// See [`Mcp23s08::scan`](crate::mcp23s08::Mcp23s08::scan).
+    pub async fn scan(spi: &mut SPI) -> Result<[bool; 4], Error<E>> {
+        let mut found = [false; 4];
+        let mut dev = Mcp23s08async::detached(spi, 0);
+        dev.enable_addressing().await?;
+        for (hw_addr, slot) in found.iter_mut().enumerate() {
+            dev.hw_addr = hw_addr as u8;
+            match dev.probe().await {
+                Ok(()) => *slot = true,
+                Err(Error::NotPresent) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(found)
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum InterruptMode {
-    OnChange,
-    CompareToDefault,
+impl<I2C, E> Mcp23008async<I2C>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+{
+    /// See [`Mcp23008::new_i2c`](crate::mcp23s08::Mcp23s08::new_i2c).
+    pub async fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Self::init(I2cBus::new(i2c), hw_addr).await
+    }
 }
 
-pub struct Mcp23s08async<SPI> {
-    spi: SPI,
-    hw_addr: u8,
-    olat: u8,
-    iodir: u8,
+impl<B, E> Mcp23s08async<B>
+where
+    B: AsyncTransport<Error = E>,
+{
+    fn detached(bus: B, hw_addr: u8) -> Self {
+        Self {
+            bus,
+            hw_addr,
+            shadow: Shadow::new(),
+            policy: Policy::new(),
+            delay: NoDelay,
+            interlock: None,
+        }
+    }
+
+    async fn init(bus: B, hw_addr: u8) -> Result<Self, Error<E>> {
+        let mut this = Self::detached(bus, hw_addr);
+        // IOCON в дефолт; адресованным SPI-микросхемам включаем HAEN
+        if hw_addr == 0 || !B::NEEDS_HAEN {
+            this.write_reg(Reg::IOCON, 0x00).await?;
+        } else {
+            let iocon = this.enable_addressing().await?;
+            this.shadow.record_write(Reg::IOCON, iocon);
+        }
+        this.read_reg(Reg::IODIR).await?;
+        this.read_reg(Reg::OLAT).await?;
+        Ok(this)
+    }
 }
 
-impl<SPI, E> Mcp23s08async<SPI>
+impl<B, D, E> Mcp23s08async<B, D>
 where
-    SPI: SpiDevice<Error = E>,
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
 {
-    pub async fn new(mut spi: SPI, hw_addr: u8) -> Result<Self, Error<E>> {
-        if hw_addr > 3 {
-            return Err(Error::BadAddress);
+    /// See [`Mcp23s08::probe`](crate::mcp23s08::Mcp23s08::probe).
+    pub async fn probe(&mut self) -> Result<(), Error<E>> {
+        let saved = self
+            .read_reg(Reg::DEFVAL)
+            .await
+            .map_err(|e| e.absent_on_nack(B::error_kind))?;
+        let mut result = Ok(());
+        for pattern in PROBE_PATTERNS {
+            self.write_reg(Reg::DEFVAL, pattern).await?;
+            if self.read_reg(Reg::DEFVAL).await? != pattern {
+                result = Err(Error::NotPresent);
+                break;
+            }
+        }
+        let canary = self.policy.canary();
+        self.write_reg(Reg::DEFVAL, canary.unwrap_or(saved)).await?;
+        let lost = canary.is_some_and(|canary| canary != saved);
+        if result.is_ok() && self.policy.reset_seen(lost)? {
+            self.restore().await?;
         }
+        result
+    }
 
-        let mut this = Self {
-            spi,
-            hw_addr,
-            olat: 0x00,
-            iodir: 0xFF,
+    /// See [`Mcp23s08::set_health_check`](crate::mcp23s08::Mcp23s08::set_health_check).
+    pub async fn set_health_check(&mut self, check: Option<HealthCheck>) -> Result<(), Error<E>> {
+        self.policy.health.config = None;
+        if let Some(cfg) = check {
+            for reg in Shadow::RESTORE_ORDER {
+                self.read_reg(reg).await?;
+            }
+            if let ResetDetect::Canary(value) = cfg.detect {
+                self.write_reg(Reg::DEFVAL, value).await?;
+            }
+        }
+        self.policy.health.config = check;
+        Ok(())
+    }
+
+    /// See [`Mcp23s08::check`](crate::mcp23s08::Mcp23s08::check).
+    pub async fn check(&mut self) -> Result<bool, Error<E>> {
+        let Some(reg) = self.policy.detect_reg() else {
+            return Ok(false);
         };
+        let changed = self.read_raw(reg).await? != self.shadow.get(reg);
+        if !self.policy.reset_seen(changed)? {
+            return Ok(false);
+        }
+        self.restore().await?;
+        Ok(true)
+    }
 
-        // IOCON в дефолт
-        this.write_reg(Reg::IOCON, 0x00).await?;
-        this.iodir = this.read_reg(Reg::IODIR).await?;
-        this.olat = this.read_reg(Reg::OLAT).await?;
-        Ok(this)
+    /// See [`Mcp23s08::restore`](crate::mcp23s08::Mcp23s08::restore).
+    pub async fn restore(&mut self) -> Result<(), Error<E>> {
+        if B::NEEDS_HAEN && self.hw_addr != 0 {
+            self.enable_addressing().await?;
+        }
+        for reg in Shadow::RESTORE_ORDER {
+            self.write_checked(reg, self.shadow.get(reg)).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` once after each detected reset.
+    pub fn take_reset_event(&mut self) -> bool {
+        core::mem::take(&mut self.policy.health.pending_event)
+    }
+
+    /// Number of resets detected since the driver was created.
+    pub fn reset_count(&self) -> u32 {
+        self.policy.health.resets
+    }
+
+    /// See [`Mcp23s08::with_delay`](crate::mcp23s08::Mcp23s08::with_delay).
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s08async<B, D2> {
+        Mcp23s08async {
+            bus: self.bus,
+            hw_addr: self.hw_addr,
+            shadow: self.shadow,
+            policy: self.policy,
+            delay,
+            interlock: self.interlock,
+        }
+    }
+
+    /// See [`Mcp23s08::set_retry_policy`](crate::mcp23s08::Mcp23s08::set_retry_policy).
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.policy.retry = policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.policy.retry
+    }
+
+    pub fn retry_stats(&self) -> RetryStats {
+        self.policy.retry_stats
+    }
+
+    pub fn reset_retry_stats(&mut self) {
+        self.policy.retry_stats = RetryStats::default();
+    }
+
+    /// See [`Mcp23s08::stats`](crate::mcp23s08::Mcp23s08::stats).
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.policy.stats
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.policy.stats = Stats::default();
+    }
+
+    /// See [`Mcp23s08::set_watchdog`](crate::mcp23s08::Mcp23s08::set_watchdog).
+    pub fn set_watchdog(&mut self, config: Option<WatchdogConfig>, now_ms: u64) {
+        self.policy.watchdog = Watchdog::new(config, now_ms);
+    }
+
+    /// See [`Mcp23s08::feed`](crate::mcp23s08::Mcp23s08::feed).
+    pub fn feed(&mut self, now_ms: u64) {
+        self.policy.watchdog.last_feed = now_ms;
+    }
+
+    /// See [`Mcp23s08::poll_watchdog`](crate::mcp23s08::Mcp23s08::poll_watchdog).
+    pub async fn poll_watchdog(&mut self, now_ms: u64) -> Result<bool, Error<E>> {
+        let Some(cfg) = self.policy.poll_watchdog(now_ms) else {
+            return Ok(false);
+        };
+        self.drive_safe_state(cfg).await?;
+        Ok(true)
+    }
+
+    /// See [`Mcp23s08::watchdog_fault`](crate::mcp23s08::Mcp23s08::watchdog_fault).
+    pub fn watchdog_fault(&self) -> Option<WatchdogFault> {
+        self.policy.watchdog.fault
+    }
+
+    /// See [`Mcp23s08::clear_watchdog_fault`](crate::mcp23s08::Mcp23s08::clear_watchdog_fault).
+    pub fn clear_watchdog_fault(&mut self, now_ms: u64) {
+        self.policy.watchdog.clear(now_ms);
+    }
+
+    /// See [`Mcp23s08::set_interlock`](crate::mcp23s08::Mcp23s08::set_interlock).
+    pub fn set_interlock(&mut self, interlock: Option<Interlock>) {
+        self.interlock = interlock;
+    }
+
+    pub fn interlock(&self) -> Option<Interlock> {
+        self.interlock
+    }
+
+    /// See [`Mcp23s08::set_verify`](crate::mcp23s08::Mcp23s08::set_verify).
+    pub fn set_verify(&mut self, policy: VerifyPolicy) {
+        self.policy.verify = policy;
+    }
+
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        self.policy.verify
     }
 
-    pub fn pin<'a>(&'a mut self, pin: Pin) -> GpioPin<'a, SPI> {
-        GpioPin { dev: self, pin }
+    pub fn pin<'a>(&'a mut self, pin: Pin) -> GpioPin<'a, B, D> {
+        AsyncExpanderPin::new(self, pin)
     }
 
     pub async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        let mut iodir = self.shadow.iodir();
         if input {
-            self.iodir |= pin.bit();
+            iodir |= pin.mask();
         } else {
-            self.iodir &= !pin.bit();
+            iodir &= !pin.mask();
         }
-        self.write_reg(Reg::IODIR, self.iodir).await
+        self.write_reg(Reg::IODIR, iodir).await
     }
 
-    pub async fn set_port_direction(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.iodir = mask;
-        self.write_reg(Reg::IODIR, mask).await
+    pub async fn set_port_direction(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IODIR, mask.into().bits()).await
+    }
+
+    /// See [`Mcp23s08::modify_port_direction`](crate::mcp23s08::Mcp23s08::modify_port_direction).
+    pub async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let iodir = (self.shadow.iodir() & !mask) | (inputs & mask);
+        self.write_reg(Reg::IODIR, iodir).await
     }
 
     pub async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
         let mut gppu = self.read_reg(Reg::GPPU).await?;
         if enable {
-            gppu |= pin.bit();
+            gppu |= pin.mask();
         } else {
-            gppu &= !pin.bit();
+            gppu &= !pin.mask();
         }
         self.write_reg(Reg::GPPU, gppu).await
     }
 
-    pub async fn set_port_pullups(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.write_reg(Reg::GPPU, mask).await
+    pub async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPPU, mask.into().bits()).await
     }
 
     pub async fn set_pin_polarity(&mut self, pin: Pin, pol: Polarity) -> Result<(), Error<E>> {
         let mut ipol = self.read_reg(Reg::IPOL).await?;
         match pol {
-            Polarity::Normal => ipol &= !pin.bit(),
-            Polarity::Inverted => ipol |= pin.bit(),
+            Polarity::Normal => ipol &= !pin.mask(),
+            Polarity::Inverted => ipol |= pin.mask(),
         }
         self.write_reg(Reg::IPOL, ipol).await
     }
@@ -115,26 +327,36 @@ where
     }
 
     pub async fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
-        Ok(self.read_port().await? & pin.bit() != 0)
+        Ok(self.read_port().await? & pin.mask() != 0)
     }
 
     pub async fn write_port(&mut self, value: u8) -> Result<(), Error<E>> {
-        self.olat = value;
-        self.write_reg(Reg::GPIO, value).await
+        self.write_outputs(Reg::GPIO, value).await
     }
 
     pub async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        let mut olat = self.shadow.olat();
         if high {
-            self.olat |= pin.bit();
+            olat |= pin.mask();
         } else {
-            self.olat &= !pin.bit();
+            olat &= !pin.mask();
         }
-        self.write_reg(Reg::GPIO, self.olat).await
+        self.write_outputs(Reg::GPIO, olat).await
     }
 
     pub async fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
-        self.olat = value;
-        self.write_reg(Reg::OLAT, value).await
+        self.write_outputs(Reg::OLAT, value).await
+    }
+
+    /// See [`Mcp23s08::modify_port`](crate::mcp23s08::Mcp23s08::modify_port).
+    pub async fn modify_port(
+        &mut self,
+        mask: impl Into<PinSet>,
+        value: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let olat = (self.shadow.olat() & !mask) | (value & mask);
+        self.write_outputs(Reg::OLAT, olat).await
     }
 
     pub async fn set_pin_interrupt_enable(
@@ -144,15 +366,18 @@ where
     ) -> Result<(), Error<E>> {
         let mut gpinten = self.read_reg(Reg::GPINTEN).await?;
         if enable {
-            gpinten |= pin.bit();
+            gpinten |= pin.mask();
         } else {
-            gpinten &= !pin.bit();
+            gpinten &= !pin.mask();
         }
         self.write_reg(Reg::GPINTEN, gpinten).await
     }
 
-    pub async fn set_port_interrupt_enable(&mut self, mask: u8) -> Result<(), Error<E>> {
-        self.write_reg(Reg::GPINTEN, mask).await
+    pub async fn set_port_interrupt_enable(
+        &mut self,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPINTEN, mask.into().bits()).await
     }
 
     pub async fn set_pin_interrupt_mode(
@@ -162,90 +387,286 @@ where
     ) -> Result<(), Error<E>> {
         let mut intcon = self.read_reg(Reg::INTCON).await?;
         match mode {
-            InterruptMode::OnChange => intcon &= !pin.bit(),
-            InterruptMode::CompareToDefault => intcon |= pin.bit(),
+            InterruptMode::OnChange => intcon &= !pin.mask(),
+            InterruptMode::CompareToDefault => intcon |= pin.mask(),
         }
         self.write_reg(Reg::INTCON, intcon).await
     }
 
-    #[inline]
-    fn opcode_write(&self) -> u8 {
-        0x40 | ((self.hw_addr & 0x03) << 1)
+    pub async fn set_port_interrupt_mode(
+        &mut self,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::INTCON, mask.into().bits()).await
+    }
+
+    /// See [`Mcp23s08::set_port_default_compare`](crate::mcp23s08::Mcp23s08::set_port_default_compare).
+    pub async fn set_port_default_compare(&mut self, defval: u8) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
+        self.write_reg(Reg::DEFVAL, defval).await
+    }
+
+    pub async fn read_interrupt_flags(&mut self) -> Result<u8, Error<E>> {
+        self.read_reg(Reg::INTF).await
     }
 
-    #[inline]
-    fn opcode_read(&self) -> u8 {
-        0x40 | ((self.hw_addr & 0x03) << 1) | 1
+    pub async fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::INTCAP).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    pub async fn clear_interrupts(&mut self) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::GPIO).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// See [`Mcp23s08::dump`](crate::mcp23s08::Mcp23s08::dump).
+    pub async fn dump(&mut self) -> Result<RegisterDump, Error<E>> {
+        let mut regs = [0; Reg::ALL.len()];
+        for (value, reg) in regs.iter_mut().zip(Reg::ALL) {
+            *value = self.read_reg(reg).await?;
+        }
+        Ok(RegisterDump(regs))
+    }
+
+    pub async fn set_int_open_drain(&mut self, enable: bool) -> Result<(), Error<E>> {
+        let mut iocon = self.read_reg(Reg::IOCON).await?;
+        const ODR: u8 = 1 << 2;
+        if enable {
+            iocon |= ODR;
+        } else {
+            iocon &= !ODR;
+        }
+        self.write_reg(Reg::IOCON, iocon).await
+    }
+
+    pub async fn set_int_polarity(&mut self, active_high: bool) -> Result<(), Error<E>> {
+        let mut iocon = self.read_reg(Reg::IOCON).await?;
+        const INTPOL: u8 = 1 << 1;
+        if active_high {
+            iocon |= INTPOL;
+        } else {
+            iocon &= !INTPOL;
+        }
+        self.write_reg(Reg::IOCON, iocon).await
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Sets `HAEN` in `IOCON` at address 0, keeping the other bits read
+    /// from there. Returns the value written.
+    async fn enable_addressing(&mut self) -> Result<u8, Error<E>> {
+        let hw_addr = self.hw_addr;
+        self.hw_addr = 0;
+        let result = match self.read_raw(Reg::IOCON).await {
+            Ok(iocon) => self
+                .write_raw(Reg::IOCON, iocon | HAEN)
+                .await
+                .map(|()| iocon | HAEN),
+            Err(e) => Err(e),
+        };
+        self.hw_addr = hw_addr;
+        result
+    }
+
+    /// Output latch write under the interlock table.
+    async fn write_outputs(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        if let Some(interlock) = self.interlock {
+            let plan = interlock
+                .plan(self.shadow.olat(), val)
+                .map_err(Error::Interlock)?;
+            if let Some((step, dead_time_us)) = plan {
+                self.write_reg(reg, step).await?;
+                if dead_time_us > 0 {
+                    self.delay.delay_us(dead_time_us).await;
+                }
+            }
+        }
+        self.write_reg(reg, val).await
     }
 
     async fn write_reg(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
-        let opcode = self.opcode_write();
-        let frame = [opcode, reg as u8, val];
-        let mut ops = [Operation::Write(&frame)];
-        self.spi.transaction(&mut ops).await.map_err(Error::Spi)
+        self.policy.guard(reg)?;
+        let result = self.write_reg_unguarded(reg, val).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn write_reg_unguarded(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        self.write_checked(reg, val).await?;
+        self.shadow.record_write(reg, val);
+        Ok(())
     }
 
     async fn read_reg(&mut self, reg: Reg) -> Result<u8, Error<E>> {
-        let opcode = self.opcode_read();
-        let cmd = [opcode, reg as u8];
+        let result = self.read_reg_unguarded(reg).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn read_reg_unguarded(&mut self, reg: Reg) -> Result<u8, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        let val = self.read_raw(reg).await?;
+        self.shadow.record_read(reg, val);
+        Ok(val)
+    }
+
+    /// Drives the safe state if `result` trips the watchdog. `result` is
+    /// passed through unchanged.
+    async fn trip_on_bus_errors<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        if let Some(cfg) = self.policy.trip_on_bus_errors(&result) {
+            // The bus is failing; the safe state is best effort.
+            let _ = self.drive_safe_state(cfg).await;
+        }
+        result
+    }
+
+    /// Writes the safe output state of a tripped watchdog.
+    async fn drive_safe_state(&mut self, cfg: WatchdogConfig) -> Result<(), Error<E>> {
+        self.write_checked(Reg::OLAT, cfg.safe_olat).await?;
+        self.shadow.record_write(Reg::OLAT, cfg.safe_olat);
+        if let Some(iodir) = cfg.safe_iodir {
+            self.write_checked(Reg::IODIR, iodir).await?;
+            self.shadow.record_write(Reg::IODIR, iodir);
+        }
+        Ok(())
+    }
+
+    /// Writes `reg` and, if the verify policy covers it, reads it back.
+    async fn write_checked(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        let Some(mut readback) = self.policy.readback(reg, IOCON_BITS) else {
+            return self.write_raw(reg, val).await;
+        };
+        loop {
+            self.write_raw(reg, val).await?;
+            let read = self.read_raw(readback.from).await?;
+            if let ControlFlow::Break(result) = self.policy.verify(&mut readback, &[val], &[read]) {
+                return result;
+            }
+        }
+    }
+
+    /// Runs one register access under the retry policy.
+    async fn transact(&mut self, addr: u8, mut access: RegAccess<'_>) -> Result<(), E> {
+        let mut attempt = 0;
+        loop {
+            self.policy.begin(&access, B::frame_sizes);
+            match access.run_async(&mut self.bus, self.hw_addr, addr).await {
+                Ok(()) => {
+                    self.policy.succeeded(attempt);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let Some(backoff_us) = self.policy.failed(attempt, B::error_kind(&e)) else {
+                        return Err(e);
+                    };
+                    if backoff_us > 0 {
+                        self.delay.delay_us(backoff_us).await;
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn write_raw(&mut self, reg: Reg, val: u8) -> Result<(), Error<E>> {
+        self.transact(reg as u8, RegAccess::Write(&[val]))
+            .await
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Write,
+                source,
+            })
+    }
+
+    async fn read_raw(&mut self, reg: Reg) -> Result<u8, Error<E>> {
         let mut byte = [0u8; 1];
-        let mut ops = [Operation::Write(&cmd), Operation::Read(&mut byte)];
-        self.spi.transaction(&mut ops).await.map_err(Error::Spi)?;
+        self.transact(reg as u8, RegAccess::Read(&mut byte))
+            .await
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Read,
+                source,
+            })?;
         Ok(byte[0])
     }
 }
 
-#[repr(u8)]
-#[derive(Clone, Copy)]
-enum Reg {
-    IODIR = 0x00,
-    IPOL = 0x01,
-    GPINTEN = 0x02,
-    DEFVAL = 0x03,
-    INTCON = 0x04,
-    IOCON = 0x05,
-    GPPU = 0x06,
-    INTF = 0x07,
-    INTCAP = 0x08,
-    GPIO = 0x09,
-    OLAT = 0x0A,
-}
-
-pub struct GpioPin<'a, SPI> {
-    dev: &'a mut Mcp23s08async<SPI>,
-    pin: Pin,
-}
+/// Pin handle returned by [`Mcp23s08async::pin`].
+pub type GpioPin<'a, B, D = NoDelay> = AsyncExpanderPin<&'a mut Mcp23s08async<B, D>>;
 
-impl<'a, SPI, E> GpioPin<'a, SPI>
+impl<B, D, E> AsyncIoExpander for Mcp23s08async<B, D>
 where
-    SPI: SpiDevice<Error = E>,
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+    E: Debug,
 {
-    pub async fn is_high(&mut self) -> Result<bool, Error<E>> {
-        self.dev.read_pin(self.pin).await
+    type Error = Error<E>;
+
+    const WIDTH: u8 = 8;
+
+    async fn read_port(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08async::read_port(self).await
+    }
+
+    fn output_latch(&self) -> u8 {
+        self.shadow.olat()
+    }
+
+    async fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
+        Mcp23s08async::write_olat(self, value).await
+    }
+
+    async fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Error<E>> {
+        Mcp23s08async::modify_port(self, mask, value).await
+    }
+
+    async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Error<E>> {
+        Mcp23s08async::set_port_direction(self, inputs).await
+    }
+
+    async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        Mcp23s08async::modify_port_direction(self, mask, inputs).await
+    }
+
+    async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        Mcp23s08async::set_port_pullups(self, mask).await
     }
 
-    pub async fn is_low(&mut self) -> Result<bool, Error<E>> {
-        Ok(!self.is_high().await?)
+    async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
+        Mcp23s08async::set_pin_pullup(self, pin, enable).await
     }
 
-    pub async fn set_high(&mut self) -> Result<(), Error<E>> {
-        self.dev.write_pin(self.pin, true).await
+    async fn read_interrupt_flags(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08async::read_interrupt_flags(self).await
     }
 
-    pub async fn set_low(&mut self) -> Result<(), Error<E>> {
-        self.dev.write_pin(self.pin, false).await
+    async fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
+        Mcp23s08async::read_interrupt_capture(self).await
     }
 
-    pub async fn is_set_high(&mut self) -> Result<bool, Error<E>> {
-        Ok(self.dev.olat & self.pin.bit() != 0)
+    async fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
+        Mcp23s08async::read_pin(self, pin).await
     }
 
-    pub async fn is_set_low(&mut self) -> Result<bool, Error<E>> {
-        Ok(!self.is_set_high().await?)
+    async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        Mcp23s08async::write_pin(self, pin, high).await
     }
 
-    pub async fn toggle(&mut self) -> Result<(), Error<E>> {
-        let current = self.dev.read_pin(self.pin).await?;
-        self.dev.write_pin(self.pin, !current).await
+    async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        Mcp23s08async::set_pin_direction(self, pin, input).await
     }
 }
@@ -0,0 +1,1168 @@
+//! MCP23S17 16-bit SPI expander.
+//!
+//! The MCP23S17 is two MCP23S08 ports (`GPA`, `GPB`) behind one opcode,
+//! with up to eight chips per chip-select. [`Mcp23s17`] reuses the
+//! register names, pin types, error type, retry policy and statistics of
+//! the 8-bit driver. Per-port methods take a [`Port`]; the `*16` methods
+//! treat both ports as one 16-bit port (bit 0..=7 = `GPA0..7`,
+//! bit 8..=15 = `GPB0..7`).
+//!
+//! The register addresses depend on `IOCON.BANK`, see [`Bank`]. With the
+//! paired layout (the power-on default) a 16-bit access is one bus
+//! transaction; with the separate layout it is one per port.
+//!
+//! The I²C MCP23017 has the same registers and uses the same driver, see
+//! [`Mcp23017`].
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Pin, Reg};
+//! use mcp23s08_io::mcp23s17::{Bank, Mcp23s17, Port};
+//! use mcp23s08_io::sim::Mcp23s17Sim;
+//!
+//! let sim = Mcp23s17Sim::new();
+//! let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+//! dev.set_port_direction16(0xFF00).unwrap();
+//! dev.write_olat16(0x00A5).unwrap();
+//! dev.set_bank(Bank::Separate).unwrap();
+//! dev.write_pin(Port::A, Pin::P1, true).unwrap();
+//! assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xA7);
+//! ```
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::ops::ControlFlow;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal::spi::SpiDevice;
+
+pub use crate::expander::{ExpanderPin, IoExpander};
+use crate::health::Shadow;
+pub use crate::health::{HealthCheck, ResetDetect};
+pub use crate::interlock::{Interlock, Resolve, Rule, Violation};
+use crate::mcp23s08::{Access, Error, HAEN, InterruptMode, Pin, PinSet, Polarity, Reg};
+use crate::policy::Policy;
+pub use crate::retry::{NoDelay, RetryPolicy, RetryStats};
+pub use crate::stats::{ErrorCounts, Stats};
+pub use crate::transport::{BusErrorKind, I2cBus, Transport};
+use crate::transport::{RegAccess, i2c_hw_addr};
+pub use crate::verify::{VerifyMode, VerifyPolicy};
+use crate::watchdog::Watchdog;
+pub use crate::watchdog::{WatchdogConfig, WatchdogFault};
+
+/// `IOCON.BANK`: registers of each port grouped in their own block.
+pub(crate) const BANK: u8 = 1 << 7;
+/// `IOCON.MIRROR`: `INTA` and `INTB` internally connected.
+pub(crate) const MIRROR: u8 = 1 << 6;
+pub(crate) const ODR: u8 = 1 << 2;
+pub(crate) const INTPOL: u8 = 1 << 1;
+/// `IOCON.INTCC` (MCP23x18 only): interrupt cleared by reading `INTCAP`
+/// instead of `GPIO`.
+pub(crate) const INTCC: u8 = 1 << 0;
+
+/// One of the two 8-bit ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Port {
+    /// `GPA0..7`, bits 0..=7 of the 16-bit port.
+    A,
+    /// `GPB0..7`, bits 8..=15 of the 16-bit port.
+    B,
+}
+
+impl Port {
+    pub const ALL: [Port; 2] = [Port::A, Port::B];
+}
+
+/// Register address layout selected by `IOCON.BANK`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Bank {
+    /// `BANK = 0` (power-on): the A and B registers alternate
+    /// (`IODIRA` 0x00, `IODIRB` 0x01, ... `OLATB` 0x15).
+    #[default]
+    Paired,
+    /// `BANK = 1`: port A registers at 0x00..=0x0A, port B at 0x10..=0x1A,
+    /// each block laid out like an MCP23S08.
+    Separate,
+}
+
+impl Bank {
+    /// Address of `reg` of `port` in this layout.
+    #[inline]
+    pub const fn addr(self, reg: Reg, port: Port) -> u8 {
+        match self {
+            Bank::Paired => (reg as u8) * 2 + port as u8,
+            Bank::Separate => (port as u8) << 4 | reg as u8,
+        }
+    }
+
+    /// Register index and port behind `addr`, `None` for unused addresses.
+    pub(crate) const fn decode(self, addr: u8) -> Option<(u8, Port)> {
+        let (reg, port) = match self {
+            Bank::Paired => (addr / 2, addr % 2),
+            Bank::Separate => (addr & 0x0F, addr >> 4),
+        };
+        if reg > Reg::OLAT as u8 {
+            return None;
+        }
+        match port {
+            0 => Some((reg, Port::A)),
+            1 => Some((reg, Port::B)),
+            _ => None,
+        }
+    }
+
+    pub(crate) const fn from_iocon(iocon: u8) -> Self {
+        if iocon & BANK != 0 {
+            Bank::Separate
+        } else {
+            Bank::Paired
+        }
+    }
+
+    #[inline]
+    pub(crate) const fn iocon_bit(self) -> u8 {
+        match self {
+            Bank::Paired => 0,
+            Bank::Separate => BANK,
+        }
+    }
+}
+
+/// Member of the MCP23x17/MCP23x18 family driven by [`Mcp23s17`]. Decides
+/// at compile time which chip features the driver offers.
+pub trait Variant: sealed::Sealed {}
+
+/// Variants with push-pull outputs; only they hand out [`GpioPin`]s.
+pub trait PushPull: Variant {}
+
+/// Variants whose address is strapped with the `A2..A0` pins and enabled by
+/// `IOCON.HAEN`.
+pub trait HardwareAddress: Variant {}
+
+/// MCP23S17 and MCP23017.
+pub enum Mcp23x17 {}
+
+/// MCP23S18 and MCP23018: open-drain outputs, `IOCON.INTCC`, no `HAEN`.
+/// See [`crate::mcp23s18`].
+pub enum Mcp23x18 {}
+
+impl Variant for Mcp23x17 {}
+impl PushPull for Mcp23x17 {}
+impl HardwareAddress for Mcp23x17 {}
+impl Variant for Mcp23x18 {}
+
+mod sealed {
+    pub trait Sealed {
+        /// Implemented `IOCON` bits, the ones a verified write compares.
+        const IOCON_BITS: u8;
+    }
+    impl Sealed for super::Mcp23x17 {
+        // Bit 0 is unimplemented.
+        const IOCON_BITS: u8 = 0xFE;
+    }
+    impl Sealed for super::Mcp23x18 {
+        // INTCC in bit 0; no DISSLW (bit 4) or HAEN (bit 3).
+        const IOCON_BITS: u8 = 0xE7;
+    }
+}
+
+pub struct Mcp23s17<B, D = NoDelay, C = Mcp23x17> {
+    bus: B,
+    hw_addr: u8,
+    bank: Bank,
+    variant: PhantomData<C>,
+    /// Port A, port B. `IOCON` is kept in both.
+    shadow: [Shadow; 2],
+    policy: Policy<u16>,
+    delay: D,
+    /// Port A, port B.
+    interlock: [Option<Interlock>; 2],
+}
+
+/// MCP23017: the I²C sibling of the MCP23S17, with the same API.
+pub type Mcp23017<I2C, D = NoDelay> = Mcp23s17<I2cBus<I2C>, D>;
+
+impl<SPI, E> Mcp23s17<SPI>
+where
+    SPI: SpiDevice<Error = E>,
+    E: SpiError,
+{
+    /// Driver for the chip at `hw_addr` (0..=7), which must still be in the
+    /// power-on [`Bank::Paired`] layout.
+    pub fn new(spi: SPI, hw_addr: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank(spi, hw_addr, Bank::Paired)
+    }
+
+    /// Driver for a chip that is already in `bank` layout, e.g. after an MCU
+    /// reset that did not reset the expander. The layout is kept. At
+    /// `hw_addr` 1..=7 the chip must still have `HAEN` set from then: the
+    /// `HAEN` broadcast only reaches chips in the power-on layout.
+    pub fn new_in_bank(spi: SPI, hw_addr: u8, bank: Bank) -> Result<Self, Error<E>> {
+        if hw_addr > 7 {
+            return Err(Error::BadAddress);
+        }
+        Self::init(spi, hw_addr, bank)
+    }
+}
+
+impl<I2C, E> Mcp23017<I2C>
+where
+    I2C: embedded_hal::i2c::I2c<Error = E>,
+{
+    /// MCP23017 at the 7-bit I²C `address` (0x20..=0x27), see [`Mcp23s17::new`].
+    pub fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank_i2c(i2c, address, Bank::Paired)
+    }
+
+    /// See [`Mcp23s17::new_in_bank`].
+    pub fn new_in_bank_i2c(i2c: I2C, address: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Self::init(I2cBus::new(i2c), hw_addr, bank)
+    }
+}
+
+impl<B, E, C> Mcp23s17<B, NoDelay, C>
+where
+    B: Transport<Error = E>,
+    C: Variant,
+{
+    pub(crate) fn init(bus: B, hw_addr: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let mut this = Self {
+            bus,
+            hw_addr,
+            bank,
+            variant: PhantomData,
+            shadow: [Shadow::new(); 2],
+            policy: Policy::new(),
+            delay: NoDelay,
+            interlock: [None; 2],
+        };
+        this.attach()?;
+        Ok(this)
+    }
+}
+
+impl<B, D, E, C> Mcp23s17<B, D, C>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    C: Variant,
+{
+    /// Register layout the driver is addressing.
+    pub fn bank(&self) -> Bank {
+        self.bank
+    }
+
+    /// Hardware address (`A2..A0`) of the chip being driven.
+    pub fn hw_addr(&self) -> u8 {
+        self.hw_addr
+    }
+
+    /// Switches the chip to `bank` layout. Later accesses use the new
+    /// addresses.
+    pub fn set_bank(&mut self, bank: Bank) -> Result<(), Error<E>> {
+        let iocon = (self.iocon() & !BANK) | bank.iocon_bit();
+        self.write_iocon(iocon)
+    }
+
+    /// With `mirror` set, `INTA` and `INTB` both assert on an interrupt of
+    /// either port; otherwise each pin follows its own port.
+    pub fn set_mirror(&mut self, mirror: bool) -> Result<(), Error<E>> {
+        self.update_iocon(MIRROR, mirror)
+    }
+
+    pub fn set_int_open_drain(&mut self, enable: bool) -> Result<(), Error<E>> {
+        self.update_iocon(ODR, enable)
+    }
+
+    pub fn set_int_polarity(&mut self, active_high: bool) -> Result<(), Error<E>> {
+        self.update_iocon(INTPOL, active_high)
+    }
+
+    pub fn set_pin_direction(&mut self, port: Port, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        let mut iodir = self.shadow(port).iodir();
+        if input {
+            iodir |= pin.mask();
+        } else {
+            iodir &= !pin.mask();
+        }
+        self.write_reg(Reg::IODIR, port, iodir)
+    }
+
+    pub fn set_port_direction(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IODIR, port, mask.into().bits())
+    }
+
+    /// See [`Mcp23s08::modify_port_direction`](crate::mcp23s08::Mcp23s08::modify_port_direction).
+    pub fn modify_port_direction(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let iodir = (self.shadow(port).iodir() & !mask) | (inputs & mask);
+        self.write_reg(Reg::IODIR, port, iodir)
+    }
+
+    pub fn set_pin_pullup(&mut self, port: Port, pin: Pin, enable: bool) -> Result<(), Error<E>> {
+        let mut gppu = self.read_reg(Reg::GPPU, port)?;
+        if enable {
+            gppu |= pin.mask();
+        } else {
+            gppu &= !pin.mask();
+        }
+        self.write_reg(Reg::GPPU, port, gppu)
+    }
+
+    pub fn set_port_pullups(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPPU, port, mask.into().bits())
+    }
+
+    pub fn set_pin_polarity(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        pol: Polarity,
+    ) -> Result<(), Error<E>> {
+        let mut ipol = self.read_reg(Reg::IPOL, port)?;
+        match pol {
+            Polarity::Normal => ipol &= !pin.mask(),
+            Polarity::Inverted => ipol |= pin.mask(),
+        }
+        self.write_reg(Reg::IPOL, port, ipol)
+    }
+
+    pub fn read_port(&mut self, port: Port) -> Result<u8, Error<E>> {
+        self.read_reg(Reg::GPIO, port)
+    }
+
+    pub fn read_pin(&mut self, port: Port, pin: Pin) -> Result<bool, Error<E>> {
+        Ok(self.read_port(port)? & pin.mask() != 0)
+    }
+
+    pub fn write_port(&mut self, port: Port, value: u8) -> Result<(), Error<E>> {
+        self.write_outputs(Reg::GPIO, port, value)
+    }
+
+    pub fn write_pin(&mut self, port: Port, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        let mut olat = self.shadow(port).olat();
+        if high {
+            olat |= pin.mask();
+        } else {
+            olat &= !pin.mask();
+        }
+        self.write_outputs(Reg::GPIO, port, olat)
+    }
+
+    pub fn write_olat(&mut self, port: Port, value: u8) -> Result<(), Error<E>> {
+        self.write_outputs(Reg::OLAT, port, value)
+    }
+
+    /// Sets the pins in `mask` to the matching bits of `value` and leaves
+    /// the others as they are, in a single `OLAT` write.
+    pub fn modify_port(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+        value: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let olat = (self.shadow(port).olat() & !mask) | (value & mask);
+        self.write_outputs(Reg::OLAT, port, olat)
+    }
+
+    pub fn set_pin_interrupt_enable(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        enable: bool,
+    ) -> Result<(), Error<E>> {
+        let mut gpinten = self.read_reg(Reg::GPINTEN, port)?;
+        if enable {
+            gpinten |= pin.mask();
+        } else {
+            gpinten &= !pin.mask();
+        }
+        self.write_reg(Reg::GPINTEN, port, gpinten)
+    }
+
+    pub fn set_port_interrupt_enable(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPINTEN, port, mask.into().bits())
+    }
+
+    pub fn set_pin_interrupt_mode(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        mode: InterruptMode,
+    ) -> Result<(), Error<E>> {
+        let mut intcon = self.read_reg(Reg::INTCON, port)?;
+        match mode {
+            InterruptMode::OnChange => intcon &= !pin.mask(),
+            InterruptMode::CompareToDefault => intcon |= pin.mask(),
+        }
+        self.write_reg(Reg::INTCON, port, intcon)
+    }
+
+    pub fn set_port_interrupt_mode(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::INTCON, port, mask.into().bits())
+    }
+
+    /// See [`Mcp23s08::set_port_default_compare`](crate::mcp23s08::Mcp23s08::set_port_default_compare).
+    pub fn set_port_default_compare(&mut self, port: Port, defval: u8) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
+        self.write_reg(Reg::DEFVAL, port, defval)
+    }
+
+    pub fn read_interrupt_flags(&mut self, port: Port) -> Result<u8, Error<E>> {
+        self.read_reg(Reg::INTF, port)
+    }
+
+    pub fn read_interrupt_capture(&mut self, port: Port) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::INTCAP, port)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    pub fn clear_interrupts(&mut self, port: Port) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::GPIO, port)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// Direction of all 16 pins (`1` = input).
+    pub fn set_port_direction16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::IODIR, mask)
+    }
+
+    /// Sets the direction of the pins in `mask` (`1` in `inputs` = input)
+    /// and leaves the others as they are.
+    pub fn modify_port_direction16(&mut self, mask: u16, inputs: u16) -> Result<(), Error<E>> {
+        let iodir = (self.shadow16(Reg::IODIR) & !mask) | (inputs & mask);
+        self.write_reg16(Reg::IODIR, iodir)
+    }
+
+    pub fn set_port_pullups16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::GPPU, mask)
+    }
+
+    /// Input polarity of all 16 pins (`1` = inverted).
+    pub fn set_port_polarity16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::IPOL, mask)
+    }
+
+    pub fn read_port16(&mut self) -> Result<u16, Error<E>> {
+        self.read_reg16(Reg::GPIO)
+    }
+
+    pub fn write_port16(&mut self, value: u16) -> Result<(), Error<E>> {
+        self.write_outputs16(Reg::GPIO, value)
+    }
+
+    pub fn write_olat16(&mut self, value: u16) -> Result<(), Error<E>> {
+        self.write_outputs16(Reg::OLAT, value)
+    }
+
+    /// Sets the pins in `mask` to the matching bits of `value` and leaves
+    /// the others as they are. Only ports with a changed latch are written.
+    pub fn modify_port16(&mut self, mask: u16, value: u16) -> Result<(), Error<E>> {
+        let old = self.shadow16(Reg::OLAT);
+        let olat = (old & !mask) | (value & mask);
+        match changed_ports(old, olat) {
+            [true, true] => self.write_outputs16(Reg::OLAT, olat),
+            [true, false] => self.write_outputs(Reg::OLAT, Port::A, olat as u8),
+            [false, true] => self.write_outputs(Reg::OLAT, Port::B, (olat >> 8) as u8),
+            [false, false] => Ok(()),
+        }
+    }
+
+    pub fn set_port_interrupt_enable16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::GPINTEN, mask)
+    }
+
+    /// Interrupt mode of all 16 pins (`1` = compare to `DEFVAL`).
+    pub fn set_port_interrupt_mode16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::INTCON, mask)
+    }
+
+    pub fn set_port_default_compare16(&mut self, defval: u16) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
+        self.write_reg16(Reg::DEFVAL, defval)
+    }
+
+    pub fn read_interrupt_flags16(&mut self) -> Result<u16, Error<E>> {
+        self.read_reg16(Reg::INTF)
+    }
+
+    pub fn read_interrupt_capture16(&mut self) -> Result<u16, Error<E>> {
+        let value = self.read_reg16(Reg::INTCAP)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    pub fn clear_interrupts16(&mut self) -> Result<u16, Error<E>> {
+        let value = self.read_reg16(Reg::GPIO)?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// Installs (or with `None` removes) reset detection, see
+    /// [`Mcp23s08::set_health_check`](crate::mcp23s08::Mcp23s08::set_health_check).
+    /// A [`ResetDetect::Canary`] goes into `DEFVAL` of both ports.
+    pub fn set_health_check(&mut self, check: Option<HealthCheck>) -> Result<(), Error<E>> {
+        self.policy.health.config = None;
+        if let Some(cfg) = check {
+            self.read_reg(Reg::IOCON, Port::A)?;
+            for reg in CONFIG_ORDER {
+                self.read_reg16(reg)?;
+            }
+            if let ResetDetect::Canary(value) = cfg.detect {
+                self.write_reg16(Reg::DEFVAL, u16::from_le_bytes([value; 2]))?;
+            }
+        }
+        self.policy.health.config = check;
+        Ok(())
+    }
+
+    /// Compares the detection register of both ports with the cache, see
+    /// [`Mcp23s08::check`](crate::mcp23s08::Mcp23s08::check).
+    pub fn check(&mut self) -> Result<bool, Error<E>> {
+        let Some(reg) = self.policy.detect_reg() else {
+            return Ok(false);
+        };
+        let changed = self.read_raw16(reg)? != self.shadow16(reg);
+        if !self.policy.reset_seen(changed)? {
+            return Ok(false);
+        }
+        self.restore()?;
+        Ok(true)
+    }
+
+    /// Writes the cached configuration and output latches back to a chip
+    /// that came out of reset.
+    ///
+    /// A reset chip is in the [`Bank::Paired`] layout, so `IOCON` is written
+    /// at its paired address first, after re-enabling `HAEN` through
+    /// address 0 for SPI chips at `hw_addr` 1..=7. That broadcast leaves a
+    /// chip at address 0 alone whichever layout it uses.
+    pub fn restore(&mut self) -> Result<(), Error<E>> {
+        let iocon = self.iocon();
+        self.bank = Bank::Paired;
+        if B::NEEDS_HAEN && self.hw_addr != 0 {
+            self.enable_addressing()?;
+        }
+        self.write_checked(Reg::IOCON, Port::A, &[iocon])?;
+        for reg in CONFIG_ORDER {
+            self.write_checked16(reg, self.shadow16(reg))?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` once after each detected reset.
+    pub fn take_reset_event(&mut self) -> bool {
+        core::mem::take(&mut self.policy.health.pending_event)
+    }
+
+    /// Number of resets detected since the driver was created.
+    pub fn reset_count(&self) -> u32 {
+        self.policy.health.resets
+    }
+
+    /// Replaces the delay used for retry backoff and interlock dead-time.
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s17<B, D2, C> {
+        Mcp23s17 {
+            bus: self.bus,
+            hw_addr: self.hw_addr,
+            bank: self.bank,
+            variant: PhantomData,
+            shadow: self.shadow,
+            policy: self.policy,
+            delay,
+            interlock: self.interlock,
+        }
+    }
+
+    /// See [`Mcp23s08::set_retry_policy`](crate::mcp23s08::Mcp23s08::set_retry_policy).
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.policy.retry = policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.policy.retry
+    }
+
+    pub fn retry_stats(&self) -> RetryStats {
+        self.policy.retry_stats
+    }
+
+    pub fn reset_retry_stats(&mut self) {
+        self.policy.retry_stats = RetryStats::default();
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.policy.stats
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.policy.stats = Stats::default();
+    }
+
+    /// Installs (or with `None` removes) the software watchdog, see
+    /// [`Mcp23s08::set_watchdog`](crate::mcp23s08::Mcp23s08::set_watchdog).
+    /// The safe state covers all 16 pins.
+    pub fn set_watchdog(&mut self, config: Option<WatchdogConfig<u16>>, now_ms: u64) {
+        self.policy.watchdog = Watchdog::new(config, now_ms);
+    }
+
+    pub fn feed(&mut self, now_ms: u64) {
+        self.policy.watchdog.last_feed = now_ms;
+    }
+
+    /// See [`Mcp23s08::poll_watchdog`](crate::mcp23s08::Mcp23s08::poll_watchdog).
+    pub fn poll_watchdog(&mut self, now_ms: u64) -> Result<bool, Error<E>> {
+        let Some(cfg) = self.policy.poll_watchdog(now_ms) else {
+            return Ok(false);
+        };
+        self.drive_safe_state(cfg)?;
+        Ok(true)
+    }
+
+    pub fn watchdog_fault(&self) -> Option<WatchdogFault> {
+        self.policy.watchdog.fault
+    }
+
+    /// See [`Mcp23s08::clear_watchdog_fault`](crate::mcp23s08::Mcp23s08::clear_watchdog_fault).
+    pub fn clear_watchdog_fault(&mut self, now_ms: u64) {
+        self.policy.watchdog.clear(now_ms);
+    }
+
+    /// Installs (or with `None` removes) the interlock table of `port`. A
+    /// 16-bit write checks each port against its own table.
+    pub fn set_interlock(&mut self, port: Port, interlock: Option<Interlock>) {
+        self.interlock[port as usize] = interlock;
+    }
+
+    pub fn interlock(&self, port: Port) -> Option<Interlock> {
+        self.interlock[port as usize]
+    }
+
+    /// See [`Mcp23s08::set_verify`](crate::mcp23s08::Mcp23s08::set_verify).
+    /// A 16-bit write is read back in as many transactions as it was
+    /// written.
+    pub fn set_verify(&mut self, policy: VerifyPolicy) {
+        self.policy.verify = policy;
+    }
+
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        self.policy.verify
+    }
+
+    /// `port` as an 8-bit [`IoExpander`], for the pin handles and the
+    /// modules built on that trait. On an MCP23x18 its outputs are open
+    /// drain: a high latch bit releases the line.
+    pub fn port(&mut self, port: Port) -> PortView<'_, B, D, C> {
+        PortView { dev: self, port }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Sets up `IOCON` of the chip at `hw_addr` for the current layout and
+    /// loads the shadow.
+    fn attach(&mut self) -> Result<(), Error<E>> {
+        // An SPI chip with HAEN clear only listens on address 0, so addressed
+        // chips need HAEN set through a broadcast first.
+        let mut iocon = self.bank.iocon_bit();
+        if self.hw_addr != 0 && B::NEEDS_HAEN {
+            self.enable_addressing()?;
+            iocon |= HAEN;
+        }
+        self.write_iocon(iocon)?;
+        self.read_reg16(Reg::IODIR)?;
+        self.read_reg16(Reg::OLAT)?;
+        Ok(())
+    }
+
+    /// Sets `HAEN` in `IOCON` at address 0, which every chip with `HAEN`
+    /// clear answers to. Such a chip has never been configured, so it is in
+    /// the power-on [`Bank::Paired`] layout, and the write goes through
+    /// `IOCON`'s port B copy (0x0B). In the separate layout 0x0B is
+    /// unimplemented, so a chip at address 0 using it is not touched; one in
+    /// the paired layout has its other bits read first and kept.
+    fn enable_addressing(&mut self) -> Result<(), Error<E>> {
+        let (hw_addr, bank) = (self.hw_addr, self.bank);
+        self.hw_addr = 0;
+        self.bank = Bank::Paired;
+        let mut iocon = [0];
+        let result = match self.read_raw(Reg::IOCON, Port::B, &mut iocon) {
+            Ok(()) => {
+                let iocon = (iocon[0] & !BANK) | HAEN;
+                self.write_raw(Reg::IOCON, Port::B, &[iocon])
+            }
+            Err(e) => Err(e),
+        };
+        self.hw_addr = hw_addr;
+        self.bank = bank;
+        result
+    }
+
+    #[inline]
+    pub(crate) fn shadow(&self, port: Port) -> &Shadow {
+        &self.shadow[port as usize]
+    }
+
+    fn shadow16(&self, reg: Reg) -> u16 {
+        shadow16(&self.shadow, reg)
+    }
+
+    #[inline]
+    pub(crate) fn iocon(&self) -> u8 {
+        self.shadow[0].get(Reg::IOCON)
+    }
+
+    pub(crate) fn update_iocon(&mut self, bit: u8, set: bool) -> Result<(), Error<E>> {
+        let iocon = if set {
+            self.iocon() | bit
+        } else {
+            self.iocon() & !bit
+        };
+        self.write_iocon(iocon)
+    }
+
+    /// `IOCON` is shared by both ports; it is written through port A.
+    fn write_iocon(&mut self, iocon: u8) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IOCON, Port::A, iocon)
+    }
+
+    /// Output latch write of one port under its interlock table.
+    fn write_outputs(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        if let Some(interlock) = self.interlock[port as usize] {
+            let plan = interlock
+                .plan(self.shadow(port).olat(), val)
+                .map_err(Error::Interlock)?;
+            if let Some((step, dead_time_us)) = plan {
+                self.write_reg(reg, port, step)?;
+                if dead_time_us > 0 {
+                    self.delay.delay_us(dead_time_us);
+                }
+            }
+        }
+        self.write_reg(reg, port, val)
+    }
+
+    /// 16-bit output latch write under the interlock tables of both ports.
+    fn write_outputs16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        let plan = plan16(&self.interlock, self.shadow16(Reg::OLAT), val)?;
+        if let Some((step, dead_time_us)) = plan {
+            self.write_reg16(reg, step)?;
+            if dead_time_us > 0 {
+                self.delay.delay_us(dead_time_us);
+            }
+        }
+        self.write_reg16(reg, val)
+    }
+
+    fn write_reg(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        self.policy.guard(reg)?;
+        let result = self.write_reg_unguarded(reg, port, val);
+        self.trip_on_bus_errors(result)
+    }
+
+    fn write_reg_unguarded(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        self.write_checked(reg, port, &[val])?;
+        record_write(&mut self.shadow, reg, port, &[val]);
+        Ok(())
+    }
+
+    fn read_reg(&mut self, reg: Reg, port: Port) -> Result<u8, Error<E>> {
+        let result = self.read_reg_unguarded(reg, port);
+        self.trip_on_bus_errors(result)
+    }
+
+    fn read_reg_unguarded(&mut self, reg: Reg, port: Port) -> Result<u8, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        let mut byte = [0u8; 1];
+        self.read_raw(reg, port, &mut byte)?;
+        record_read(&mut self.shadow, reg, port, &byte);
+        Ok(byte[0])
+    }
+
+    /// Writes `reg` of both ports.
+    fn write_reg16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        self.policy.guard(reg)?;
+        let result = self.write_reg16_unguarded(reg, val);
+        self.trip_on_bus_errors(result)
+    }
+
+    fn write_reg16_unguarded(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        self.write_checked16(reg, val)?;
+        record_write(&mut self.shadow, reg, Port::A, &val.to_le_bytes());
+        Ok(())
+    }
+
+    fn read_reg16(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        let result = self.read_reg16_unguarded(reg);
+        self.trip_on_bus_errors(result)
+    }
+
+    fn read_reg16_unguarded(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check()?;
+        }
+        let val = self.read_raw16(reg)?;
+        record_read(&mut self.shadow, reg, Port::A, &val.to_le_bytes());
+        Ok(val)
+    }
+
+    /// Drives the safe state if `result` trips the watchdog. `result` is
+    /// passed through unchanged.
+    fn trip_on_bus_errors<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        if let Some(cfg) = self.policy.trip_on_bus_errors(&result) {
+            // The bus is failing; the safe state is best effort.
+            let _ = self.drive_safe_state(cfg);
+        }
+        result
+    }
+
+    /// Writes the safe output state of a tripped watchdog.
+    fn drive_safe_state(&mut self, cfg: WatchdogConfig<u16>) -> Result<(), Error<E>> {
+        let olat = cfg.safe_olat;
+        self.write_checked16(Reg::OLAT, olat)?;
+        record_write(&mut self.shadow, Reg::OLAT, Port::A, &olat.to_le_bytes());
+        if let Some(iodir) = cfg.safe_iodir {
+            self.write_checked16(Reg::IODIR, iodir)?;
+            record_write(&mut self.shadow, Reg::IODIR, Port::A, &iodir.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Writes `reg` of both ports; one transaction in the paired layout,
+    /// where the address pointer steps from the A to the B register.
+    fn write_checked16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        let bytes = val.to_le_bytes();
+        match self.bank {
+            Bank::Paired => self.write_checked(reg, Port::A, &bytes),
+            Bank::Separate => {
+                self.write_checked(reg, Port::A, &bytes[..1])?;
+                self.write_checked(reg, Port::B, &bytes[1..])
+            }
+        }
+    }
+
+    /// Writes `data` (one or two bytes) starting at `reg` of `port` and, if
+    /// the verify policy covers it, reads it back.
+    fn write_checked(&mut self, reg: Reg, port: Port, data: &[u8]) -> Result<(), Error<E>> {
+        let Some(mut readback) = self.policy.readback(reg, C::IOCON_BITS) else {
+            return self.write_raw(reg, port, data);
+        };
+        let bank = self.bank;
+        let mut buf = [0u8; 2];
+        let read = &mut buf[..data.len()];
+        loop {
+            self.write_raw(reg, port, data)?;
+            self.read_raw(readback.from, port, read)?;
+            if reg == Reg::IOCON && (data[0] ^ read[0]) & BANK != 0 {
+                // The layout did not switch; keep addressing the old one.
+                self.bank = bank;
+            }
+            if let ControlFlow::Break(result) = self.policy.verify(&mut readback, data, read) {
+                return result;
+            }
+        }
+    }
+
+    fn read_raw16(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        let mut bytes = [0u8; 2];
+        match self.bank {
+            Bank::Paired => self.read_raw(reg, Port::A, &mut bytes)?,
+            Bank::Separate => {
+                self.read_raw(reg, Port::A, &mut bytes[..1])?;
+                self.read_raw(reg, Port::B, &mut bytes[1..])?;
+            }
+        }
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    /// Runs one register access under the retry policy.
+    fn transact(&mut self, addr: u8, mut access: RegAccess<'_>) -> Result<(), E> {
+        let mut attempt = 0;
+        loop {
+            self.policy.begin(&access, B::frame_sizes);
+            match access.run(&mut self.bus, self.hw_addr, addr) {
+                Ok(()) => {
+                    self.policy.succeeded(attempt);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let Some(backoff_us) = self.policy.failed(attempt, B::error_kind(&e)) else {
+                        return Err(e);
+                    };
+                    if backoff_us > 0 {
+                        self.delay.delay_us(backoff_us);
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Writes `data` (one or two bytes) starting at `reg` of `port`. A write
+    /// to `IOCON` switches the layout at once.
+    fn write_raw(&mut self, reg: Reg, port: Port, data: &[u8]) -> Result<(), Error<E>> {
+        self.transact(self.bank.addr(reg, port), RegAccess::Write(data))
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Write,
+                source,
+            })?;
+        if reg == Reg::IOCON {
+            self.bank = Bank::from_iocon(data[0]);
+        }
+        Ok(())
+    }
+
+    fn read_raw(&mut self, reg: Reg, port: Port, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.transact(self.bank.addr(reg, port), RegAccess::Read(buf))
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Read,
+                source,
+            })
+    }
+}
+
+impl<B, D, E, C> Mcp23s17<B, D, C>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+    C: PushPull,
+{
+    /// Handle for one pin, see [`ExpanderPin`].
+    ///
+    /// Only push-pull variants have this; an MCP23S18 hands out
+    /// [`OpenDrainPin`](crate::mcp23s18::OpenDrainPin)s instead.
+    pub fn pin(&mut self, port: Port, pin: Pin) -> GpioPin<'_, B, D, C> {
+        ExpanderPin::new(self.port(port), pin)
+    }
+}
+
+impl<B, D, E, C> Mcp23s17<B, D, C>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    C: HardwareAddress,
+{
+    /// Moves the driver to the chip strapped to `hw_addr` (0..=7) on the
+    /// same bus, e.g. on boards whose address is set with jumpers. `IOCON`
+    /// of that chip is set up as by [`new_in_bank`](Mcp23s17::new_in_bank)
+    /// with the current layout, and the shadow is reloaded from it.
+    ///
+    /// Only variants with address pins have this; on an MCP23S18 it does
+    /// not compile:
+    ///
+    /// ```compile_fail
+    /// use mcp23s08_io::mcp23s18::Mcp23s18;
+    /// use mcp23s08_io::sim::Mcp23s17Sim;
+    ///
+    /// let sim = Mcp23s17Sim::mcp23s18();
+    /// let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    /// dev.set_hw_addr(1).unwrap();
+    /// ```
+    pub fn set_hw_addr(&mut self, hw_addr: u8) -> Result<(), Error<E>> {
+        if hw_addr > 7 {
+            return Err(Error::BadAddress);
+        }
+        self.hw_addr = hw_addr;
+        self.attach()
+    }
+}
+
+/// Which bytes (port A, port B) differ between two 16-bit values.
+pub(crate) fn changed_ports(old: u16, new: u16) -> [bool; 2] {
+    let diff = old ^ new;
+    [diff & 0x00FF != 0, diff & 0xFF00 != 0]
+}
+
+/// [`Shadow::RESTORE_ORDER`] without the shared `IOCON`, which is written
+/// once and first.
+pub(crate) const CONFIG_ORDER: [Reg; 7] = [
+    Reg::IPOL,
+    Reg::GPINTEN,
+    Reg::DEFVAL,
+    Reg::INTCON,
+    Reg::GPPU,
+    Reg::OLAT,
+    Reg::IODIR,
+];
+
+/// Cached `reg` of both ports as one 16-bit value.
+pub(crate) fn shadow16(shadow: &[Shadow; 2], reg: Reg) -> u16 {
+    u16::from_le_bytes([shadow[0].get(reg), shadow[1].get(reg)])
+}
+
+/// Files a write of `data` (one or two bytes) starting at `reg` of `port`.
+/// `IOCON` is kept in both ports.
+pub(crate) fn record_write(shadow: &mut [Shadow; 2], reg: Reg, port: Port, data: &[u8]) {
+    if reg == Reg::IOCON {
+        shadow.iter_mut().for_each(|s| s.record_write(reg, data[0]));
+        return;
+    }
+    for (s, &byte) in shadow[port as usize..].iter_mut().zip(data) {
+        s.record_write(reg, byte);
+    }
+}
+
+/// Read counterpart of [`record_write`].
+pub(crate) fn record_read(shadow: &mut [Shadow; 2], reg: Reg, port: Port, data: &[u8]) {
+    if reg == Reg::IOCON {
+        shadow.iter_mut().for_each(|s| s.record_read(reg, data[0]));
+        return;
+    }
+    for (s, &byte) in shadow[port as usize..].iter_mut().zip(data) {
+        s.record_read(reg, byte);
+    }
+}
+
+/// Checks a 16-bit latch move against the interlock table of each port.
+/// When a port has to go break-before-make, returns the intermediate latch
+/// (ports without a step already at their new value) and the longer
+/// dead-time.
+pub(crate) fn plan16<E>(
+    interlock: &[Option<Interlock>; 2],
+    old: u16,
+    new: u16,
+) -> Result<Option<(u16, u32)>, Error<E>> {
+    let (old, new) = (old.to_le_bytes(), new.to_le_bytes());
+    let mut step = new;
+    let mut dead_time_us = None;
+    for (i, interlock) in interlock.iter().enumerate() {
+        let Some(interlock) = interlock else {
+            continue;
+        };
+        if let Some((byte, us)) = interlock.plan(old[i], new[i]).map_err(Error::Interlock)? {
+            step[i] = byte;
+            dead_time_us = Some(dead_time_us.unwrap_or(0).max(us));
+        }
+    }
+    Ok(dead_time_us.map(|us| (u16::from_le_bytes(step), us)))
+}
+
+/// One port of an [`Mcp23s17`] as an 8-bit [`IoExpander`], returned by
+/// [`Mcp23s17::port`]. Writes go through the driver, so the interlock,
+/// watchdog and verify settings of the port apply.
+pub struct PortView<'a, B, D = NoDelay, C = Mcp23x17> {
+    dev: &'a mut Mcp23s17<B, D, C>,
+    port: Port,
+}
+
+impl<B, D, C> PortView<'_, B, D, C> {
+    pub fn port(&self) -> Port {
+        self.port
+    }
+}
+
+/// Pin handle returned by [`Mcp23s17::pin`].
+pub type GpioPin<'a, B, D = NoDelay, C = Mcp23x17> = ExpanderPin<PortView<'a, B, D, C>>;
+
+impl<B, D, E, C> IoExpander for PortView<'_, B, D, C>
+where
+    C: Variant,
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = Error<E>;
+
+    const WIDTH: u8 = 8;
+
+    fn read_port(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_port(self.port)
+    }
+
+    fn output_latch(&self) -> u8 {
+        self.dev.shadow(self.port).olat()
+    }
+
+    fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
+        self.dev.write_olat(self.port, value)
+    }
+
+    fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Error<E>> {
+        self.dev.modify_port(self.port, mask, value)
+    }
+
+    fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.dev.set_port_direction(self.port, inputs)
+    }
+
+    fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        self.dev.modify_port_direction(self.port, mask, inputs)
+    }
+
+    fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.dev.set_port_pullups(self.port, mask)
+    }
+
+    fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
+        self.dev.set_pin_pullup(self.port, pin, enable)
+    }
+
+    fn read_interrupt_flags(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_interrupt_flags(self.port)
+    }
+
+    fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_interrupt_capture(self.port)
+    }
+
+    fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
+        self.dev.read_pin(self.port, pin)
+    }
+
+    fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        self.dev.write_pin(self.port, pin, high)
+    }
+
+    fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        self.dev.set_pin_direction(self.port, pin, input)
+    }
+}
@@ -0,0 +1,941 @@
+//! Async MCP23S17 driver, see [`crate::mcp23s17`].
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::ops::ControlFlow;
+
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::health::Shadow;
+use crate::mcp23s08::{Access, Error, HAEN, InterruptMode, Pin, PinSet, Polarity, Reg};
+use crate::mcp23s17::{
+    BANK, CONFIG_ORDER, INTPOL, MIRROR, ODR, changed_ports, plan16, record_read, record_write,
+    shadow16,
+};
+use crate::policy::Policy;
+use crate::transport::{RegAccess, i2c_hw_addr};
+use crate::watchdog::Watchdog;
+
+pub use crate::expander::{AsyncExpanderPin, AsyncIoExpander};
+pub use crate::health::{HealthCheck, ResetDetect};
+pub use crate::interlock::{Interlock, Resolve, Rule, Violation};
+pub use crate::mcp23s17::{Bank, HardwareAddress, Mcp23x17, Port, PushPull, Variant};
+pub use crate::retry::{NoDelay, RetryPolicy, RetryStats};
+pub use crate::stats::{ErrorCounts, Stats};
+pub use crate::transport::{AsyncTransport, BusErrorKind, I2cBus};
+pub use crate::verify::{VerifyMode, VerifyPolicy};
+pub use crate::watchdog::{WatchdogConfig, WatchdogFault};
+
+/// Async counterpart of [`Mcp23s17`](crate::mcp23s17::Mcp23s17).
+pub struct Mcp23s17async<B, D = NoDelay, C = Mcp23x17> {
+    bus: B,
+    hw_addr: u8,
+    bank: Bank,
+    variant: PhantomData<C>,
+    /// Port A, port B. `IOCON` is kept in both.
+    shadow: [Shadow; 2],
+    policy: Policy<u16>,
+    delay: D,
+    /// Port A, port B.
+    interlock: [Option<Interlock>; 2],
+}
+
+/// Async counterpart of [`Mcp23017`](crate::mcp23s17::Mcp23017).
+pub type Mcp23017async<I2C, D = NoDelay> = Mcp23s17async<I2cBus<I2C>, D>;
+
+impl<SPI, E> Mcp23s17async<SPI>
+where
+    SPI: SpiDevice<Error = E>,
+    E: SpiError,
+{
+    /// See [`Mcp23s17::new`](crate::mcp23s17::Mcp23s17::new).
+    pub async fn new(spi: SPI, hw_addr: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank(spi, hw_addr, Bank::Paired).await
+    }
+
+    /// See [`Mcp23s17::new_in_bank`](crate::mcp23s17::Mcp23s17::new_in_bank).
+    pub async fn new_in_bank(spi: SPI, hw_addr: u8, bank: Bank) -> Result<Self, Error<E>> {
+        if hw_addr > 7 {
+            return Err(Error::BadAddress);
+        }
+        Self::init(spi, hw_addr, bank).await
+    }
+}
+
+impl<I2C, E> Mcp23017async<I2C>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+{
+    /// See [`Mcp23017::new_i2c`](crate::mcp23s17::Mcp23s17::new_i2c).
+    pub async fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank_i2c(i2c, address, Bank::Paired).await
+    }
+
+    /// See [`Mcp23017::new_in_bank_i2c`](crate::mcp23s17::Mcp23s17::new_in_bank_i2c).
+    pub async fn new_in_bank_i2c(i2c: I2C, address: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Self::init(I2cBus::new(i2c), hw_addr, bank).await
+    }
+}
+
+impl<B, E, C> Mcp23s17async<B, NoDelay, C>
+where
+    B: AsyncTransport<Error = E>,
+    C: Variant,
+{
+    pub(crate) async fn init(bus: B, hw_addr: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let mut this = Self {
+            bus,
+            hw_addr,
+            bank,
+            variant: PhantomData,
+            shadow: [Shadow::new(); 2],
+            policy: Policy::new(),
+            delay: NoDelay,
+            interlock: [None; 2],
+        };
+        this.attach().await?;
+        Ok(this)
+    }
+}
+
+impl<B, D, E, C> Mcp23s17async<B, D, C>
+where
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+    C: Variant,
+{
+    /// See [`Mcp23s17::bank`](crate::mcp23s17::Mcp23s17::bank).
+    pub fn bank(&self) -> Bank {
+        self.bank
+    }
+
+    /// See [`Mcp23s17::hw_addr`](crate::mcp23s17::Mcp23s17::hw_addr).
+    pub fn hw_addr(&self) -> u8 {
+        self.hw_addr
+    }
+
+    /// See [`Mcp23s17::set_bank`](crate::mcp23s17::Mcp23s17::set_bank).
+    pub async fn set_bank(&mut self, bank: Bank) -> Result<(), Error<E>> {
+        let iocon = (self.iocon() & !BANK) | bank.iocon_bit();
+        self.write_iocon(iocon).await
+    }
+
+    /// See [`Mcp23s17::set_mirror`](crate::mcp23s17::Mcp23s17::set_mirror).
+    pub async fn set_mirror(&mut self, mirror: bool) -> Result<(), Error<E>> {
+        self.update_iocon(MIRROR, mirror).await
+    }
+
+    pub async fn set_int_open_drain(&mut self, enable: bool) -> Result<(), Error<E>> {
+        self.update_iocon(ODR, enable).await
+    }
+
+    pub async fn set_int_polarity(&mut self, active_high: bool) -> Result<(), Error<E>> {
+        self.update_iocon(INTPOL, active_high).await
+    }
+
+    pub async fn set_pin_direction(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        input: bool,
+    ) -> Result<(), Error<E>> {
+        let mut iodir = self.shadow(port).iodir();
+        if input {
+            iodir |= pin.mask();
+        } else {
+            iodir &= !pin.mask();
+        }
+        self.write_reg(Reg::IODIR, port, iodir).await
+    }
+
+    pub async fn set_port_direction(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IODIR, port, mask.into().bits()).await
+    }
+
+    /// See [`Mcp23s08::modify_port_direction`](crate::mcp23s08::Mcp23s08::modify_port_direction).
+    pub async fn modify_port_direction(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let iodir = (self.shadow(port).iodir() & !mask) | (inputs & mask);
+        self.write_reg(Reg::IODIR, port, iodir).await
+    }
+
+    pub async fn set_pin_pullup(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        enable: bool,
+    ) -> Result<(), Error<E>> {
+        let mut gppu = self.read_reg(Reg::GPPU, port).await?;
+        if enable {
+            gppu |= pin.mask();
+        } else {
+            gppu &= !pin.mask();
+        }
+        self.write_reg(Reg::GPPU, port, gppu).await
+    }
+
+    pub async fn set_port_pullups(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPPU, port, mask.into().bits()).await
+    }
+
+    pub async fn set_pin_polarity(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        pol: Polarity,
+    ) -> Result<(), Error<E>> {
+        let mut ipol = self.read_reg(Reg::IPOL, port).await?;
+        match pol {
+            Polarity::Normal => ipol &= !pin.mask(),
+            Polarity::Inverted => ipol |= pin.mask(),
+        }
+        self.write_reg(Reg::IPOL, port, ipol).await
+    }
+
+    pub async fn read_port(&mut self, port: Port) -> Result<u8, Error<E>> {
+        self.read_reg(Reg::GPIO, port).await
+    }
+
+    pub async fn read_pin(&mut self, port: Port, pin: Pin) -> Result<bool, Error<E>> {
+        Ok(self.read_port(port).await? & pin.mask() != 0)
+    }
+
+    pub async fn write_port(&mut self, port: Port, value: u8) -> Result<(), Error<E>> {
+        self.write_outputs(Reg::GPIO, port, value).await
+    }
+
+    pub async fn write_pin(&mut self, port: Port, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        let mut olat = self.shadow(port).olat();
+        if high {
+            olat |= pin.mask();
+        } else {
+            olat &= !pin.mask();
+        }
+        self.write_outputs(Reg::GPIO, port, olat).await
+    }
+
+    pub async fn write_olat(&mut self, port: Port, value: u8) -> Result<(), Error<E>> {
+        self.write_outputs(Reg::OLAT, port, value).await
+    }
+
+    /// See [`Mcp23s17::modify_port`](crate::mcp23s17::Mcp23s17::modify_port).
+    pub async fn modify_port(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+        value: u8,
+    ) -> Result<(), Error<E>> {
+        let mask = mask.into().bits();
+        let olat = (self.shadow(port).olat() & !mask) | (value & mask);
+        self.write_outputs(Reg::OLAT, port, olat).await
+    }
+
+    pub async fn set_pin_interrupt_enable(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        enable: bool,
+    ) -> Result<(), Error<E>> {
+        let mut gpinten = self.read_reg(Reg::GPINTEN, port).await?;
+        if enable {
+            gpinten |= pin.mask();
+        } else {
+            gpinten &= !pin.mask();
+        }
+        self.write_reg(Reg::GPINTEN, port, gpinten).await
+    }
+
+    pub async fn set_port_interrupt_enable(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::GPINTEN, port, mask.into().bits()).await
+    }
+
+    pub async fn set_pin_interrupt_mode(
+        &mut self,
+        port: Port,
+        pin: Pin,
+        mode: InterruptMode,
+    ) -> Result<(), Error<E>> {
+        let mut intcon = self.read_reg(Reg::INTCON, port).await?;
+        match mode {
+            InterruptMode::OnChange => intcon &= !pin.mask(),
+            InterruptMode::CompareToDefault => intcon |= pin.mask(),
+        }
+        self.write_reg(Reg::INTCON, port, intcon).await
+    }
+
+    pub async fn set_port_interrupt_mode(
+        &mut self,
+        port: Port,
+        mask: impl Into<PinSet>,
+    ) -> Result<(), Error<E>> {
+        self.write_reg(Reg::INTCON, port, mask.into().bits()).await
+    }
+
+    /// See [`Mcp23s08::set_port_default_compare`](crate::mcp23s08::Mcp23s08::set_port_default_compare).
+    pub async fn set_port_default_compare(
+        &mut self,
+        port: Port,
+        defval: u8,
+    ) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
+        self.write_reg(Reg::DEFVAL, port, defval).await
+    }
+
+    pub async fn read_interrupt_flags(&mut self, port: Port) -> Result<u8, Error<E>> {
+        self.read_reg(Reg::INTF, port).await
+    }
+
+    pub async fn read_interrupt_capture(&mut self, port: Port) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::INTCAP, port).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    pub async fn clear_interrupts(&mut self, port: Port) -> Result<u8, Error<E>> {
+        let value = self.read_reg(Reg::GPIO, port).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// See [`Mcp23s17::set_port_direction16`](crate::mcp23s17::Mcp23s17::set_port_direction16).
+    pub async fn set_port_direction16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::IODIR, mask).await
+    }
+
+    /// See [`Mcp23s17::modify_port_direction16`](crate::mcp23s17::Mcp23s17::modify_port_direction16).
+    pub async fn modify_port_direction16(
+        &mut self,
+        mask: u16,
+        inputs: u16,
+    ) -> Result<(), Error<E>> {
+        let iodir = (self.shadow16(Reg::IODIR) & !mask) | (inputs & mask);
+        self.write_reg16(Reg::IODIR, iodir).await
+    }
+
+    pub async fn set_port_pullups16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::GPPU, mask).await
+    }
+
+    /// See [`Mcp23s17::set_port_polarity16`](crate::mcp23s17::Mcp23s17::set_port_polarity16).
+    pub async fn set_port_polarity16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::IPOL, mask).await
+    }
+
+    pub async fn read_port16(&mut self) -> Result<u16, Error<E>> {
+        self.read_reg16(Reg::GPIO).await
+    }
+
+    pub async fn write_port16(&mut self, value: u16) -> Result<(), Error<E>> {
+        self.write_outputs16(Reg::GPIO, value).await
+    }
+
+    pub async fn write_olat16(&mut self, value: u16) -> Result<(), Error<E>> {
+        self.write_outputs16(Reg::OLAT, value).await
+    }
+
+    /// See [`Mcp23s17::modify_port16`](crate::mcp23s17::Mcp23s17::modify_port16).
+    pub async fn modify_port16(&mut self, mask: u16, value: u16) -> Result<(), Error<E>> {
+        let old = self.shadow16(Reg::OLAT);
+        let olat = (old & !mask) | (value & mask);
+        match changed_ports(old, olat) {
+            [true, true] => self.write_outputs16(Reg::OLAT, olat).await,
+            [true, false] => self.write_outputs(Reg::OLAT, Port::A, olat as u8).await,
+            [false, true] => {
+                self.write_outputs(Reg::OLAT, Port::B, (olat >> 8) as u8)
+                    .await
+            }
+            [false, false] => Ok(()),
+        }
+    }
+
+    pub async fn set_port_interrupt_enable16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::GPINTEN, mask).await
+    }
+
+    /// See [`Mcp23s17::set_port_interrupt_mode16`](crate::mcp23s17::Mcp23s17::set_port_interrupt_mode16).
+    pub async fn set_port_interrupt_mode16(&mut self, mask: u16) -> Result<(), Error<E>> {
+        self.write_reg16(Reg::INTCON, mask).await
+    }
+
+    pub async fn set_port_default_compare16(&mut self, defval: u16) -> Result<(), Error<E>> {
+        self.policy.guard_canary()?;
+        self.write_reg16(Reg::DEFVAL, defval).await
+    }
+
+    pub async fn read_interrupt_flags16(&mut self) -> Result<u16, Error<E>> {
+        self.read_reg16(Reg::INTF).await
+    }
+
+    pub async fn read_interrupt_capture16(&mut self) -> Result<u16, Error<E>> {
+        let value = self.read_reg16(Reg::INTCAP).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    pub async fn clear_interrupts16(&mut self) -> Result<u16, Error<E>> {
+        let value = self.read_reg16(Reg::GPIO).await?;
+        self.policy
+            .record(|s| s.interrupts_serviced = s.interrupts_serviced.wrapping_add(1));
+        Ok(value)
+    }
+
+    /// See [`Mcp23s17::set_health_check`](crate::mcp23s17::Mcp23s17::set_health_check).
+    pub async fn set_health_check(&mut self, check: Option<HealthCheck>) -> Result<(), Error<E>> {
+        self.policy.health.config = None;
+        if let Some(cfg) = check {
+            self.read_reg(Reg::IOCON, Port::A).await?;
+            for reg in CONFIG_ORDER {
+                self.read_reg16(reg).await?;
+            }
+            if let ResetDetect::Canary(value) = cfg.detect {
+                self.write_reg16(Reg::DEFVAL, u16::from_le_bytes([value; 2]))
+                    .await?;
+            }
+        }
+        self.policy.health.config = check;
+        Ok(())
+    }
+
+    /// See [`Mcp23s17::check`](crate::mcp23s17::Mcp23s17::check).
+    pub async fn check(&mut self) -> Result<bool, Error<E>> {
+        let Some(reg) = self.policy.detect_reg() else {
+            return Ok(false);
+        };
+        let changed = self.read_raw16(reg).await? != self.shadow16(reg);
+        if !self.policy.reset_seen(changed)? {
+            return Ok(false);
+        }
+        self.restore().await?;
+        Ok(true)
+    }
+
+    /// See [`Mcp23s17::restore`](crate::mcp23s17::Mcp23s17::restore).
+    pub async fn restore(&mut self) -> Result<(), Error<E>> {
+        let iocon = self.iocon();
+        self.bank = Bank::Paired;
+        if B::NEEDS_HAEN && self.hw_addr != 0 {
+            self.enable_addressing().await?;
+        }
+        self.write_checked(Reg::IOCON, Port::A, &[iocon]).await?;
+        for reg in CONFIG_ORDER {
+            self.write_checked16(reg, self.shadow16(reg)).await?;
+        }
+        Ok(())
+    }
+
+    /// See [`Mcp23s17::take_reset_event`](crate::mcp23s17::Mcp23s17::take_reset_event).
+    pub fn take_reset_event(&mut self) -> bool {
+        core::mem::take(&mut self.policy.health.pending_event)
+    }
+
+    /// See [`Mcp23s17::reset_count`](crate::mcp23s17::Mcp23s17::reset_count).
+    pub fn reset_count(&self) -> u32 {
+        self.policy.health.resets
+    }
+
+    /// See [`Mcp23s17::with_delay`](crate::mcp23s17::Mcp23s17::with_delay).
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s17async<B, D2, C> {
+        Mcp23s17async {
+            bus: self.bus,
+            hw_addr: self.hw_addr,
+            bank: self.bank,
+            variant: PhantomData,
+            shadow: self.shadow,
+            policy: self.policy,
+            delay,
+            interlock: self.interlock,
+        }
+    }
+
+    /// See [`Mcp23s17::set_retry_policy`](crate::mcp23s17::Mcp23s17::set_retry_policy).
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.policy.retry = policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.policy.retry
+    }
+
+    pub fn retry_stats(&self) -> RetryStats {
+        self.policy.retry_stats
+    }
+
+    pub fn reset_retry_stats(&mut self) {
+        self.policy.retry_stats = RetryStats::default();
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.policy.stats
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.policy.stats = Stats::default();
+    }
+
+    /// See [`Mcp23s17::set_watchdog`](crate::mcp23s17::Mcp23s17::set_watchdog).
+    pub fn set_watchdog(&mut self, config: Option<WatchdogConfig<u16>>, now_ms: u64) {
+        self.policy.watchdog = Watchdog::new(config, now_ms);
+    }
+
+    pub fn feed(&mut self, now_ms: u64) {
+        self.policy.watchdog.last_feed = now_ms;
+    }
+
+    /// See [`Mcp23s17::poll_watchdog`](crate::mcp23s17::Mcp23s17::poll_watchdog).
+    pub async fn poll_watchdog(&mut self, now_ms: u64) -> Result<bool, Error<E>> {
+        let Some(cfg) = self.policy.poll_watchdog(now_ms) else {
+            return Ok(false);
+        };
+        self.drive_safe_state(cfg).await?;
+        Ok(true)
+    }
+
+    pub fn watchdog_fault(&self) -> Option<WatchdogFault> {
+        self.policy.watchdog.fault
+    }
+
+    /// See [`Mcp23s17::clear_watchdog_fault`](crate::mcp23s17::Mcp23s17::clear_watchdog_fault).
+    pub fn clear_watchdog_fault(&mut self, now_ms: u64) {
+        self.policy.watchdog.clear(now_ms);
+    }
+
+    /// See [`Mcp23s17::set_interlock`](crate::mcp23s17::Mcp23s17::set_interlock).
+    pub fn set_interlock(&mut self, port: Port, interlock: Option<Interlock>) {
+        self.interlock[port as usize] = interlock;
+    }
+
+    pub fn interlock(&self, port: Port) -> Option<Interlock> {
+        self.interlock[port as usize]
+    }
+
+    /// See [`Mcp23s17::set_verify`](crate::mcp23s17::Mcp23s17::set_verify).
+    pub fn set_verify(&mut self, policy: VerifyPolicy) {
+        self.policy.verify = policy;
+    }
+
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        self.policy.verify
+    }
+
+    /// See [`Mcp23s17::port`](crate::mcp23s17::Mcp23s17::port).
+    pub fn port(&mut self, port: Port) -> PortView<'_, B, D, C> {
+        PortView { dev: self, port }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Sets up `IOCON` of the chip at `hw_addr` for the current layout and
+    /// loads the shadow.
+    async fn attach(&mut self) -> Result<(), Error<E>> {
+        // An SPI chip with HAEN clear only listens on address 0, so addressed
+        // chips need HAEN set through a broadcast first.
+        let mut iocon = self.bank.iocon_bit();
+        if self.hw_addr != 0 && B::NEEDS_HAEN {
+            self.enable_addressing().await?;
+            iocon |= HAEN;
+        }
+        self.write_iocon(iocon).await?;
+        self.read_reg16(Reg::IODIR).await?;
+        self.read_reg16(Reg::OLAT).await?;
+        Ok(())
+    }
+
+    /// Sets `HAEN` in `IOCON` at address 0, which every chip with `HAEN`
+    /// clear answers to. Such a chip has never been configured, so it is in
+    /// the power-on [`Bank::Paired`] layout, and the write goes through
+    /// `IOCON`'s port B copy (0x0B). In the separate layout 0x0B is
+    /// unimplemented, so a chip at address 0 using it is not touched; one in
+    /// the paired layout has its other bits read first and kept.
+    async fn enable_addressing(&mut self) -> Result<(), Error<E>> {
+        let (hw_addr, bank) = (self.hw_addr, self.bank);
+        self.hw_addr = 0;
+        self.bank = Bank::Paired;
+        let mut iocon = [0];
+        let result = match self.read_raw(Reg::IOCON, Port::B, &mut iocon).await {
+            Ok(()) => {
+                let iocon = (iocon[0] & !BANK) | HAEN;
+                self.write_raw(Reg::IOCON, Port::B, &[iocon]).await
+            }
+            Err(e) => Err(e),
+        };
+        self.hw_addr = hw_addr;
+        self.bank = bank;
+        result
+    }
+
+    #[inline]
+    pub(crate) fn shadow(&self, port: Port) -> &Shadow {
+        &self.shadow[port as usize]
+    }
+
+    fn shadow16(&self, reg: Reg) -> u16 {
+        shadow16(&self.shadow, reg)
+    }
+
+    #[inline]
+    pub(crate) fn iocon(&self) -> u8 {
+        self.shadow[0].get(Reg::IOCON)
+    }
+
+    pub(crate) async fn update_iocon(&mut self, bit: u8, set: bool) -> Result<(), Error<E>> {
+        let iocon = if set {
+            self.iocon() | bit
+        } else {
+            self.iocon() & !bit
+        };
+        self.write_iocon(iocon).await
+    }
+
+    /// `IOCON` is shared by both ports; it is written through port A.
+    async fn write_iocon(&mut self, iocon: u8) -> Result<(), Error<E>> {
+        self.write_reg(Reg::IOCON, Port::A, iocon).await
+    }
+
+    /// Output latch write of one port under its interlock table.
+    async fn write_outputs(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        if let Some(interlock) = self.interlock[port as usize] {
+            let plan = interlock
+                .plan(self.shadow(port).olat(), val)
+                .map_err(Error::Interlock)?;
+            if let Some((step, dead_time_us)) = plan {
+                self.write_reg(reg, port, step).await?;
+                if dead_time_us > 0 {
+                    self.delay.delay_us(dead_time_us).await;
+                }
+            }
+        }
+        self.write_reg(reg, port, val).await
+    }
+
+    /// 16-bit output latch write under the interlock tables of both ports.
+    async fn write_outputs16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        let plan = plan16(&self.interlock, self.shadow16(Reg::OLAT), val)?;
+        if let Some((step, dead_time_us)) = plan {
+            self.write_reg16(reg, step).await?;
+            if dead_time_us > 0 {
+                self.delay.delay_us(dead_time_us).await;
+            }
+        }
+        self.write_reg16(reg, val).await
+    }
+
+    async fn write_reg(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        self.policy.guard(reg)?;
+        let result = self.write_reg_unguarded(reg, port, val).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn write_reg_unguarded(&mut self, reg: Reg, port: Port, val: u8) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        self.write_checked(reg, port, &[val]).await?;
+        record_write(&mut self.shadow, reg, port, &[val]);
+        Ok(())
+    }
+
+    async fn read_reg(&mut self, reg: Reg, port: Port) -> Result<u8, Error<E>> {
+        let result = self.read_reg_unguarded(reg, port).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn read_reg_unguarded(&mut self, reg: Reg, port: Port) -> Result<u8, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        let mut byte = [0u8; 1];
+        self.read_raw(reg, port, &mut byte).await?;
+        record_read(&mut self.shadow, reg, port, &byte);
+        Ok(byte[0])
+    }
+
+    /// Writes `reg` of both ports.
+    async fn write_reg16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        self.policy.guard(reg)?;
+        let result = self.write_reg16_unguarded(reg, val).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn write_reg16_unguarded(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        self.write_checked16(reg, val).await?;
+        record_write(&mut self.shadow, reg, Port::A, &val.to_le_bytes());
+        Ok(())
+    }
+
+    async fn read_reg16(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        let result = self.read_reg16_unguarded(reg).await;
+        self.trip_on_bus_errors(result).await
+    }
+
+    async fn read_reg16_unguarded(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        if self.policy.health.on_access() {
+            self.check().await?;
+        }
+        let val = self.read_raw16(reg).await?;
+        record_read(&mut self.shadow, reg, Port::A, &val.to_le_bytes());
+        Ok(val)
+    }
+
+    /// Drives the safe state if `result` trips the watchdog. `result` is
+    /// passed through unchanged.
+    async fn trip_on_bus_errors<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        if let Some(cfg) = self.policy.trip_on_bus_errors(&result) {
+            // The bus is failing; the safe state is best effort.
+            let _ = self.drive_safe_state(cfg).await;
+        }
+        result
+    }
+
+    /// Writes the safe output state of a tripped watchdog.
+    async fn drive_safe_state(&mut self, cfg: WatchdogConfig<u16>) -> Result<(), Error<E>> {
+        let olat = cfg.safe_olat;
+        self.write_checked16(Reg::OLAT, olat).await?;
+        record_write(&mut self.shadow, Reg::OLAT, Port::A, &olat.to_le_bytes());
+        if let Some(iodir) = cfg.safe_iodir {
+            self.write_checked16(Reg::IODIR, iodir).await?;
+            record_write(&mut self.shadow, Reg::IODIR, Port::A, &iodir.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Writes `reg` of both ports; one transaction in the paired layout,
+    /// where the address pointer steps from the A to the B register.
+    async fn write_checked16(&mut self, reg: Reg, val: u16) -> Result<(), Error<E>> {
+        let bytes = val.to_le_bytes();
+        match self.bank {
+            Bank::Paired => self.write_checked(reg, Port::A, &bytes).await,
+            Bank::Separate => {
+                self.write_checked(reg, Port::A, &bytes[..1]).await?;
+                self.write_checked(reg, Port::B, &bytes[1..]).await
+            }
+        }
+    }
+
+    /// Writes `data` (one or two bytes) starting at `reg` of `port` and, if
+    /// the verify policy covers it, reads it back.
+    async fn write_checked(&mut self, reg: Reg, port: Port, data: &[u8]) -> Result<(), Error<E>> {
+        let Some(mut readback) = self.policy.readback(reg, C::IOCON_BITS) else {
+            return self.write_raw(reg, port, data).await;
+        };
+        let bank = self.bank;
+        let mut buf = [0u8; 2];
+        let read = &mut buf[..data.len()];
+        loop {
+            self.write_raw(reg, port, data).await?;
+            self.read_raw(readback.from, port, read).await?;
+            if reg == Reg::IOCON && (data[0] ^ read[0]) & BANK != 0 {
+                // The layout did not switch; keep addressing the old one.
+                self.bank = bank;
+            }
+            if let ControlFlow::Break(result) = self.policy.verify(&mut readback, data, read) {
+                return result;
+            }
+        }
+    }
+
+    async fn read_raw16(&mut self, reg: Reg) -> Result<u16, Error<E>> {
+        let mut bytes = [0u8; 2];
+        match self.bank {
+            Bank::Paired => self.read_raw(reg, Port::A, &mut bytes).await?,
+            Bank::Separate => {
+                self.read_raw(reg, Port::A, &mut bytes[..1]).await?;
+                self.read_raw(reg, Port::B, &mut bytes[1..]).await?;
+            }
+        }
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    /// Runs one register access under the retry policy.
+    async fn transact(&mut self, addr: u8, mut access: RegAccess<'_>) -> Result<(), E> {
+        let mut attempt = 0;
+        loop {
+            self.policy.begin(&access, B::frame_sizes);
+            match access.run_async(&mut self.bus, self.hw_addr, addr).await {
+                Ok(()) => {
+                    self.policy.succeeded(attempt);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let Some(backoff_us) = self.policy.failed(attempt, B::error_kind(&e)) else {
+                        return Err(e);
+                    };
+                    if backoff_us > 0 {
+                        self.delay.delay_us(backoff_us).await;
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Writes `data` (one or two bytes) starting at `reg` of `port`. A write
+    /// to `IOCON` switches the layout at once.
+    async fn write_raw(&mut self, reg: Reg, port: Port, data: &[u8]) -> Result<(), Error<E>> {
+        self.transact(self.bank.addr(reg, port), RegAccess::Write(data))
+            .await
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Write,
+                source,
+            })?;
+        if reg == Reg::IOCON {
+            self.bank = Bank::from_iocon(data[0]);
+        }
+        Ok(())
+    }
+
+    async fn read_raw(&mut self, reg: Reg, port: Port, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.transact(self.bank.addr(reg, port), RegAccess::Read(buf))
+            .await
+            .map_err(|source| Error::Bus {
+                reg,
+                access: Access::Read,
+                source,
+            })
+    }
+}
+
+impl<B, D, E, C> Mcp23s17async<B, D, C>
+where
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+    C: PushPull,
+{
+    /// See [`Mcp23s17::pin`](crate::mcp23s17::Mcp23s17::pin).
+    pub fn pin(&mut self, port: Port, pin: Pin) -> GpioPin<'_, B, D, C> {
+        AsyncExpanderPin::new(self.port(port), pin)
+    }
+}
+
+impl<B, D, E, C> Mcp23s17async<B, D, C>
+where
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+    C: HardwareAddress,
+{
+    /// See [`Mcp23s17::set_hw_addr`](crate::mcp23s17::Mcp23s17::set_hw_addr).
+    pub async fn set_hw_addr(&mut self, hw_addr: u8) -> Result<(), Error<E>> {
+        if hw_addr > 7 {
+            return Err(Error::BadAddress);
+        }
+        self.hw_addr = hw_addr;
+        self.attach().await
+    }
+}
+
+/// Async counterpart of [`PortView`](crate::mcp23s17::PortView), returned
+/// by [`Mcp23s17async::port`].
+pub struct PortView<'a, B, D = NoDelay, C = Mcp23x17> {
+    dev: &'a mut Mcp23s17async<B, D, C>,
+    port: Port,
+}
+
+impl<B, D, C> PortView<'_, B, D, C> {
+    pub fn port(&self) -> Port {
+        self.port
+    }
+}
+
+/// Pin handle returned by [`Mcp23s17async::pin`].
+pub type GpioPin<'a, B, D = NoDelay, C = Mcp23x17> = AsyncExpanderPin<PortView<'a, B, D, C>>;
+
+impl<B, D, E, C> AsyncIoExpander for PortView<'_, B, D, C>
+where
+    C: Variant,
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = Error<E>;
+
+    const WIDTH: u8 = 8;
+
+    async fn read_port(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_port(self.port).await
+    }
+
+    fn output_latch(&self) -> u8 {
+        self.dev.shadow(self.port).olat()
+    }
+
+    async fn write_olat(&mut self, value: u8) -> Result<(), Error<E>> {
+        self.dev.write_olat(self.port, value).await
+    }
+
+    async fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Error<E>> {
+        self.dev.modify_port(self.port, mask, value).await
+    }
+
+    async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.dev.set_port_direction(self.port, inputs).await
+    }
+
+    async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Error<E>> {
+        self.dev
+            .modify_port_direction(self.port, mask, inputs)
+            .await
+    }
+
+    async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Error<E>> {
+        self.dev.set_port_pullups(self.port, mask).await
+    }
+
+    async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Error<E>> {
+        self.dev.set_pin_pullup(self.port, pin, enable).await
+    }
+
+    async fn read_interrupt_flags(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_interrupt_flags(self.port).await
+    }
+
+    async fn read_interrupt_capture(&mut self) -> Result<u8, Error<E>> {
+        self.dev.read_interrupt_capture(self.port).await
+    }
+
+    async fn read_pin(&mut self, pin: Pin) -> Result<bool, Error<E>> {
+        self.dev.read_pin(self.port, pin).await
+    }
+
+    async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), Error<E>> {
+        self.dev.write_pin(self.port, pin, high).await
+    }
+
+    async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), Error<E>> {
+        self.dev.set_pin_direction(self.port, pin, input).await
+    }
+}
@@ -0,0 +1,242 @@
+//! MCP23S18 / MCP23018 16-bit expanders with open-drain outputs.
+//!
+//! The MCP23x18 has the register map of the MCP23x17 and is driven by the
+//! same core, [`Mcp23s17`] with the [`Mcp23x18`] variant, which
+//! [`Mcp23s18`] wraps and dereferences to. The differences show up in the
+//! types:
+//!
+//! - Outputs are open drain: a latch bit of 0 sinks current, 1 releases the
+//!   line. There is no push-pull `pin()`; outputs are [`OpenDrainPin`]s,
+//!   whose `set_high` releases the line. `port()` still views a port as an
+//!   [`IoExpander`](crate::expander::IoExpander) with open-drain outputs.
+//! - `IOCON.INTCC` selects whether reading `INTCAP` or `GPIO` clears an
+//!   interrupt, see [`InterruptClear`].
+//! - The MCP23S18 has no address pins and no `IOCON.HAEN`, so
+//!   [`Mcp23s18::new`] takes no address and `set_hw_addr` does not exist.
+//!   The MCP23018 is placed at 0x20..=0x27 by the voltage on its `ADDR` pin.
+//!
+//! ```
+//! use embedded_hal::digital::OutputPin;
+//! use mcp23s08_io::mcp23s08::{Pin, Reg};
+//! use mcp23s08_io::mcp23s17::Port;
+//! use mcp23s08_io::mcp23s18::Mcp23s18;
+//! use mcp23s08_io::sim::Mcp23s17Sim;
+//!
+//! let sim = Mcp23s17Sim::mcp23s18();
+//! let mut dev = Mcp23s18::new(sim.device()).unwrap();
+//! // LED from the supply through a resistor into GPA0: lit while sinking.
+//! let mut led = dev.open_drain(Port::A, Pin::P0).unwrap();
+//! led.set_low().unwrap();
+//! assert_eq!(sim.reg(0, Port::A, Reg::OLAT) & 0x01, 0);
+//! ```
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal::spi::SpiDevice;
+
+use crate::mcp23s08::{Error, Pin};
+use crate::mcp23s17::{INTCC, Mcp23s17};
+use crate::transport::i2c_hw_addr;
+
+pub use crate::mcp23s17::{Bank, Mcp23x18, Port};
+pub use crate::retry::NoDelay;
+pub use crate::transport::{BusErrorKind, I2cBus, Transport};
+
+/// What clears a pending interrupt (`IOCON.INTCC`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InterruptClear {
+    /// Reading `GPIO` (power-on default), e.g. through `clear_interrupts`.
+    #[default]
+    Gpio,
+    /// Reading `INTCAP` through `read_interrupt_capture`. `GPIO` can then be
+    /// polled without acknowledging the interrupt.
+    Capture,
+}
+
+/// MCP23S18 on SPI or, as [`Mcp23018`], MCP23018 on I²C.
+///
+/// Dereferences to the shared [`Mcp23s17`] core for the port, 16-bit,
+/// interrupt and `IOCON` methods.
+pub struct Mcp23s18<B, D = NoDelay> {
+    core: Mcp23s17<B, D, Mcp23x18>,
+}
+
+/// MCP23018: the I²C sibling of the MCP23S18, with the same API.
+pub type Mcp23018<I2C, D = NoDelay> = Mcp23s18<I2cBus<I2C>, D>;
+
+impl<SPI, E> Mcp23s18<SPI>
+where
+    SPI: SpiDevice<Error = E>,
+    E: SpiError,
+{
+    /// Driver for the chip on `spi`, which must still be in the power-on
+    /// [`Bank::Paired`] layout.
+    pub fn new(spi: SPI) -> Result<Self, Error<E>> {
+        Self::new_in_bank(spi, Bank::Paired)
+    }
+
+    /// See [`Mcp23s17::new_in_bank`].
+    pub fn new_in_bank(spi: SPI, bank: Bank) -> Result<Self, Error<E>> {
+        Ok(Self {
+            core: Mcp23s17::init(spi, 0, bank)?,
+        })
+    }
+}
+
+impl<I2C, E> Mcp23018<I2C>
+where
+    I2C: embedded_hal::i2c::I2c<Error = E>,
+{
+    /// MCP23018 at the 7-bit I²C `address` (0x20..=0x27).
+    pub fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank_i2c(i2c, address, Bank::Paired)
+    }
+
+    /// See [`Mcp23s17::new_in_bank`].
+    pub fn new_in_bank_i2c(i2c: I2C, address: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Ok(Self {
+            core: Mcp23s17::init(I2cBus::new(i2c), hw_addr, bank)?,
+        })
+    }
+}
+
+impl<B, D, E> Mcp23s18<B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+{
+    pub fn set_interrupt_clear(&mut self, clear: InterruptClear) -> Result<(), Error<E>> {
+        self.core
+            .update_iocon(INTCC, clear == InterruptClear::Capture)
+    }
+
+    pub fn interrupt_clear(&self) -> InterruptClear {
+        if self.core.iocon() & INTCC != 0 {
+            InterruptClear::Capture
+        } else {
+            InterruptClear::Gpio
+        }
+    }
+
+    /// Makes `pin` an output, released at first, and returns its handle.
+    pub fn open_drain<'a>(
+        &'a mut self,
+        port: Port,
+        pin: Pin,
+    ) -> Result<OpenDrainPin<'a, B, D>, Error<E>> {
+        // Release the latch before the driver is switched on.
+        self.core.modify_port(port, pin, 0xFF)?;
+        self.core.set_pin_direction(port, pin, false)?;
+        Ok(OpenDrainPin {
+            dev: &mut self.core,
+            port,
+            pin,
+        })
+    }
+
+    /// Replaces the delay used for retry backoff.
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s18<B, D2> {
+        Mcp23s18 {
+            core: self.core.with_delay(delay),
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.core.into_inner()
+    }
+}
+
+impl<B, D> Deref for Mcp23s18<B, D> {
+    type Target = Mcp23s17<B, D, Mcp23x18>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl<B, D> DerefMut for Mcp23s18<B, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.core
+    }
+}
+
+/// Open-drain output of an [`Mcp23s18`]. `set_low` sinks current;
+/// `set_high` releases the line, which then follows whatever drives it (a
+/// pull-up, or another device holding it low).
+///
+/// The push-pull `pin()` of the core does not exist here:
+///
+/// ```compile_fail
+/// use embedded_hal::digital::OutputPin;
+/// use mcp23s08_io::mcp23s08::Pin;
+/// use mcp23s08_io::mcp23s18::{Mcp23s18, Port};
+/// use mcp23s08_io::sim::Mcp23s17Sim;
+///
+/// let sim = Mcp23s17Sim::mcp23s18();
+/// let mut dev = Mcp23s18::new(sim.device()).unwrap();
+/// dev.pin(Port::A, Pin::P0).set_high().unwrap();
+/// ```
+pub struct OpenDrainPin<'a, B, D = NoDelay> {
+    dev: &'a mut Mcp23s17<B, D, Mcp23x18>,
+    port: Port,
+    pin: Pin,
+}
+
+impl<'a, B, D, E> embedded_hal::digital::ErrorType for OpenDrainPin<'a, B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    type Error = Error<E>;
+}
+
+impl<'a, B, D, E> embedded_hal::digital::OutputPin for OpenDrainPin<'a, B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.dev.write_pin(self.port, self.pin, false)
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.dev.write_pin(self.port, self.pin, true)
+    }
+}
+
+impl<'a, B, D, E> embedded_hal::digital::StatefulOutputPin for OpenDrainPin<'a, B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    /// `true` while the line is released.
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.dev.shadow(self.port).olat() & self.pin.mask() != 0)
+    }
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_set_high()?)
+    }
+}
+
+/// Level on the line, which stays low while another device sinks it even if
+/// this pin is released.
+impl<'a, B, D, E> embedded_hal::digital::InputPin for OpenDrainPin<'a, B, D>
+where
+    B: Transport<Error = E>,
+    D: DelayNs,
+    E: Debug,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.dev.read_pin(self.port, self.pin)
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
@@ -0,0 +1,169 @@
+//! Async MCP23S18 / MCP23018 driver, see [`crate::mcp23s18`].
+
+use core::ops::{Deref, DerefMut};
+
+use embedded_hal::spi::Error as SpiError;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::mcp23s08::{Error, Pin};
+use crate::mcp23s17::INTCC;
+use crate::mcp23s17async::Mcp23s17async;
+use crate::transport::i2c_hw_addr;
+
+pub use crate::mcp23s17::{Bank, Mcp23x18, Port};
+pub use crate::mcp23s18::InterruptClear;
+pub use crate::retry::NoDelay;
+pub use crate::transport::{AsyncTransport, BusErrorKind, I2cBus};
+
+/// Async counterpart of [`Mcp23s18`](crate::mcp23s18::Mcp23s18).
+pub struct Mcp23s18async<B, D = NoDelay> {
+    core: Mcp23s17async<B, D, Mcp23x18>,
+}
+
+/// Async counterpart of [`Mcp23018`](crate::mcp23s18::Mcp23018).
+pub type Mcp23018async<I2C, D = NoDelay> = Mcp23s18async<I2cBus<I2C>, D>;
+
+impl<SPI, E> Mcp23s18async<SPI>
+where
+    SPI: SpiDevice<Error = E>,
+    E: SpiError,
+{
+    /// See [`Mcp23s18::new`](crate::mcp23s18::Mcp23s18::new).
+    pub async fn new(spi: SPI) -> Result<Self, Error<E>> {
+        Self::new_in_bank(spi, Bank::Paired).await
+    }
+
+    /// See [`Mcp23s18::new_in_bank`](crate::mcp23s18::Mcp23s18::new_in_bank).
+    pub async fn new_in_bank(spi: SPI, bank: Bank) -> Result<Self, Error<E>> {
+        Ok(Self {
+            core: Mcp23s17async::init(spi, 0, bank).await?,
+        })
+    }
+}
+
+impl<I2C, E> Mcp23018async<I2C>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+{
+    /// See [`Mcp23018::new_i2c`](crate::mcp23s18::Mcp23s18::new_i2c).
+    pub async fn new_i2c(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        Self::new_in_bank_i2c(i2c, address, Bank::Paired).await
+    }
+
+    /// See [`Mcp23018::new_in_bank_i2c`](crate::mcp23s18::Mcp23s18::new_in_bank_i2c).
+    pub async fn new_in_bank_i2c(i2c: I2C, address: u8, bank: Bank) -> Result<Self, Error<E>> {
+        let hw_addr = i2c_hw_addr(address).ok_or(Error::BadAddress)?;
+        Ok(Self {
+            core: Mcp23s17async::init(I2cBus::new(i2c), hw_addr, bank).await?,
+        })
+    }
+}
+
+impl<B, D, E> Mcp23s18async<B, D>
+where
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+{
+    /// See [`Mcp23s18::set_interrupt_clear`](crate::mcp23s18::Mcp23s18::set_interrupt_clear).
+    pub async fn set_interrupt_clear(&mut self, clear: InterruptClear) -> Result<(), Error<E>> {
+        self.core
+            .update_iocon(INTCC, clear == InterruptClear::Capture)
+            .await
+    }
+
+    /// See [`Mcp23s18::interrupt_clear`](crate::mcp23s18::Mcp23s18::interrupt_clear).
+    pub fn interrupt_clear(&self) -> InterruptClear {
+        if self.core.iocon() & INTCC != 0 {
+            InterruptClear::Capture
+        } else {
+            InterruptClear::Gpio
+        }
+    }
+
+    /// See [`Mcp23s18::open_drain`](crate::mcp23s18::Mcp23s18::open_drain).
+    pub async fn open_drain<'a>(
+        &'a mut self,
+        port: Port,
+        pin: Pin,
+    ) -> Result<OpenDrainPin<'a, B, D>, Error<E>> {
+        self.core.modify_port(port, pin, 0xFF).await?;
+        self.core.set_pin_direction(port, pin, false).await?;
+        Ok(OpenDrainPin {
+            dev: &mut self.core,
+            port,
+            pin,
+        })
+    }
+
+    /// See [`Mcp23s18::with_delay`](crate::mcp23s18::Mcp23s18::with_delay).
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> Mcp23s18async<B, D2> {
+        Mcp23s18async {
+            core: self.core.with_delay(delay),
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.core.into_inner()
+    }
+}
+
+impl<B, D> Deref for Mcp23s18async<B, D> {
+    type Target = Mcp23s17async<B, D, Mcp23x18>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl<B, D> DerefMut for Mcp23s18async<B, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.core
+    }
+}
+
+/// Async counterpart of [`OpenDrainPin`](crate::mcp23s18::OpenDrainPin).
+pub struct OpenDrainPin<'a, B, D = NoDelay> {
+    dev: &'a mut Mcp23s17async<B, D, Mcp23x18>,
+    port: Port,
+    pin: Pin,
+}
+
+impl<'a, B, D, E> OpenDrainPin<'a, B, D>
+where
+    B: AsyncTransport<Error = E>,
+    D: DelayNs,
+{
+    /// Level on the line.
+    pub async fn is_high(&mut self) -> Result<bool, Error<E>> {
+        self.dev.read_pin(self.port, self.pin).await
+    }
+
+    pub async fn is_low(&mut self) -> Result<bool, Error<E>> {
+        Ok(!self.is_high().await?)
+    }
+
+    /// Releases the line.
+    pub async fn set_high(&mut self) -> Result<(), Error<E>> {
+        self.dev.write_pin(self.port, self.pin, true).await
+    }
+
+    /// Sinks current.
+    pub async fn set_low(&mut self) -> Result<(), Error<E>> {
+        self.dev.write_pin(self.port, self.pin, false).await
+    }
+
+    pub async fn is_set_high(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.dev.shadow(self.port).olat() & self.pin.mask() != 0)
+    }
+
+    pub async fn is_set_low(&mut self) -> Result<bool, Error<E>> {
+        Ok(!self.is_set_high().await?)
+    }
+
+    /// Flips the latch, not the line level.
+    pub async fn toggle(&mut self) -> Result<(), Error<E>> {
+        let released = self.is_set_high().await?;
+        self.dev.write_pin(self.port, self.pin, !released).await
+    }
+}
@@ -0,0 +1,239 @@
+//! 8-bit parallel bus with strobe.
+//!
+//! [`ParallelBus`] uses all eight pins of an [`IoExpander`] such as the
+//! [`Mcp23s08`](crate::mcp23s08::Mcp23s08) as the data bus
+//! and any `OutputPin` (a pin of a second expander or a native MCU pin) as
+//! the strobe, plus an optional read/write select. A byte write is one
+//! `OLAT` write plus the strobe pulse; the direction register is only
+//! touched when the bus turns around between writes and reads.
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Pin};
+//! use mcp23s08_io::parallel::ParallelBus;
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::with_chips(&[0, 1]);
+//! let mut data = Mcp23s08::new(sim.device(), 0).unwrap();
+//! let mut ctrl = Mcp23s08::new(sim.device(), 1).unwrap();
+//! ctrl.set_pin_direction(Pin::P0, false).unwrap();
+//!
+//! let mut bus = ParallelBus::new(&mut data, ctrl.pin(Pin::P0)).unwrap();
+//! bus.write_bytes(b"hi").unwrap();
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{Error as _, ErrorKind, OutputPin};
+
+use crate::expander::IoExpander;
+use crate::mcp23s08::NoDelay;
+
+/// Error of [`ParallelBus`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParallelError<E> {
+    /// Talking to the data-port expander failed.
+    Expander(E),
+    /// Driving the strobe or read/write pin failed.
+    Pin(ErrorKind),
+}
+
+impl<E> From<E> for ParallelError<E> {
+    fn from(e: E) -> Self {
+        ParallelError::Expander(e)
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for ParallelError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParallelError::Expander(e) => write!(f, "expander error: {e}"),
+            ParallelError::Pin(kind) => write!(f, "control pin error: {kind}"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for ParallelError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ParallelError::Expander(e) => Some(e),
+            ParallelError::Pin(_) => None,
+        }
+    }
+}
+
+/// Bus timing in nanoseconds. Waits use the delay given to
+/// [`ParallelBus::with_delay`]; without one they are skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// Data (or read/write select) stable before the strobe goes active.
+    pub setup_ns: u32,
+    /// Strobe active time. For reads, the time before the port is sampled.
+    pub strobe_ns: u32,
+    /// Data held after the strobe goes inactive.
+    pub hold_ns: u32,
+}
+
+/// Placeholder for a bus without read/write select.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoPin;
+
+impl embedded_hal::digital::ErrorType for NoPin {
+    type Error = core::convert::Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// 8-bit parallel bus on one expander port.
+pub struct ParallelBus<'a, X, STB, RW = NoPin, T = NoDelay> {
+    dev: &'a mut X,
+    strobe: STB,
+    rw: RW,
+    strobe_active_high: bool,
+    delay: T,
+    timing: Timing,
+    /// Current port direction, `None` until the first transfer.
+    reading: Option<bool>,
+}
+
+impl<'a, X, STB> ParallelBus<'a, X, STB>
+where
+    X: IoExpander,
+    STB: OutputPin,
+{
+    /// Bus with an active-high strobe, which is driven inactive here. The
+    /// data port direction is set on the first transfer.
+    pub fn new(dev: &'a mut X, mut strobe: STB) -> Result<Self, ParallelError<X::Error>> {
+        strobe.set_low().map_err(|e| ParallelError::Pin(e.kind()))?;
+        Ok(Self {
+            dev,
+            strobe,
+            rw: NoPin,
+            strobe_active_high: true,
+            delay: NoDelay,
+            timing: Timing::default(),
+            reading: None,
+        })
+    }
+}
+
+impl<'a, X, STB, RW, T> ParallelBus<'a, X, STB, RW, T>
+where
+    X: IoExpander,
+    STB: OutputPin,
+    RW: OutputPin,
+    T: DelayNs,
+{
+    /// Adds a read/write select pin: high while reading, low while writing.
+    pub fn with_rw<RW2: OutputPin>(self, rw: RW2) -> ParallelBus<'a, X, STB, RW2, T> {
+        ParallelBus {
+            dev: self.dev,
+            strobe: self.strobe,
+            rw,
+            strobe_active_high: self.strobe_active_high,
+            delay: self.delay,
+            timing: self.timing,
+            // Unknown until the select pin has been driven.
+            reading: None,
+        }
+    }
+
+    /// Uses `delay` for the setup, strobe and hold times.
+    pub fn with_delay<T2: DelayNs>(
+        self,
+        delay: T2,
+        timing: Timing,
+    ) -> ParallelBus<'a, X, STB, RW, T2> {
+        ParallelBus {
+            dev: self.dev,
+            strobe: self.strobe,
+            rw: self.rw,
+            strobe_active_high: self.strobe_active_high,
+            delay,
+            timing,
+            reading: self.reading,
+        }
+    }
+
+    /// Makes the strobe active low and drives it inactive.
+    pub fn strobe_active_low(mut self) -> Result<Self, ParallelError<X::Error>> {
+        self.strobe_active_high = false;
+        self.set_strobe(false)?;
+        Ok(self)
+    }
+
+    /// Puts `byte` on the bus and pulses the strobe.
+    pub fn write_byte(&mut self, byte: u8) -> Result<(), ParallelError<X::Error>> {
+        if self.reading != Some(false) {
+            // Peripheral stops driving first, then the expander takes over
+            // with the new value already in the latch.
+            self.rw
+                .set_low()
+                .map_err(|e| ParallelError::Pin(e.kind()))?;
+            self.dev.write_olat(byte)?;
+            self.dev.set_port_direction(0x00)?;
+            self.reading = Some(false);
+        } else {
+            self.dev.write_olat(byte)?;
+        }
+        self.wait(self.timing.setup_ns);
+        self.set_strobe(true)?;
+        self.wait(self.timing.strobe_ns);
+        self.set_strobe(false)?;
+        self.wait(self.timing.hold_ns);
+        Ok(())
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), ParallelError<X::Error>> {
+        for &byte in bytes {
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+
+    /// Strobes the peripheral and samples the bus while the strobe is
+    /// active.
+    pub fn read_byte(&mut self) -> Result<u8, ParallelError<X::Error>> {
+        if self.reading != Some(true) {
+            // Release the bus before asking the peripheral to drive it.
+            self.dev.set_port_direction(0xFF)?;
+            self.rw
+                .set_high()
+                .map_err(|e| ParallelError::Pin(e.kind()))?;
+            self.reading = Some(true);
+        }
+        self.wait(self.timing.setup_ns);
+        self.set_strobe(true)?;
+        self.wait(self.timing.strobe_ns);
+        let byte = self.dev.read_port();
+        self.set_strobe(false)?;
+        self.wait(self.timing.hold_ns);
+        Ok(byte?)
+    }
+
+    /// Gives back the strobe and read/write pins.
+    pub fn release(self) -> (STB, RW) {
+        (self.strobe, self.rw)
+    }
+
+    fn set_strobe(&mut self, active: bool) -> Result<(), ParallelError<X::Error>> {
+        let result = if active == self.strobe_active_high {
+            self.strobe.set_high()
+        } else {
+            self.strobe.set_low()
+        };
+        result.map_err(|e| ParallelError::Pin(e.kind()))
+    }
+
+    fn wait(&mut self, ns: u32) {
+        if ns > 0 {
+            self.delay.delay_ns(ns);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Decisions shared by the blocking and async drivers.
+//!
+//! A driver only moves bytes. Counting transactions, deciding on retries,
+//! judging read-backs, spotting resets and tripping the watchdog happens
+//! here, so every driver flavour behaves the same and the sync and async
+//! wrappers differ in nothing but `.await`.
+
+use core::ops::ControlFlow;
+
+use crate::health::{Health, ResetDetect};
+use crate::mcp23s08::{Error, Reg};
+use crate::retry::{RetryPolicy, RetryStats};
+use crate::stats::Stats;
+use crate::transport::{BusErrorKind, RegAccess};
+use crate::verify::VerifyPolicy;
+use crate::watchdog::{Watchdog, WatchdogConfig, WatchdogFault};
+
+/// Policy settings and bookkeeping of one driver. `P` is the port width.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Policy<P = u8> {
+    pub(crate) health: Health,
+    pub(crate) verify: VerifyPolicy,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) retry_stats: RetryStats,
+    pub(crate) watchdog: Watchdog<P>,
+    #[cfg(feature = "stats")]
+    pub(crate) stats: Stats,
+}
+
+impl<P: Copy> Policy<P> {
+    /// Everything off, as a freshly created driver has it.
+    pub(crate) fn new() -> Self {
+        Self {
+            health: Health::default(),
+            verify: VerifyPolicy::OFF,
+            retry: RetryPolicy::NONE,
+            retry_stats: RetryStats::default(),
+            watchdog: Watchdog::default(),
+            #[cfg(feature = "stats")]
+            stats: Stats::default(),
+        }
+    }
+
+    /// Updates the statistics; compiles to nothing without the `stats` feature.
+    #[inline(always)]
+    pub(crate) fn record(&mut self, f: impl FnOnce(&mut Stats)) {
+        #[cfg(feature = "stats")]
+        f(&mut self.stats);
+        #[cfg(not(feature = "stats"))]
+        let _ = f;
+    }
+
+    /// Counts the transaction `access` is about to start. `frame_sizes` is
+    /// the transport's byte count for it.
+    #[inline]
+    pub(crate) fn begin(
+        &mut self,
+        access: &RegAccess<'_>,
+        frame_sizes: fn(bool, usize) -> (usize, usize),
+    ) {
+        self.record(|s| {
+            let (sent, received) = frame_sizes(access.is_read(), access.len());
+            s.record_transaction(sent, received);
+        });
+    }
+
+    /// Attempt number `attempt` (0-based) of a transaction succeeded.
+    pub(crate) fn succeeded(&mut self, attempt: u8) {
+        self.retry.on_success(attempt, &mut self.retry_stats);
+        self.watchdog.record_result(true);
+    }
+
+    /// Attempt number `attempt` failed with `kind`. Returns the backoff in
+    /// µs before the next attempt, or `None` if the error is final.
+    pub(crate) fn failed(&mut self, attempt: u8, kind: BusErrorKind) -> Option<u32> {
+        self.record(|s| s.errors.record(kind));
+        let backoff_us = self.retry.on_error(attempt, kind, &mut self.retry_stats);
+        match backoff_us {
+            Some(_) => self.record(|s| s.retries = s.retries.wrapping_add(1)),
+            None => self.watchdog.record_result(false),
+        }
+        backoff_us
+    }
+
+    /// Read-back owed after writing `reg`, `None` if the verify policy does
+    /// not cover it. `iocon_bits` are the `IOCON` bits of the chip variant.
+    pub(crate) fn readback(&self, reg: Reg, iocon_bits: u8) -> Option<Readback> {
+        let (from, mask) = self.verify.readback(reg, iocon_bits)?;
+        Some(Readback {
+            reg,
+            from,
+            mask,
+            retries: self.verify.retries,
+        })
+    }
+
+    /// Judges the bytes `read` back after writing `wrote`. `Continue` asks
+    /// for the write to be repeated; `Break` carries the final outcome.
+    pub(crate) fn verify<E>(
+        &mut self,
+        readback: &mut Readback,
+        wrote: &[u8],
+        read: &[u8],
+    ) -> ControlFlow<Result<(), Error<E>>> {
+        let mismatch = wrote
+            .iter()
+            .zip(read)
+            .find(|&(wrote, read)| (wrote ^ read) & readback.mask != 0);
+        let Some((&wrote, &read)) = mismatch else {
+            return ControlFlow::Break(Ok(()));
+        };
+        self.record(|s| s.verify_failures = s.verify_failures.wrapping_add(1));
+        if readback.retries == 0 {
+            return ControlFlow::Break(Err(Error::VerifyFailed {
+                reg: readback.reg,
+                wrote,
+                read,
+            }));
+        }
+        readback.retries -= 1;
+        ControlFlow::Continue(())
+    }
+
+    /// Refuses writes to `reg` while a latched watchdog fault blocks them.
+    pub(crate) fn guard<E>(&self, reg: Reg) -> Result<(), Error<E>> {
+        match self.watchdog.fault {
+            Some(fault) if self.watchdog.blocks(reg) => Err(Error::WatchdogFault(fault)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Trips the watchdog if the last feed is older than the timeout.
+    /// Returns the settings whose safe state must now be driven.
+    pub(crate) fn poll_watchdog(&mut self, now_ms: u64) -> Option<WatchdogConfig<P>> {
+        if self.watchdog.fault.is_some() || !self.watchdog.expired(now_ms) {
+            return None;
+        }
+        self.trip(WatchdogFault::Timeout)
+    }
+
+    /// Trips the watchdog once too many transactions in a row have failed,
+    /// `result` being the latest.
+    pub(crate) fn trip_on_bus_errors<T, E>(
+        &mut self,
+        result: &Result<T, Error<E>>,
+    ) -> Option<WatchdogConfig<P>> {
+        if result.is_ok() || !self.watchdog.bus_errors_exceeded() {
+            return None;
+        }
+        self.trip(WatchdogFault::BusErrors)
+    }
+
+    /// Latches `fault`, unless no watchdog is installed.
+    fn trip(&mut self, fault: WatchdogFault) -> Option<WatchdogConfig<P>> {
+        let cfg = self.watchdog.config?;
+        self.watchdog.fault = Some(fault);
+        Some(cfg)
+    }
+
+    /// Value kept in `DEFVAL` while a [`ResetDetect::Canary`] is installed.
+    pub(crate) fn canary(&self) -> Option<u8> {
+        match self.health.config?.detect {
+            ResetDetect::Canary(value) => Some(value),
+            ResetDetect::Iodir => None,
+        }
+    }
+
+    /// Refuses `DEFVAL` writes that would overwrite the canary.
+    pub(crate) fn guard_canary<E>(&self) -> Result<(), Error<E>> {
+        match self.canary() {
+            Some(_) => Err(Error::CanaryInUse),
+            None => Ok(()),
+        }
+    }
+
+    /// Register `check()` compares against the cache, `None` without a
+    /// health check.
+    pub(crate) fn detect_reg(&self) -> Option<Reg> {
+        self.health.config.map(|cfg| cfg.detect.reg())
+    }
+
+    /// Verdict of `check()` once it knows whether the detection register
+    /// still matches the cache. `Ok(true)` means the cached configuration
+    /// must be re-applied.
+    pub(crate) fn reset_seen<E>(&mut self, changed: bool) -> Result<bool, Error<E>> {
+        let Some(cfg) = self.health.config.filter(|_| changed) else {
+            return Ok(false);
+        };
+        self.health.record_reset();
+        self.record(|s| s.resets = s.resets.wrapping_add(1));
+        if !cfg.recover {
+            return Err(Error::UnexpectedReset);
+        }
+        Ok(true)
+    }
+}
+
+/// Read-back check of one register write.
+pub(crate) struct Readback {
+    /// Register written.
+    reg: Reg,
+    /// Register the written value is read back from.
+    pub(crate) from: Reg,
+    /// Bits that read back as written.
+    mask: u8,
+    /// Write attempts left after a mismatch.
+    retries: u8,
+}
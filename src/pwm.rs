@@ -0,0 +1,156 @@
+//! Low-frequency software PWM on expander outputs.
+//!
+//! [`SoftPwm`] splits a PWM period into `resolution` ticks. Each call to
+//! `tick()` works out the latch pattern for the next tick and writes it
+//! with `modify_port` only when it differs from the last one, so a channel
+//! costs at most two SPI writes per period however fine the resolution is.
+//! The tick rate is `frequency × resolution`: 100 Hz with 50 steps needs a
+//! 5 kHz timer.
+//!
+//! Duty cycles are set through [`SoftPwm::set_duty`] or a per-channel
+//! [`PwmChannel`] implementing `embedded_hal::pwm::SetDutyCycle`. State
+//! lives in atomics, so `SoftPwm` is `Sync`: channels in task context and
+//! `tick()` in a timer callback or interrupt can share one `&SoftPwm`.
+//! Only load/store is used, so targets without CAS (Cortex-M0) work too.
+
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use crate::expander::{AsyncIoExpander, IoExpander};
+use crate::mcp23s08::{Pin, PinSet};
+
+/// Software PWM generator for up to 8 pins of one expander.
+#[derive(Debug)]
+pub struct SoftPwm {
+    pins: PinSet,
+    resolution: u16,
+    duty: [AtomicU16; 8],
+    phase: AtomicU16,
+    /// Last pattern written, [`NOT_WRITTEN`] until the first write.
+    written: AtomicU16,
+}
+
+/// `written` before the first write; no pattern fits in 8 bits like this.
+const NOT_WRITTEN: u16 = u16::MAX;
+
+impl SoftPwm {
+    /// PWM on `pins` with `resolution` ticks per period (at least 1). All
+    /// duty cycles start at 0.
+    pub fn new(pins: impl Into<PinSet>, resolution: u16) -> Self {
+        Self {
+            pins: pins.into(),
+            resolution: resolution.max(1),
+            duty: Default::default(),
+            phase: AtomicU16::new(0),
+            written: AtomicU16::new(NOT_WRITTEN),
+        }
+    }
+
+    pub fn pins(&self) -> PinSet {
+        self.pins
+    }
+
+    pub fn resolution(&self) -> u16 {
+        self.resolution
+    }
+
+    /// Sets the number of ticks per period `pin` is high, clamped to the
+    /// resolution. Takes effect from the next tick.
+    pub fn set_duty(&self, pin: Pin, duty: u16) {
+        self.duty[pin as usize].store(duty.min(self.resolution), Ordering::Relaxed);
+    }
+
+    pub fn duty(&self, pin: Pin) -> u16 {
+        self.duty[pin as usize].load(Ordering::Relaxed)
+    }
+
+    /// `SetDutyCycle` handle for one channel.
+    pub fn channel(&self, pin: Pin) -> PwmChannel<'_> {
+        PwmChannel { pwm: self, pin }
+    }
+
+    /// Latch pattern of the PWM pins at `phase`.
+    pub fn pattern(&self, phase: u16) -> u8 {
+        self.pins
+            .iter()
+            .filter(|&pin| phase < self.duty(pin))
+            .fold(0, |bits, pin| bits | pin.mask())
+    }
+
+    /// Advances one tick. Call it at `frequency × resolution` Hz, e.g. from
+    /// a periodic timer callback. If the write fails the tick is not consumed.
+    ///
+    /// Only one context may tick a given `SoftPwm`; duty cycles can be
+    /// changed from anywhere.
+    pub fn tick<X>(&self, dev: &mut X) -> Result<(), X::Error>
+    where
+        X: IoExpander,
+    {
+        if let Some(pattern) = self.next_write() {
+            dev.modify_port(self.pins, pattern)?;
+            self.written.store(u16::from(pattern), Ordering::Relaxed);
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// Async counterpart of [`tick`](Self::tick).
+    pub async fn tick_async<X>(&self, dev: &mut X) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+    {
+        if let Some(pattern) = self.next_write() {
+            dev.modify_port(self.pins, pattern).await?;
+            self.written.store(u16::from(pattern), Ordering::Relaxed);
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// Runs the PWM forever, sleeping `tick_us` on `delay` between ticks.
+    /// Returns only when a write fails.
+    pub async fn run<X, T>(&self, dev: &mut X, delay: &mut T, tick_us: u32) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+        T: embedded_hal_async::delay::DelayNs,
+    {
+        loop {
+            self.tick_async(dev).await?;
+            delay.delay_us(tick_us).await;
+        }
+    }
+
+    /// The pattern for the current phase if it has to be written.
+    fn next_write(&self) -> Option<u8> {
+        let pattern = self.pattern(self.phase.load(Ordering::Relaxed));
+        (self.written.load(Ordering::Relaxed) != u16::from(pattern)).then_some(pattern)
+    }
+
+    fn advance(&self) {
+        let phase = self.phase.load(Ordering::Relaxed) + 1;
+        let phase = if phase >= self.resolution { 0 } else { phase };
+        self.phase.store(phase, Ordering::Relaxed);
+    }
+}
+
+/// One PWM channel of a [`SoftPwm`].
+#[derive(Clone, Copy, Debug)]
+pub struct PwmChannel<'a> {
+    pwm: &'a SoftPwm,
+    pin: Pin,
+}
+
+impl embedded_hal::pwm::ErrorType for PwmChannel<'_> {
+    type Error = Infallible;
+}
+
+impl embedded_hal::pwm::SetDutyCycle for PwmChannel<'_> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.pwm.resolution
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+        self.pwm.set_duty(self.pin, duty);
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! Retrying of failed bus transactions.
+//!
+//! A [`RetryPolicy`] repeats a transaction whose error the classifier deems
+//! transient, optionally waiting with exponential backoff between attempts.
+//! The driver counts what happened in [`RetryStats`].
+
+use crate::transport::BusErrorKind;
+
+/// Delay that returns immediately. Default delay type of the drivers, used
+/// when retries need no backoff.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoDelay;
+
+impl embedded_hal::delay::DelayNs for NoDelay {
+    #[inline]
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    #[inline]
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Retry settings of a driver.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Attempts per transaction, including the first one. `1` disables
+    /// retries.
+    pub max_attempts: u8,
+    /// Delay before the first retry; doubled for each further retry. Needs a
+    /// delay installed with `with_delay`.
+    pub backoff_us: u32,
+    /// Decides whether an error is worth another attempt.
+    pub retryable: fn(BusErrorKind) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl RetryPolicy {
+    /// Every error is returned at once.
+    pub const NONE: RetryPolicy = RetryPolicy {
+        max_attempts: 1,
+        backoff_us: 0,
+        retryable: is_transient,
+    };
+
+    /// `max_attempts` tries without backoff, retrying [`is_transient`] errors.
+    pub const fn new(max_attempts: u8) -> Self {
+        Self {
+            max_attempts,
+            backoff_us: 0,
+            retryable: is_transient,
+        }
+    }
+
+    pub const fn backoff_us(mut self, backoff_us: u32) -> Self {
+        self.backoff_us = backoff_us;
+        self
+    }
+
+    pub const fn retryable(mut self, retryable: fn(BusErrorKind) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Called after attempt number `attempt` (0-based) failed with `kind`.
+    /// Returns the backoff in µs if the transaction should be repeated.
+    pub(crate) fn on_error(
+        &self,
+        attempt: u8,
+        kind: BusErrorKind,
+        stats: &mut RetryStats,
+    ) -> Option<u32> {
+        if attempt.saturating_add(1) >= self.max_attempts || !(self.retryable)(kind) {
+            if attempt > 0 {
+                stats.exhausted = stats.exhausted.wrapping_add(1);
+            }
+            return None;
+        }
+        if attempt == 0 {
+            stats.retried = stats.retried.wrapping_add(1);
+        }
+        stats.retries = stats.retries.wrapping_add(1);
+        Some(self.backoff_us.saturating_mul(1 << attempt.min(16)))
+    }
+
+    /// Called when attempt number `attempt` succeeded.
+    pub(crate) fn on_success(&self, attempt: u8, stats: &mut RetryStats) {
+        if attempt > 0 {
+            stats.recovered = stats.recovered.wrapping_add(1);
+        }
+    }
+}
+
+/// Default classifier: everything except `ModeFault` and
+/// `ChipSelectFault`, which point at a misconfigured bus rather than noise,
+/// and `NoAcknowledge`: an I²C chip that does not answer is missing, not
+/// busy.
+pub fn is_transient(kind: BusErrorKind) -> bool {
+    !matches!(
+        kind,
+        BusErrorKind::ModeFault | BusErrorKind::ChipSelectFault | BusErrorKind::NoAcknowledge(_)
+    )
+}
+
+/// Classifier that retries every error.
+pub fn any_error(_kind: BusErrorKind) -> bool {
+    true
+}
+
+/// How often retries were needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryStats {
+    /// Transactions that failed at least once and were retried.
+    pub retried: u32,
+    /// Extra attempts made in total.
+    pub retries: u32,
+    /// Retried transactions that eventually succeeded.
+    pub recovered: u32,
+    /// Retried transactions that still failed after the last attempt.
+    pub exhausted: u32,
+}
@@ -0,0 +1,211 @@
+//! Timed output changes: pulses, blinking and delayed set/clear.
+//!
+//! A [`Scheduler`] keeps at most one pending job per pin. Jobs are applied
+//! by `poll(dev, now_ms)` from a timer or the main loop; everything that is
+//! due at that moment goes out as a single `modify_port` write, so the
+//! driver's interlock table still applies. With the async driver,
+//! [`run`](Scheduler::run) sleeps on a `DelayNs` between deadlines instead.
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, Reg};
+//! use mcp23s08_io::scheduler::Scheduler;
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! dev.set_port_direction(0x00).unwrap();
+//!
+//! let mut sched = Scheduler::new();
+//! sched.pulse(Pin::P4, 0, 250);
+//! sched.poll(&mut dev, 0).unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0x10);
+//! sched.poll(&mut dev, 250).unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+//! ```
+//!
+//! Times are milliseconds of whatever monotonic clock the application has.
+//! Deadlines are kept on a fixed grid, so a late `poll` does not stretch a
+//! blink period.
+
+use crate::expander::{AsyncIoExpander, IoExpander};
+use crate::mcp23s08::{Pin, PinSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Job {
+    /// When `level` is applied.
+    due: u64,
+    level: bool,
+    /// Time the pin stays high/low before the following change.
+    on_ms: u32,
+    off_ms: u32,
+    /// Changes still to come after this one.
+    remaining: u32,
+}
+
+impl Job {
+    /// Moves to the next change, or returns `None` when the job is done.
+    fn advance(self) -> Option<Job> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let hold = if self.level { self.on_ms } else { self.off_ms };
+        Some(Job {
+            due: self.due + u64::from(hold),
+            level: !self.level,
+            remaining: self.remaining - 1,
+            ..self
+        })
+    }
+}
+
+/// Pending timed output changes, one slot per pin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Scheduler {
+    jobs: [Option<Job>; 8],
+}
+
+impl Scheduler {
+    pub const fn new() -> Self {
+        Self { jobs: [None; 8] }
+    }
+
+    /// Drives `pin` high at `now_ms` and low again `duration_ms` later.
+    /// Replaces any pending job of the pin.
+    pub fn pulse(&mut self, pin: Pin, now_ms: u64, duration_ms: u32) {
+        self.jobs[pin as usize] = Some(Job {
+            due: now_ms,
+            level: true,
+            on_ms: duration_ms,
+            off_ms: 0,
+            remaining: 1,
+        });
+    }
+
+    /// Blinks `pin` `count` times starting at `now_ms`, high for `on_ms` and
+    /// low for `off_ms`. The pin ends low. Replaces any pending job of the
+    /// pin; `count == 0` just cancels it.
+    pub fn blink(&mut self, pin: Pin, now_ms: u64, on_ms: u32, off_ms: u32, count: u16) {
+        self.jobs[pin as usize] = (count > 0).then_some(Job {
+            due: now_ms,
+            level: true,
+            on_ms,
+            off_ms,
+            remaining: u32::from(count) * 2 - 1,
+        });
+    }
+
+    /// Sets `pin` to `high` once `delay_ms` have passed since `now_ms`.
+    /// Replaces any pending job of the pin.
+    pub fn set_after(&mut self, pin: Pin, high: bool, now_ms: u64, delay_ms: u32) {
+        self.jobs[pin as usize] = Some(Job {
+            due: now_ms + u64::from(delay_ms),
+            level: high,
+            on_ms: 0,
+            off_ms: 0,
+            remaining: 0,
+        });
+    }
+
+    /// Drops the pending job of `pin`. The pin keeps its current level.
+    pub fn cancel(&mut self, pin: Pin) {
+        self.jobs[pin as usize] = None;
+    }
+
+    /// Drops every pending job.
+    pub fn cancel_all(&mut self) {
+        self.jobs = [None; 8];
+    }
+
+    /// Pins with a pending job.
+    pub fn pending(&self) -> PinSet {
+        Pin::ALL
+            .into_iter()
+            .filter(|&pin| self.jobs[pin as usize].is_some())
+            .collect()
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.jobs.iter().all(Option::is_none)
+    }
+
+    /// Earliest pending deadline.
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.jobs.iter().flatten().map(|job| job.due).min()
+    }
+
+    /// Applies everything due at `now_ms` in one write and returns the next
+    /// deadline. Nothing is written when nothing is due. If the write fails
+    /// the jobs stay pending and are retried on the next poll.
+    pub fn poll<X>(&mut self, dev: &mut X, now_ms: u64) -> Result<Option<u64>, X::Error>
+    where
+        X: IoExpander,
+    {
+        let (next, mask, value) = self.step(now_ms);
+        if !mask.is_empty() {
+            dev.modify_port(mask, value)?;
+        }
+        *self = next;
+        Ok(self.next_deadline())
+    }
+
+    /// Async counterpart of [`poll`](Self::poll).
+    pub async fn poll_async<X>(&mut self, dev: &mut X, now_ms: u64) -> Result<Option<u64>, X::Error>
+    where
+        X: AsyncIoExpander,
+    {
+        let (next, mask, value) = self.step(now_ms);
+        if !mask.is_empty() {
+            dev.modify_port(mask, value).await?;
+        }
+        *self = next;
+        Ok(self.next_deadline())
+    }
+
+    /// Runs the pending jobs to completion, sleeping on `delay` between
+    /// deadlines. `start_ms` is the time the jobs were scheduled against.
+    /// Returns the time at which the last change was applied.
+    pub async fn run<X, T>(
+        &mut self,
+        dev: &mut X,
+        delay: &mut T,
+        start_ms: u64,
+    ) -> Result<u64, X::Error>
+    where
+        X: AsyncIoExpander,
+        T: embedded_hal_async::delay::DelayNs,
+    {
+        let mut now = start_ms;
+        while let Some(due) = self.next_deadline() {
+            // A deadline may lie more than `u32::MAX` ms ahead.
+            while due > now {
+                let ms = u32::try_from(due - now).unwrap_or(u32::MAX);
+                delay.delay_ms(ms).await;
+                now += u64::from(ms);
+            }
+            self.poll_async(dev, now).await?;
+        }
+        Ok(now)
+    }
+
+    /// The scheduler after applying everything due at `now_ms`, and the
+    /// pins/levels to write for it.
+    fn step(&self, now_ms: u64) -> (Scheduler, PinSet, u8) {
+        let mut next = *self;
+        let mut mask = PinSet::EMPTY;
+        let mut value = 0;
+        for pin in Pin::ALL {
+            let mut slot = next.jobs[pin as usize];
+            while let Some(job) = slot.filter(|job| job.due <= now_ms) {
+                mask.insert(pin);
+                if job.level {
+                    value |= pin.mask();
+                } else {
+                    value &= !pin.mask();
+                }
+                slot = job.advance();
+            }
+            next.jobs[pin as usize] = slot;
+        }
+        (next, mask, value)
+    }
+}
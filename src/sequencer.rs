@@ -0,0 +1,272 @@
+//! Timeline playback of port states.
+//!
+//! A [`Sequencer`] plays a list of [`Step`]s, each a latch value (optionally
+//! limited to a pin mask) at an offset from the start of the timeline. The
+//! list is any cloneable iterator of steps, typically a `static` table:
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Reg};
+//! use mcp23s08_io::sequencer::{Sequencer, Step};
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! static CHASE: [Step; 3] = [
+//!     Step::new(0, 0b001),
+//!     Step::new(100, 0b010),
+//!     Step::new(200, 0b100),
+//! ];
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! dev.set_port_direction(0x00).unwrap();
+//!
+//! let mut seq = Sequencer::new(CHASE.iter().copied()).looping(300);
+//! seq.start(0);
+//! seq.tick(&mut dev, 0).unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0b001);
+//! seq.tick(&mut dev, 150).unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0b010);
+//! ```
+//!
+//! Time only moves when `tick(dev, now_ms)` (or `tick_async`) is called, so
+//! tests drive it with made-up timestamps. Steps that fall due together are
+//! merged into one `modify_port` write.
+
+use crate::expander::{AsyncIoExpander, IoExpander};
+use crate::mcp23s08::PinSet;
+
+/// One timeline entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Step {
+    /// Offset from the start of the timeline. Steps must be in order.
+    pub at_ms: u32,
+    pub value: u8,
+    /// Pins this step drives; the others keep their latch.
+    pub mask: PinSet,
+}
+
+impl Step {
+    /// Drives the whole port.
+    pub const fn new(at_ms: u32, value: u8) -> Self {
+        Self {
+            at_ms,
+            value,
+            mask: PinSet::ALL,
+        }
+    }
+
+    /// Drives only the pins in `mask`.
+    pub const fn masked(at_ms: u32, value: u8, mask: PinSet) -> Self {
+        Self { at_ms, value, mask }
+    }
+}
+
+/// Playback state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Playback {
+    Stopped,
+    Playing,
+    Paused,
+    /// The last step of a non-looping timeline was played.
+    Finished,
+}
+
+/// Plays a timeline onto the output latch.
+#[derive(Clone, Debug)]
+pub struct Sequencer<I> {
+    timeline: I,
+    cursor: I,
+    next: Option<Step>,
+    period_ms: Option<u32>,
+    speed_percent: u16,
+    playback: Playback,
+    /// Timeline time of the current pass, in µs so slow speeds do not lose
+    /// time to rounding.
+    position_us: u64,
+    last_tick: u64,
+}
+
+impl<I> Sequencer<I>
+where
+    I: Iterator<Item = Step> + Clone,
+{
+    /// Stopped at the beginning, played once at normal speed.
+    pub fn new(timeline: I) -> Self {
+        let mut cursor = timeline.clone();
+        let next = cursor.next();
+        Self {
+            timeline,
+            cursor,
+            next,
+            period_ms: None,
+            speed_percent: 100,
+            playback: Playback::Stopped,
+            position_us: 0,
+            last_tick: 0,
+        }
+    }
+
+    /// Repeats the timeline every `period_ms` (at least 1 ms).
+    pub fn looping(mut self, period_ms: u32) -> Self {
+        self.period_ms = Some(period_ms.max(1));
+        self
+    }
+
+    /// Playback speed in percent: `200` plays twice as fast, `50` half as
+    /// fast, `0` holds the current position. Takes effect from the next tick.
+    pub fn set_speed_percent(&mut self, percent: u16) {
+        self.speed_percent = percent;
+    }
+
+    pub fn speed_percent(&self) -> u16 {
+        self.speed_percent
+    }
+
+    pub fn playback(&self) -> Playback {
+        self.playback
+    }
+
+    /// Timeline time of the current pass.
+    pub fn position_ms(&self) -> u64 {
+        self.position_us / 1_000
+    }
+
+    /// Plays from the beginning, starting at `now_ms`.
+    pub fn start(&mut self, now_ms: u64) {
+        self.rewind();
+        self.playback = Playback::Playing;
+        self.last_tick = now_ms;
+    }
+
+    /// Freezes the timeline where it is.
+    pub fn pause(&mut self) {
+        if self.playback == Playback::Playing {
+            self.playback = Playback::Paused;
+        }
+    }
+
+    /// Continues a paused timeline from `now_ms`.
+    pub fn resume(&mut self, now_ms: u64) {
+        if self.playback == Playback::Paused {
+            self.playback = Playback::Playing;
+            self.last_tick = now_ms;
+        }
+    }
+
+    /// Stops and rewinds. The outputs keep their current state.
+    pub fn stop(&mut self) {
+        self.rewind();
+        self.playback = Playback::Stopped;
+    }
+
+    /// Plays every step due at `now_ms` and returns when the next one is
+    /// due, or `None` when nothing more will happen without `start`/`resume`.
+    /// If the write fails the position is not advanced.
+    pub fn tick<X>(&mut self, dev: &mut X, now_ms: u64) -> Result<Option<u64>, X::Error>
+    where
+        X: IoExpander,
+    {
+        let mut next = self.clone();
+        let (mask, value) = next.advance(now_ms);
+        if !mask.is_empty() {
+            dev.modify_port(mask, value)?;
+        }
+        *self = next;
+        Ok(self.deadline(now_ms))
+    }
+
+    /// Async counterpart of [`tick`](Self::tick).
+    pub async fn tick_async<X>(&mut self, dev: &mut X, now_ms: u64) -> Result<Option<u64>, X::Error>
+    where
+        X: AsyncIoExpander,
+    {
+        let mut next = self.clone();
+        let (mask, value) = next.advance(now_ms);
+        if !mask.is_empty() {
+            dev.modify_port(mask, value).await?;
+        }
+        *self = next;
+        Ok(self.deadline(now_ms))
+    }
+
+    /// Starts playback and sleeps on `delay` between steps until the
+    /// timeline finishes. A looping timeline never returns unless a write
+    /// fails.
+    pub async fn run<X, T>(&mut self, dev: &mut X, delay: &mut T) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+        T: embedded_hal_async::delay::DelayNs,
+    {
+        let mut now = 0;
+        self.start(now);
+        while let Some(due) = self.tick_async(dev, now).await? {
+            // Slow playback can put a step more than `u32::MAX` ms ahead.
+            while due > now {
+                let ms = u32::try_from(due - now).unwrap_or(u32::MAX);
+                delay.delay_ms(ms).await;
+                now += u64::from(ms);
+            }
+        }
+        Ok(())
+    }
+
+    fn rewind(&mut self) {
+        self.cursor = self.timeline.clone();
+        self.next = self.cursor.next();
+        self.position_us = 0;
+    }
+
+    /// Moves the timeline to `now_ms` and returns the pins and levels of the
+    /// steps passed on the way.
+    fn advance(&mut self, now_ms: u64) -> (PinSet, u8) {
+        let mut mask = PinSet::EMPTY;
+        let mut value = 0;
+        if self.playback != Playback::Playing {
+            return (mask, value);
+        }
+        let elapsed_ms = now_ms.saturating_sub(self.last_tick);
+        self.last_tick = now_ms;
+        self.position_us += elapsed_ms * u64::from(self.speed_percent) * 10;
+
+        loop {
+            match self.next {
+                Some(step) if u64::from(step.at_ms) * 1_000 <= self.position_us => {
+                    value = (value & !step.mask.bits()) | (step.value & step.mask.bits());
+                    mask |= step.mask;
+                    self.next = self.cursor.next();
+                }
+                Some(_) => break,
+                None => match self.period_ms {
+                    Some(period) if self.position_us >= u64::from(period) * 1_000 => {
+                        let position_us = self.position_us - u64::from(period) * 1_000;
+                        self.rewind();
+                        self.position_us = position_us;
+                    }
+                    Some(_) => break,
+                    None => {
+                        self.playback = Playback::Finished;
+                        break;
+                    }
+                },
+            }
+        }
+        (mask, value)
+    }
+
+    /// Wall-clock time of the next step or loop restart.
+    fn deadline(&self, now_ms: u64) -> Option<u64> {
+        if self.playback != Playback::Playing || self.speed_percent == 0 {
+            return None;
+        }
+        let target_ms = match (self.next, self.period_ms) {
+            (Some(step), _) => step.at_ms,
+            (None, Some(period)) => period,
+            (None, None) => return None,
+        };
+        let ahead_us = (u64::from(target_ms) * 1_000).saturating_sub(self.position_us);
+        // Timeline µs → wall ms, rounded up so the step is due when woken.
+        let scale = u64::from(self.speed_percent) * 10;
+        Some(now_ms + ahead_us.div_ceil(scale))
+    }
+}
@@ -0,0 +1,698 @@
+//! One expander shared between several tasks or threads.
+//!
+//! The drivers take `&mut self`, so two tasks cannot both hold one. These
+//! wrappers own the driver behind a mutex and hand out cheap, cloneable
+//! handles that implement [`IoExpander`](crate::expander::IoExpander) /
+//! [`AsyncIoExpander`](crate::expander::AsyncIoExpander) themselves:
+//! every call locks the driver for its whole duration, so the
+//! read-modify-writes of the cached `OLAT` and `IODIR` (`modify_port`,
+//! `write_pin`, `modify_port_direction`, …) cannot interleave with another
+//! task's. Pin objects from `pin()` on a handle are cloneable too.
+//!
+//! - `SharedExpander` (feature `critical-section`) — blocking drivers;
+//!   each call runs inside a critical section, so handles may also be used
+//!   from interrupt handlers. The bus must work with interrupts disabled.
+//! - `AsyncSharedExpander` (feature `embassy-sync`) — async drivers behind
+//!   an `embassy_sync` mutex; `M` picks the raw mutex
+//!   (`CriticalSectionRawMutex`, `NoopRawMutex`, …).
+//! - `ArcExpander` / `SharedMcp23s08` (feature `std`) — blocking drivers
+//!   behind `Arc<Mutex<_>>`. The `ArcExpander` is itself the cloneable
+//!   handle; it and its pins are `Send + Sync`, for use across threads.
+//!
+//! Use `with` / `lock` to run several calls as one atomic step.
+//!
+//! ```
+//! # #[cfg(feature = "critical-section")] {
+//! use embedded_hal::digital::OutputPin;
+//! use mcp23s08_io::expander::IoExpander;
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, Reg};
+//! use mcp23s08_io::shared::SharedExpander;
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let shared = SharedExpander::new(Mcp23s08::new(sim.device(), 0).unwrap());
+//! let mut io = shared.handle();
+//! io.set_port_direction(0xF0).unwrap();
+//!
+//! // A relay task and a status-LED task each keep their own pin.
+//! let mut relay = io.pin(Pin::P0);
+//! let mut led = io.pin(Pin::P3);
+//! relay.set_high().unwrap();
+//! led.set_high().unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0x09);
+//! # }
+//! ```
+
+#[cfg(feature = "critical-section")]
+pub use self::blocking::{SharedExpander, SharedHandle, SharedPin};
+#[cfg(feature = "embassy-sync")]
+pub use self::nonblocking::{
+    AsyncSharedExpander, AsyncSharedGuard, AsyncSharedHandle, AsyncSharedPin,
+};
+#[cfg(feature = "std")]
+pub use self::threaded::{ArcExpander, ArcPin, SharedMcp23s08};
+
+#[cfg(feature = "critical-section")]
+mod blocking {
+    use core::cell::RefCell;
+
+    use critical_section::Mutex;
+
+    use crate::expander::IoExpander;
+    use crate::mcp23s08::{Pin, PinSet};
+
+    /// Blocking expander shared through critical sections.
+    ///
+    /// Usually placed in a `static` (e.g. with `static_cell`) so that
+    /// handles are `'static`.
+    pub struct SharedExpander<X> {
+        dev: Mutex<RefCell<X>>,
+    }
+
+    impl<X> SharedExpander<X> {
+        pub const fn new(dev: X) -> Self {
+            Self {
+                dev: Mutex::new(RefCell::new(dev)),
+            }
+        }
+
+        pub fn handle(&self) -> SharedHandle<'_, X> {
+            SharedHandle { shared: self }
+        }
+
+        /// Runs `f` on the driver inside one critical section.
+        ///
+        /// # Panics
+        ///
+        /// If `f` uses a handle of the same expander.
+        pub fn with<R>(&self, f: impl FnOnce(&mut X) -> R) -> R {
+            critical_section::with(|cs| f(&mut self.dev.borrow_ref_mut(cs)))
+        }
+
+        pub fn into_inner(self) -> X {
+            self.dev.into_inner().into_inner()
+        }
+    }
+
+    /// Cloneable [`IoExpander`] handle of a [`SharedExpander`]. Each call is
+    /// one critical section.
+    pub struct SharedHandle<'a, X> {
+        shared: &'a SharedExpander<X>,
+    }
+
+    impl<X> Clone for SharedHandle<'_, X> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<X> Copy for SharedHandle<'_, X> {}
+
+    impl<'a, X> SharedHandle<'a, X> {
+        /// See [`SharedExpander::with`].
+        pub fn with<R>(&self, f: impl FnOnce(&mut X) -> R) -> R {
+            self.shared.with(f)
+        }
+
+        /// Cloneable pin object. The direction is not changed.
+        pub fn pin(&self, pin: Pin) -> SharedPin<'a, X> {
+            SharedPin { io: *self, pin }
+        }
+    }
+
+    impl<X: IoExpander> IoExpander for SharedHandle<'_, X> {
+        type Error = X::Error;
+
+        const WIDTH: u8 = X::WIDTH;
+
+        fn read_port(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_port())
+        }
+
+        fn output_latch(&self) -> u8 {
+            self.with(|dev| dev.output_latch())
+        }
+
+        fn write_olat(&mut self, value: u8) -> Result<(), X::Error> {
+            self.with(|dev| dev.write_olat(value))
+        }
+
+        fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), X::Error> {
+            self.with(|dev| dev.modify_port(mask, value))
+        }
+
+        fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_port_direction(inputs))
+        }
+
+        fn modify_port_direction(
+            &mut self,
+            mask: impl Into<PinSet>,
+            inputs: u8,
+        ) -> Result<(), X::Error> {
+            self.with(|dev| dev.modify_port_direction(mask, inputs))
+        }
+
+        fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_port_pullups(mask))
+        }
+
+        fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_pin_pullup(pin, enable))
+        }
+
+        fn read_interrupt_flags(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_interrupt_flags())
+        }
+
+        fn read_interrupt_capture(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_interrupt_capture())
+        }
+
+        fn read_pin(&mut self, pin: Pin) -> Result<bool, X::Error> {
+            self.with(|dev| dev.read_pin(pin))
+        }
+
+        fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.write_pin(pin, high))
+        }
+
+        fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_pin_direction(pin, input))
+        }
+    }
+
+    /// Cloneable pin of a [`SharedExpander`].
+    pub struct SharedPin<'a, X> {
+        io: SharedHandle<'a, X>,
+        pin: Pin,
+    }
+
+    impl<X> Clone for SharedPin<'_, X> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<X> Copy for SharedPin<'_, X> {}
+
+    impl<X> SharedPin<'_, X> {
+        pub fn pin(&self) -> Pin {
+            self.pin
+        }
+    }
+
+    impl<X> embedded_hal::digital::ErrorType for SharedPin<'_, X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        type Error = X::Error;
+    }
+
+    impl<X> embedded_hal::digital::InputPin for SharedPin<'_, X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            self.io.read_pin(self.pin)
+        }
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.is_high()?)
+        }
+    }
+
+    impl<X> embedded_hal::digital::OutputPin for SharedPin<'_, X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.io.write_pin(self.pin, true)
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.io.write_pin(self.pin, false)
+        }
+    }
+
+    impl<X> embedded_hal::digital::StatefulOutputPin for SharedPin<'_, X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.io.output_latch() & self.pin.mask() != 0)
+        }
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.is_set_high()?)
+        }
+        /// Inverts the latch bit in one critical section.
+        fn toggle(&mut self) -> Result<(), Self::Error> {
+            let pin = self.pin;
+            self.io
+                .with(|dev| dev.modify_port(pin, !dev.output_latch()))
+        }
+    }
+}
+
+#[cfg(feature = "embassy-sync")]
+mod nonblocking {
+    use core::ops::{Deref, DerefMut};
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use embassy_sync::blocking_mutex::raw::RawMutex;
+    use embassy_sync::mutex::{Mutex, MutexGuard};
+
+    use crate::expander::AsyncIoExpander;
+    use crate::mcp23s08::{Pin, PinSet};
+
+    /// Async expander shared through an `embassy_sync` mutex.
+    ///
+    /// The output latch is mirrored outside the mutex so that
+    /// `output_latch()` never waits for another task's transfer.
+    pub struct AsyncSharedExpander<M: RawMutex, X> {
+        dev: Mutex<M, X>,
+        olat: AtomicU8,
+    }
+
+    impl<M: RawMutex, X: AsyncIoExpander> AsyncSharedExpander<M, X> {
+        pub fn new(dev: X) -> Self {
+            Self {
+                olat: AtomicU8::new(dev.output_latch()),
+                dev: Mutex::new(dev),
+            }
+        }
+
+        pub fn handle(&self) -> AsyncSharedHandle<'_, M, X> {
+            AsyncSharedHandle { shared: self }
+        }
+
+        /// Locks the driver for several calls. The latch mirror is updated
+        /// when the guard is dropped.
+        pub async fn lock(&self) -> AsyncSharedGuard<'_, M, X> {
+            AsyncSharedGuard {
+                dev: self.dev.lock().await,
+                olat: &self.olat,
+            }
+        }
+
+        pub fn into_inner(self) -> X {
+            self.dev.into_inner()
+        }
+
+        /// Runs `op` with the driver locked.
+        async fn run<R>(&self, op: impl AsyncFnOnce(&mut X) -> R) -> R {
+            op(&mut *self.lock().await).await
+        }
+    }
+
+    /// Exclusive access to the driver of an [`AsyncSharedExpander`].
+    pub struct AsyncSharedGuard<'a, M: RawMutex, X: AsyncIoExpander> {
+        dev: MutexGuard<'a, M, X>,
+        olat: &'a AtomicU8,
+    }
+
+    impl<M: RawMutex, X: AsyncIoExpander> Deref for AsyncSharedGuard<'_, M, X> {
+        type Target = X;
+
+        fn deref(&self) -> &X {
+            &self.dev
+        }
+    }
+
+    impl<M: RawMutex, X: AsyncIoExpander> DerefMut for AsyncSharedGuard<'_, M, X> {
+        fn deref_mut(&mut self) -> &mut X {
+            &mut self.dev
+        }
+    }
+
+    impl<M: RawMutex, X: AsyncIoExpander> Drop for AsyncSharedGuard<'_, M, X> {
+        fn drop(&mut self) {
+            self.olat.store(self.dev.output_latch(), Ordering::Relaxed);
+        }
+    }
+
+    /// Cloneable [`AsyncIoExpander`] handle of an [`AsyncSharedExpander`].
+    /// Each call holds the mutex until it completes.
+    pub struct AsyncSharedHandle<'a, M: RawMutex, X> {
+        shared: &'a AsyncSharedExpander<M, X>,
+    }
+
+    impl<M: RawMutex, X> Clone for AsyncSharedHandle<'_, M, X> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<M: RawMutex, X> Copy for AsyncSharedHandle<'_, M, X> {}
+
+    impl<'a, M: RawMutex, X: AsyncIoExpander> AsyncSharedHandle<'a, M, X> {
+        /// See [`AsyncSharedExpander::lock`].
+        pub async fn lock(&self) -> AsyncSharedGuard<'a, M, X> {
+            self.shared.lock().await
+        }
+
+        /// Cloneable pin object. The direction is not changed.
+        pub fn pin(&self, pin: Pin) -> AsyncSharedPin<'a, M, X> {
+            AsyncSharedPin { io: *self, pin }
+        }
+    }
+
+    impl<M: RawMutex, X: AsyncIoExpander> AsyncIoExpander for AsyncSharedHandle<'_, M, X> {
+        type Error = X::Error;
+
+        const WIDTH: u8 = X::WIDTH;
+
+        async fn read_port(&mut self) -> Result<u8, X::Error> {
+            self.shared.run(async |dev| dev.read_port().await).await
+        }
+
+        fn output_latch(&self) -> u8 {
+            self.shared.olat.load(Ordering::Relaxed)
+        }
+
+        async fn write_olat(&mut self, value: u8) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.write_olat(value).await)
+                .await
+        }
+
+        async fn modify_port(
+            &mut self,
+            mask: impl Into<PinSet>,
+            value: u8,
+        ) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.modify_port(mask, value).await)
+                .await
+        }
+
+        async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.set_port_direction(inputs).await)
+                .await
+        }
+
+        async fn modify_port_direction(
+            &mut self,
+            mask: impl Into<PinSet>,
+            inputs: u8,
+        ) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.modify_port_direction(mask, inputs).await)
+                .await
+        }
+
+        async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.set_port_pullups(mask).await)
+                .await
+        }
+
+        async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.set_pin_pullup(pin, enable).await)
+                .await
+        }
+
+        async fn read_interrupt_flags(&mut self) -> Result<u8, X::Error> {
+            self.shared
+                .run(async |dev| dev.read_interrupt_flags().await)
+                .await
+        }
+
+        async fn read_interrupt_capture(&mut self) -> Result<u8, X::Error> {
+            self.shared
+                .run(async |dev| dev.read_interrupt_capture().await)
+                .await
+        }
+
+        async fn read_pin(&mut self, pin: Pin) -> Result<bool, X::Error> {
+            self.shared.run(async |dev| dev.read_pin(pin).await).await
+        }
+
+        async fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.write_pin(pin, high).await)
+                .await
+        }
+
+        async fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), X::Error> {
+            self.shared
+                .run(async |dev| dev.set_pin_direction(pin, input).await)
+                .await
+        }
+    }
+
+    /// Cloneable pin of an [`AsyncSharedExpander`].
+    pub struct AsyncSharedPin<'a, M: RawMutex, X> {
+        io: AsyncSharedHandle<'a, M, X>,
+        pin: Pin,
+    }
+
+    impl<M: RawMutex, X> Clone for AsyncSharedPin<'_, M, X> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<M: RawMutex, X> Copy for AsyncSharedPin<'_, M, X> {}
+
+    impl<M: RawMutex, X: AsyncIoExpander> AsyncSharedPin<'_, M, X> {
+        pub fn pin(&self) -> Pin {
+            self.pin
+        }
+
+        pub async fn is_high(&mut self) -> Result<bool, X::Error> {
+            self.io.read_pin(self.pin).await
+        }
+
+        pub async fn is_low(&mut self) -> Result<bool, X::Error> {
+            Ok(!self.is_high().await?)
+        }
+
+        pub async fn set_high(&mut self) -> Result<(), X::Error> {
+            self.io.write_pin(self.pin, true).await
+        }
+
+        pub async fn set_low(&mut self) -> Result<(), X::Error> {
+            self.io.write_pin(self.pin, false).await
+        }
+
+        pub async fn is_set_high(&mut self) -> Result<bool, X::Error> {
+            Ok(self.io.output_latch() & self.pin.mask() != 0)
+        }
+
+        pub async fn is_set_low(&mut self) -> Result<bool, X::Error> {
+            Ok(!self.is_set_high().await?)
+        }
+
+        /// Inverts the latch bit with the mutex held throughout.
+        pub async fn toggle(&mut self) -> Result<(), X::Error> {
+            let pin = self.pin;
+            self.io
+                .shared
+                .run(async |dev| dev.modify_port(pin, !dev.output_latch()).await)
+                .await
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod threaded {
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    use crate::expander::IoExpander;
+    use crate::mcp23s08::{Mcp23s08, NoDelay, Pin, PinSet};
+
+    /// [`Mcp23s08`] shared between threads.
+    pub type SharedMcp23s08<B, D = NoDelay> = ArcExpander<Mcp23s08<B, D>>;
+
+    /// Blocking expander behind `Arc<Mutex<_>>`. Clones are handles to the
+    /// same driver; each [`IoExpander`] call locks it once.
+    ///
+    /// A thread that panics while holding the lock does not poison the
+    /// other handles.
+    pub struct ArcExpander<X> {
+        dev: Arc<Mutex<X>>,
+    }
+
+    impl<X> Clone for ArcExpander<X> {
+        fn clone(&self) -> Self {
+            Self {
+                dev: Arc::clone(&self.dev),
+            }
+        }
+    }
+
+    impl<X> ArcExpander<X> {
+        pub fn new(dev: X) -> Self {
+            Self {
+                dev: Arc::new(Mutex::new(dev)),
+            }
+        }
+
+        /// Runs `f` on the driver with the lock held.
+        ///
+        /// # Panics
+        ///
+        /// If `f` uses a handle of the same expander, the re-entrant
+        /// `Mutex::lock` either deadlocks or panics; which one is up to the
+        /// platform.
+        pub fn with<R>(&self, f: impl FnOnce(&mut X) -> R) -> R {
+            f(&mut self.dev.lock().unwrap_or_else(PoisonError::into_inner))
+        }
+
+        /// Cloneable, `Send` pin object. The direction is not changed.
+        pub fn pin(&self, pin: Pin) -> ArcPin<X> {
+            ArcPin {
+                io: self.clone(),
+                pin,
+            }
+        }
+
+        /// The driver, if this is the last handle (pins count as handles).
+        pub fn try_into_inner(self) -> Result<X, Self> {
+            Arc::try_unwrap(self.dev)
+                .map(|dev| dev.into_inner().unwrap_or_else(PoisonError::into_inner))
+                .map_err(|dev| Self { dev })
+        }
+    }
+
+    impl<X: IoExpander> IoExpander for ArcExpander<X> {
+        type Error = X::Error;
+
+        const WIDTH: u8 = X::WIDTH;
+
+        fn read_port(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_port())
+        }
+
+        fn output_latch(&self) -> u8 {
+            self.with(|dev| dev.output_latch())
+        }
+
+        fn write_olat(&mut self, value: u8) -> Result<(), X::Error> {
+            self.with(|dev| dev.write_olat(value))
+        }
+
+        fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), X::Error> {
+            self.with(|dev| dev.modify_port(mask, value))
+        }
+
+        fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_port_direction(inputs))
+        }
+
+        fn modify_port_direction(
+            &mut self,
+            mask: impl Into<PinSet>,
+            inputs: u8,
+        ) -> Result<(), X::Error> {
+            self.with(|dev| dev.modify_port_direction(mask, inputs))
+        }
+
+        fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_port_pullups(mask))
+        }
+
+        fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_pin_pullup(pin, enable))
+        }
+
+        fn read_interrupt_flags(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_interrupt_flags())
+        }
+
+        fn read_interrupt_capture(&mut self) -> Result<u8, X::Error> {
+            self.with(|dev| dev.read_interrupt_capture())
+        }
+
+        fn read_pin(&mut self, pin: Pin) -> Result<bool, X::Error> {
+            self.with(|dev| dev.read_pin(pin))
+        }
+
+        fn write_pin(&mut self, pin: Pin, high: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.write_pin(pin, high))
+        }
+
+        fn set_pin_direction(&mut self, pin: Pin, input: bool) -> Result<(), X::Error> {
+            self.with(|dev| dev.set_pin_direction(pin, input))
+        }
+    }
+
+    /// Cloneable pin of an [`ArcExpander`].
+    pub struct ArcPin<X> {
+        io: ArcExpander<X>,
+        pin: Pin,
+    }
+
+    impl<X> Clone for ArcPin<X> {
+        fn clone(&self) -> Self {
+            Self {
+                io: self.io.clone(),
+                pin: self.pin,
+            }
+        }
+    }
+
+    impl<X> ArcPin<X> {
+        pub fn pin(&self) -> Pin {
+            self.pin
+        }
+    }
+
+    impl<X> embedded_hal::digital::ErrorType for ArcPin<X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        type Error = X::Error;
+    }
+
+    impl<X> embedded_hal::digital::InputPin for ArcPin<X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            self.io.read_pin(self.pin)
+        }
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.is_high()?)
+        }
+    }
+
+    impl<X> embedded_hal::digital::OutputPin for ArcPin<X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.io.write_pin(self.pin, true)
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.io.write_pin(self.pin, false)
+        }
+    }
+
+    impl<X> embedded_hal::digital::StatefulOutputPin for ArcPin<X>
+    where
+        X: IoExpander,
+        X::Error: embedded_hal::digital::Error,
+    {
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.io.output_latch() & self.pin.mask() != 0)
+        }
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.is_set_high()?)
+        }
+        /// Inverts the latch bit with the lock held.
+        fn toggle(&mut self) -> Result<(), Self::Error> {
+            let pin = self.pin;
+            self.io
+                .with(|dev| dev.modify_port(pin, !dev.output_latch()))
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! 74HC595 / 74HC165 shift-register chains on expander pins.
+//!
+//! Three expander pins drive a chain of `N` registers. [`Hc595Chain`]
+//! keeps the outputs as an `N`-byte virtual port; [`Hc165Chain`] reads `N`
+//! bytes of inputs. Data and clock change together in one `OLAT` write, so
+//! shifting costs two SPI transactions per bit instead of three.
+//!
+//! Bit `i` of the virtual port is bit `i % 8` of register `i / 8`, where
+//! register 0 is the one wired to the expander.
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Pin};
+//! use mcp23s08_io::shiftreg::Hc595Chain;
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! let mut leds: Hc595Chain<'_, _, 2> =
+//!     Hc595Chain::new(&mut dev, Pin::P0, Pin::P1, Pin::P2).unwrap();
+//! leds.write([0x01, 0x80]).unwrap();
+//! leds.set_bit(3, true).unwrap();
+//! assert_eq!(leds.state(), [0x09, 0x80]);
+//! ```
+
+use crate::expander::IoExpander;
+use crate::mcp23s08::Pin;
+
+/// Chain of `N` 74HC595 output registers.
+pub struct Hc595Chain<'a, X, const N: usize = 1> {
+    dev: &'a mut X,
+    data: Pin,
+    clock: Pin,
+    latch: Pin,
+    state: [u8; N],
+}
+
+impl<'a, X: IoExpander, const N: usize> Hc595Chain<'a, X, N> {
+    /// Makes the three pins outputs and clears every register.
+    pub fn new(dev: &'a mut X, data: Pin, clock: Pin, latch: Pin) -> Result<Self, X::Error> {
+        dev.modify_port(data | clock | latch, 0x00)?;
+        dev.modify_port_direction(data | clock | latch, 0x00)?;
+        let mut chain = Self {
+            dev,
+            data,
+            clock,
+            latch,
+            state: [0; N],
+        };
+        chain.flush()?;
+        Ok(chain)
+    }
+
+    /// Last value shifted out.
+    pub fn state(&self) -> [u8; N] {
+        self.state
+    }
+
+    /// Shifts `bytes` out and latches them.
+    pub fn write(&mut self, bytes: [u8; N]) -> Result<(), X::Error> {
+        self.state = bytes;
+        self.flush()
+    }
+
+    /// Changes one bit and shifts the whole chain out.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= N * 8`.
+    pub fn set_bit(&mut self, index: usize, high: bool) -> Result<(), X::Error> {
+        let mask = 1 << (index % 8);
+        if high {
+            self.state[index / 8] |= mask;
+        } else {
+            self.state[index / 8] &= !mask;
+        }
+        self.flush()
+    }
+
+    /// # Panics
+    ///
+    /// If `index >= N * 8`.
+    pub fn bit(&self, index: usize) -> bool {
+        self.state[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Shifts the cached state out again, e.g. after a supply glitch.
+    pub fn flush(&mut self) -> Result<(), X::Error> {
+        let pins = self.data | self.clock;
+        // The far end of the chain goes first, MSB first.
+        for byte in self.state.iter().rev() {
+            for bit in (0..8).rev() {
+                let data = if byte & (1 << bit) != 0 {
+                    self.data.mask()
+                } else {
+                    0
+                };
+                self.dev.modify_port(pins, data)?;
+                self.dev.modify_port(self.clock, self.clock.mask())?;
+            }
+        }
+        self.dev
+            .modify_port(self.clock | self.latch, self.latch.mask())?;
+        self.dev.modify_port(self.latch, 0x00)
+    }
+
+    /// Handle for output bit `index` of the chain.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= N * 8`.
+    pub fn pin(&mut self, index: usize) -> Hc595Pin<'_, 'a, X, N> {
+        assert!(index < N * 8, "bit {index} outside a {N}-register chain");
+        Hc595Pin { chain: self, index }
+    }
+}
+
+/// One output of an [`Hc595Chain`].
+pub struct Hc595Pin<'c, 'a, X, const N: usize> {
+    chain: &'c mut Hc595Chain<'a, X, N>,
+    index: usize,
+}
+
+impl<X, const N: usize> embedded_hal::digital::ErrorType for Hc595Pin<'_, '_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    type Error = X::Error;
+}
+
+impl<X, const N: usize> embedded_hal::digital::OutputPin for Hc595Pin<'_, '_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.chain.set_bit(self.index, false)
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.chain.set_bit(self.index, true)
+    }
+}
+
+impl<X, const N: usize> embedded_hal::digital::StatefulOutputPin for Hc595Pin<'_, '_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.chain.bit(self.index))
+    }
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.chain.bit(self.index))
+    }
+}
+
+/// Chain of `N` 74HC165 input registers.
+pub struct Hc165Chain<'a, X, const N: usize = 1> {
+    dev: &'a mut X,
+    data: Pin,
+    clock: Pin,
+    load: Pin,
+}
+
+impl<'a, X: IoExpander, const N: usize> Hc165Chain<'a, X, N> {
+    /// `data` (QH of the register wired to the expander) becomes an input,
+    /// `clock` and `load` (SH/LD, active low) outputs idling low and high.
+    pub fn new(dev: &'a mut X, data: Pin, clock: Pin, load: Pin) -> Result<Self, X::Error> {
+        dev.modify_port(clock | load, load.mask())?;
+        dev.modify_port_direction(data | clock | load, data.mask())?;
+        Ok(Self {
+            dev,
+            data,
+            clock,
+            load,
+        })
+    }
+
+    /// Samples all inputs and shifts them in.
+    pub fn read(&mut self) -> Result<[u8; N], X::Error> {
+        self.dev.modify_port(self.load, 0x00)?;
+        self.dev.modify_port(self.load, self.load.mask())?;
+        let mut bytes = [0; N];
+        // Register 0 is read first, its H input first.
+        for byte in bytes.iter_mut() {
+            for bit in (0..8).rev() {
+                if self.dev.read_pin(self.data)? {
+                    *byte |= 1 << bit;
+                }
+                self.dev.modify_port(self.clock, self.clock.mask())?;
+                self.dev.modify_port(self.clock, 0x00)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Handle for input bit `index` of the chain. Every read samples the
+    /// whole chain.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= N * 8`.
+    pub fn pin(&mut self, index: usize) -> Hc165Pin<'_, 'a, X, N> {
+        assert!(index < N * 8, "bit {index} outside a {N}-register chain");
+        Hc165Pin { chain: self, index }
+    }
+}
+
+/// One input of an [`Hc165Chain`].
+pub struct Hc165Pin<'c, 'a, X, const N: usize> {
+    chain: &'c mut Hc165Chain<'a, X, N>,
+    index: usize,
+}
+
+impl<X, const N: usize> embedded_hal::digital::ErrorType for Hc165Pin<'_, '_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    type Error = X::Error;
+}
+
+impl<X, const N: usize> embedded_hal::digital::InputPin for Hc165Pin<'_, '_, X, N>
+where
+    X: IoExpander,
+    X::Error: embedded_hal::digital::Error,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let bytes = self.chain.read()?;
+        Ok(bytes[self.index / 8] & (1 << (self.index % 8)) != 0)
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
@@ -0,0 +1,638 @@
+//! Register-level MCP23S08, MCP23S17 and MCP23S18 simulators.
+//!
+//! [`Mcp23s08Sim`] models up to four chips sharing one chip-select (HAEN
+//! addressing, sequential register access, input polarity, interrupt
+//! capture) and can inject bus faults. It implements both the blocking and
+//! the async `SpiDevice`, so the drivers can run on the host without
+//! hardware:
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, Reg};
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! dev.set_port_direction(0xF0).unwrap();
+//! dev.write_olat(0x05).unwrap();
+//! assert_eq!(sim.reg(0, Reg::OLAT), 0x05);
+//! ```
+//!
+//! [`Mcp23s17Sim`] does the same for up to eight MCP23S17 chips in either
+//! register layout, or for one MCP23S18.
+
+use core::cell::RefCell;
+
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation};
+
+use crate::mcp23s08::{HAEN, Reg};
+use crate::mcp23s17::{Bank, INTCC, MIRROR, Port};
+
+const REG_COUNT: usize = 11;
+const SEQOP: u8 = 1 << 5;
+
+/// Power-on values of the register file.
+const POR: [u8; REG_COUNT] = [0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+#[derive(Clone, Copy, Debug)]
+struct Chip {
+    present: bool,
+    regs: [u8; REG_COUNT],
+    /// Levels applied to the pins from outside.
+    inputs: u8,
+    /// MCP23S18 port: open-drain outputs and `IOCON.INTCC`.
+    mcp23x18: bool,
+}
+
+impl Chip {
+    const ABSENT: Chip = Chip {
+        present: false,
+        regs: POR,
+        inputs: 0x00,
+        mcp23x18: false,
+    };
+
+    fn reg(&self, reg: Reg) -> u8 {
+        self.regs[reg as usize]
+    }
+
+    /// Value seen on `GPIO`: input pins (after `IPOL`) merged with the latch.
+    /// An open-drain output only pulls low; released, it reads the line.
+    fn gpio(&self) -> u8 {
+        let iodir = self.reg(Reg::IODIR);
+        let inputs = (self.inputs ^ self.reg(Reg::IPOL)) & iodir;
+        let mut outputs = self.reg(Reg::OLAT);
+        if self.mcp23x18 {
+            outputs &= self.inputs;
+        }
+        inputs | (outputs & !iodir)
+    }
+
+    fn responds_to(&self, hw_addr: u8, opcode_addr: u8) -> bool {
+        if !self.present {
+            return false;
+        }
+        if self.reg(Reg::IOCON) & HAEN != 0 {
+            opcode_addr == hw_addr
+        } else {
+            opcode_addr == 0
+        }
+    }
+
+    fn read(&mut self, reg: u8) -> u8 {
+        match reg {
+            r if r == Reg::GPIO as u8 => {
+                let value = self.gpio();
+                if !self.intcc() {
+                    self.clear_interrupt();
+                }
+                value
+            }
+            r if r == Reg::INTCAP as u8 => {
+                let value = self.reg(Reg::INTCAP);
+                if !self.mcp23x18 || self.intcc() {
+                    self.clear_interrupt();
+                }
+                value
+            }
+            r => self.regs[r as usize],
+        }
+    }
+
+    fn write(&mut self, reg: u8, value: u8) {
+        match reg {
+            r if r == Reg::GPIO as u8 => self.regs[Reg::OLAT as usize] = value,
+            r if r == Reg::INTF as u8 || r == Reg::INTCAP as u8 => {}
+            r if r == Reg::IOCON as u8 => self.regs[r as usize] = value & 0x3E,
+            r => self.regs[r as usize] = value,
+        }
+        self.update_interrupts(self.gpio());
+    }
+
+    /// `IOCON.INTCC`: only an `INTCAP` read clears the interrupt.
+    fn intcc(&self) -> bool {
+        self.mcp23x18 && self.reg(Reg::IOCON) & INTCC != 0
+    }
+
+    fn set_inputs(&mut self, levels: u8) {
+        let before = self.gpio();
+        self.inputs = levels;
+        self.update_interrupts(before);
+    }
+
+    fn clear_interrupt(&mut self) {
+        self.regs[Reg::INTF as usize] = 0;
+        // Compare-to-DEFVAL sources re-assert while the mismatch persists.
+        let gpio = self.gpio();
+        self.update_interrupts(gpio);
+    }
+
+    fn update_interrupts(&mut self, before: u8) {
+        let gpio = self.gpio();
+        let enabled = self.reg(Reg::GPINTEN) & self.reg(Reg::IODIR);
+        let intcon = self.reg(Reg::INTCON);
+        let changed = (gpio ^ before) & !intcon;
+        let mismatch = (gpio ^ self.reg(Reg::DEFVAL)) & intcon;
+        let fired = (changed | mismatch) & enabled;
+        if fired != 0 {
+            if self.reg(Reg::INTF) == 0 {
+                self.regs[Reg::INTCAP as usize] = gpio;
+            }
+            self.regs[Reg::INTF as usize] |= fired;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State<C> {
+    chips: C,
+    fail_next: u32,
+    fail_kind: ErrorKind,
+    drop_writes: u32,
+    transactions: u32,
+}
+
+impl<C> State<C> {
+    fn new(chips: C) -> Self {
+        Self {
+            chips,
+            fail_next: 0,
+            fail_kind: ErrorKind::Other,
+            drop_writes: 0,
+            transactions: 0,
+        }
+    }
+
+    /// Shifts every byte of `operations` through `clock`.
+    fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+        clock: fn(&mut Frame, &mut Self, u8) -> u8,
+    ) -> Result<(), ErrorKind> {
+        self.transactions += 1;
+        if self.fail_next > 0 {
+            self.fail_next -= 1;
+            return Err(self.fail_kind);
+        }
+
+        let mut frame = Frame::default();
+        for op in operations.iter_mut() {
+            match op {
+                Operation::Write(buf) => {
+                    for &b in buf.iter() {
+                        clock(&mut frame, self, b);
+                    }
+                }
+                Operation::Read(buf) => {
+                    for b in buf.iter_mut() {
+                        *b = clock(&mut frame, self, 0x00);
+                    }
+                }
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let out = clock(&mut frame, self, write.get(i).copied().unwrap_or(0));
+                        if let Some(r) = read.get_mut(i) {
+                            *r = out;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    for b in buf.iter_mut() {
+                        *b = clock(&mut frame, self, *b);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Simulated MCP23S08 chips on one chip-select line.
+///
+/// All methods take `&self`; use [`device`](Self::device) to get an
+/// `SpiDevice` that borrows the simulator, or pass the simulator itself by
+/// value.
+#[derive(Debug)]
+pub struct Mcp23s08Sim {
+    state: RefCell<State<[Chip; 4]>>,
+}
+
+impl Default for Mcp23s08Sim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mcp23s08Sim {
+    /// One chip with its address pins tied to 0.
+    pub fn new() -> Self {
+        Self::with_chips(&[0])
+    }
+
+    /// A chip for every hardware address in `hw_addrs` (0..=3).
+    pub fn with_chips(hw_addrs: &[u8]) -> Self {
+        let mut chips = [Chip::ABSENT; 4];
+        for &addr in hw_addrs {
+            chips[(addr & 0x03) as usize].present = true;
+        }
+        Self {
+            state: RefCell::new(State::new(chips)),
+        }
+    }
+
+    /// `SpiDevice` borrowing this simulator.
+    pub fn device(&self) -> SimDevice<'_> {
+        SimDevice { sim: self }
+    }
+
+    /// Current content of `reg` on the chip at `hw_addr`.
+    pub fn reg(&self, hw_addr: u8, reg: Reg) -> u8 {
+        let state = self.state.borrow();
+        let chip = &state.chips[(hw_addr & 0x03) as usize];
+        match reg {
+            Reg::GPIO => chip.gpio(),
+            _ => chip.reg(reg),
+        }
+    }
+
+    /// Overwrites `reg` behind the driver's back.
+    pub fn set_reg(&self, hw_addr: u8, reg: Reg, value: u8) {
+        self.state.borrow_mut().chips[(hw_addr & 0x03) as usize].regs[reg as usize] = value;
+    }
+
+    /// Drives the external pin levels of the chip at `hw_addr`.
+    pub fn set_inputs(&self, hw_addr: u8, levels: u8) {
+        self.state.borrow_mut().chips[(hw_addr & 0x03) as usize].set_inputs(levels);
+    }
+
+    /// Puts every chip back into its power-on state, as after a brown-out.
+    pub fn power_on_reset(&self) {
+        for chip in self.state.borrow_mut().chips.iter_mut() {
+            chip.regs = POR;
+        }
+    }
+
+    /// Fails the next `count` transactions with `ErrorKind::Other`.
+    pub fn fail_next(&self, count: u32) {
+        self.fail_next_with(count, ErrorKind::Other);
+    }
+
+    /// Fails the next `count` transactions with `kind`.
+    pub fn fail_next_with(&self, count: u32, kind: ErrorKind) {
+        let mut state = self.state.borrow_mut();
+        state.fail_next = count;
+        state.fail_kind = kind;
+    }
+
+    /// Silently ignores the data of the next `count` write transactions.
+    pub fn drop_next_writes(&self, count: u32) {
+        self.state.borrow_mut().drop_writes = count;
+    }
+
+    /// Number of transactions seen so far, including failed ones.
+    pub fn transactions(&self) -> u32 {
+        self.state.borrow().transactions
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.state
+            .borrow_mut()
+            .transaction(operations, Frame::clock)
+    }
+}
+
+/// Simulated MCP23S17 chips on one chip-select line.
+///
+/// Models up to eight chips with both register layouts (`IOCON.BANK`),
+/// sequential access, input polarity and per-port interrupt capture.
+/// The state of the `INTA`/`INTB` outputs, including `IOCON.MIRROR`, is
+/// available through [`interrupt_pending`](Self::interrupt_pending).
+/// [`mcp23s18`](Self::mcp23s18) models an MCP23S18 instead.
+#[derive(Debug)]
+pub struct Mcp23s17Sim {
+    state: RefCell<State<[[Chip; 2]; 8]>>,
+}
+
+impl Default for Mcp23s17Sim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mcp23s17Sim {
+    /// One chip with its address pins tied to 0.
+    pub fn new() -> Self {
+        Self::with_chips(&[0])
+    }
+
+    /// A chip for every hardware address in `hw_addrs` (0..=7).
+    pub fn with_chips(hw_addrs: &[u8]) -> Self {
+        let mut chips = [[Chip::ABSENT; 2]; 8];
+        for &addr in hw_addrs {
+            for port in chips[(addr & 0x07) as usize].iter_mut() {
+                port.present = true;
+            }
+        }
+        Self {
+            state: RefCell::new(State::new(chips)),
+        }
+    }
+
+    /// One MCP23S18 instead: open-drain outputs on lines pulled up from
+    /// outside, `IOCON.INTCC`, and no address pins (only opcode `0x40`/`0x41`
+    /// is answered).
+    pub fn mcp23s18() -> Self {
+        let sim = Self::new();
+        for port in sim.state.borrow_mut().chips[0].iter_mut() {
+            port.mcp23x18 = true;
+            port.inputs = 0xFF;
+        }
+        sim
+    }
+
+    /// `SpiDevice` borrowing this simulator.
+    pub fn device(&self) -> SimDevice<'_, Mcp23s17Sim> {
+        SimDevice { sim: self }
+    }
+
+    /// Current content of `reg` of `port` on the chip at `hw_addr`.
+    pub fn reg(&self, hw_addr: u8, port: Port, reg: Reg) -> u8 {
+        let state = self.state.borrow();
+        let chip = &state.chips[(hw_addr & 0x07) as usize][port as usize];
+        match reg {
+            Reg::GPIO => chip.gpio(),
+            _ => chip.reg(reg),
+        }
+    }
+
+    /// Overwrites `reg` of `port` behind the driver's back.
+    pub fn set_reg(&self, hw_addr: u8, port: Port, reg: Reg, value: u8) {
+        self.state.borrow_mut().chips[(hw_addr & 0x07) as usize][port as usize].regs
+            [reg as usize] = value;
+    }
+
+    /// Drives the external pin levels of the chip at `hw_addr`
+    /// (bit 0..=7 = `GPA0..7`, bit 8..=15 = `GPB0..7`).
+    pub fn set_inputs(&self, hw_addr: u8, levels: u16) {
+        let mut state = self.state.borrow_mut();
+        let ports = &mut state.chips[(hw_addr & 0x07) as usize];
+        for (port, level) in ports.iter_mut().zip(levels.to_le_bytes()) {
+            port.set_inputs(level);
+        }
+    }
+
+    /// Whether the `INTA` (`Port::A`) or `INTB` output is asserted. With
+    /// `IOCON.MIRROR` set both follow the interrupts of either port.
+    pub fn interrupt_pending(&self, hw_addr: u8, port: Port) -> bool {
+        let state = self.state.borrow();
+        let ports = &state.chips[(hw_addr & 0x07) as usize];
+        let [a, b] = ports.map(|p| p.reg(Reg::INTF) != 0);
+        if ports[0].reg(Reg::IOCON) & MIRROR != 0 {
+            return a || b;
+        }
+        match port {
+            Port::A => a,
+            Port::B => b,
+        }
+    }
+
+    /// Puts every chip back into its power-on state, as after a brown-out.
+    pub fn power_on_reset(&self) {
+        for ports in self.state.borrow_mut().chips.iter_mut() {
+            for port in ports.iter_mut() {
+                port.regs = POR;
+            }
+        }
+    }
+
+    /// Fails the next `count` transactions with `ErrorKind::Other`.
+    pub fn fail_next(&self, count: u32) {
+        self.fail_next_with(count, ErrorKind::Other);
+    }
+
+    /// Fails the next `count` transactions with `kind`.
+    pub fn fail_next_with(&self, count: u32, kind: ErrorKind) {
+        let mut state = self.state.borrow_mut();
+        state.fail_next = count;
+        state.fail_kind = kind;
+    }
+
+    /// Silently ignores the data of the next `count` write transactions.
+    pub fn drop_next_writes(&self, count: u32) {
+        self.state.borrow_mut().drop_writes = count;
+    }
+
+    /// Number of transactions seen so far, including failed ones.
+    pub fn transactions(&self) -> u32 {
+        self.state.borrow().transactions
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.state
+            .borrow_mut()
+            .transaction(operations, Frame::clock17)
+    }
+}
+
+/// Decoder state of one chip-select frame.
+#[derive(Default)]
+struct Frame {
+    index: usize,
+    opcode: u8,
+    addr: u8,
+    drop: bool,
+}
+
+impl Frame {
+    /// Takes the opcode and address bytes. Returns the MISO byte while the
+    /// header is shifted in and `None` once register data follows.
+    fn header(&mut self, drop_writes: &mut u32, mosi: u8) -> Option<u8> {
+        let index = self.index;
+        self.index += 1;
+        match index {
+            0 => {
+                self.opcode = mosi;
+                if mosi & 1 == 0 && *drop_writes > 0 {
+                    *drop_writes -= 1;
+                    self.drop = true;
+                }
+                Some(0xFF)
+            }
+            1 => {
+                self.addr = mosi;
+                Some(0xFF)
+            }
+            _ => None,
+        }
+    }
+
+    /// Shifts one byte into MCP23S08 chips and returns the byte on MISO.
+    fn clock(&mut self, state: &mut State<[Chip; 4]>, mosi: u8) -> u8 {
+        if let Some(miso) = self.header(&mut state.drop_writes, mosi) {
+            return miso;
+        }
+        if self.opcode & 0xF8 != 0x40 || self.addr as usize >= REG_COUNT {
+            return 0xFF;
+        }
+
+        let opcode_addr = (self.opcode >> 1) & 0x03;
+        let read = self.opcode & 1 != 0;
+        let mut miso = 0xFF;
+        let mut sequential = true;
+        for (hw_addr, chip) in state.chips.iter_mut().enumerate() {
+            if !chip.responds_to(hw_addr as u8, opcode_addr) {
+                continue;
+            }
+            sequential = chip.reg(Reg::IOCON) & SEQOP == 0;
+            if read {
+                // Several drivers on MISO: a low wins.
+                miso &= chip.read(self.addr);
+            } else if !self.drop {
+                chip.write(self.addr, mosi);
+            }
+        }
+        if sequential {
+            self.addr = (self.addr + 1) % REG_COUNT as u8;
+        }
+        miso
+    }
+
+    /// Shifts one byte into MCP23S17 chips and returns the byte on MISO.
+    fn clock17(&mut self, state: &mut State<[[Chip; 2]; 8]>, mosi: u8) -> u8 {
+        if let Some(miso) = self.header(&mut state.drop_writes, mosi) {
+            return miso;
+        }
+        if self.opcode & 0xF0 != 0x40 {
+            return 0xFF;
+        }
+
+        let opcode_addr = (self.opcode >> 1) & 0x07;
+        let read = self.opcode & 1 != 0;
+        let mut miso = 0xFF;
+        let mut next = None;
+        for (hw_addr, ports) in state.chips.iter_mut().enumerate() {
+            if !ports[0].responds_to(hw_addr as u8, opcode_addr) {
+                continue;
+            }
+            let iocon = ports[0].reg(Reg::IOCON);
+            let bank = Bank::from_iocon(iocon);
+            if iocon & SEQOP == 0 {
+                next = Some(next_addr(bank, self.addr));
+            }
+            let Some((reg, port)) = bank.decode(self.addr) else {
+                continue;
+            };
+            if read {
+                miso &= ports[port as usize].read(reg);
+            } else if self.drop {
+                continue;
+            } else if reg == Reg::IOCON as u8 {
+                // One register seen from both ports. Unimplemented bits: 0
+                // (INTCC) on the MCP23S17, 3 (HAEN) and 4 on the MCP23S18.
+                let mask = if ports[0].mcp23x18 { 0xE7 } else { 0xFE };
+                for port in ports.iter_mut() {
+                    port.regs[Reg::IOCON as usize] = mosi & mask;
+                }
+            } else {
+                ports[port as usize].write(reg, mosi);
+            }
+        }
+        if let Some(addr) = next {
+            self.addr = addr;
+        }
+        miso
+    }
+}
+
+/// MCP23S17 address pointer after a sequential access: it runs through the
+/// whole map in the paired layout and wraps within the port's block in the
+/// separate one.
+fn next_addr(bank: Bank, addr: u8) -> u8 {
+    let last = Reg::OLAT as u8;
+    match bank {
+        Bank::Paired => (addr + 1) % (2 * (last + 1)),
+        Bank::Separate if addr & 0x0F >= last => addr & 0x10,
+        Bank::Separate => addr + 1,
+    }
+}
+
+/// `SpiDevice` handle borrowing a simulator.
+#[derive(Debug)]
+pub struct SimDevice<'a, S = Mcp23s08Sim> {
+    sim: &'a S,
+}
+
+impl<S> Clone for SimDevice<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for SimDevice<'_, S> {}
+
+impl<S> SimDevice<'_, S> {
+    /// The simulator behind this handle.
+    pub fn sim(&self) -> &S {
+        self.sim
+    }
+}
+
+impl<S> ErrorType for SimDevice<'_, S> {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal::spi::SpiDevice for SimDevice<'_> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.sim.transaction(operations)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for SimDevice<'_> {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.sim.transaction(operations)
+    }
+}
+
+impl embedded_hal::spi::SpiDevice for SimDevice<'_, Mcp23s17Sim> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.sim.transaction(operations)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for SimDevice<'_, Mcp23s17Sim> {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.sim.transaction(operations)
+    }
+}
+
+impl ErrorType for Mcp23s08Sim {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal::spi::SpiDevice for Mcp23s08Sim {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        Mcp23s08Sim::transaction(self, operations)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for Mcp23s08Sim {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        Mcp23s08Sim::transaction(self, operations)
+    }
+}
+
+impl ErrorType for Mcp23s17Sim {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal::spi::SpiDevice for Mcp23s17Sim {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        Mcp23s17Sim::transaction(self, operations)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for Mcp23s17Sim {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        Mcp23s17Sim::transaction(self, operations)
+    }
+}
@@ -0,0 +1,77 @@
+//! Communication statistics.
+//!
+//! Counters are only kept when the `stats` cargo feature is enabled; without
+//! it the drivers carry no extra state and the recording calls compile to
+//! nothing.
+
+use crate::transport::BusErrorKind;
+
+/// Failed transactions by [`BusErrorKind`]. The first four are SPI kinds,
+/// the next three I²C ones; an overrun is counted for either bus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ErrorCounts {
+    pub overrun: u32,
+    pub mode_fault: u32,
+    pub frame_format: u32,
+    pub chip_select_fault: u32,
+    pub no_acknowledge: u32,
+    pub arbitration_loss: u32,
+    pub bus: u32,
+    pub other: u32,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u32 {
+        self.overrun
+            .wrapping_add(self.mode_fault)
+            .wrapping_add(self.frame_format)
+            .wrapping_add(self.chip_select_fault)
+            .wrapping_add(self.no_acknowledge)
+            .wrapping_add(self.arbitration_loss)
+            .wrapping_add(self.bus)
+            .wrapping_add(self.other)
+    }
+
+    pub(crate) fn record(&mut self, kind: BusErrorKind) {
+        let counter = match kind {
+            BusErrorKind::Overrun => &mut self.overrun,
+            BusErrorKind::ModeFault => &mut self.mode_fault,
+            BusErrorKind::FrameFormat => &mut self.frame_format,
+            BusErrorKind::ChipSelectFault => &mut self.chip_select_fault,
+            BusErrorKind::NoAcknowledge(_) => &mut self.no_acknowledge,
+            BusErrorKind::ArbitrationLoss => &mut self.arbitration_loss,
+            BusErrorKind::Bus => &mut self.bus,
+            BusErrorKind::Other => &mut self.other,
+        };
+        *counter = counter.wrapping_add(1);
+    }
+}
+
+/// Snapshot of a driver's counters. All counters wrap on overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Bus transactions started, including failed and retried ones.
+    pub transactions: u32,
+    pub bytes_sent: u32,
+    pub bytes_received: u32,
+    pub errors: ErrorCounts,
+    /// Extra attempts made by the retry policy.
+    pub retries: u32,
+    /// Read-backs that did not match the written value.
+    pub verify_failures: u32,
+    /// Chip resets seen by the health check.
+    pub resets: u32,
+    /// Interrupts acknowledged through `read_interrupt_capture` or
+    /// `clear_interrupts`.
+    pub interrupts_serviced: u32,
+}
+
+impl Stats {
+    pub(crate) fn record_transaction(&mut self, sent: usize, received: usize) {
+        self.transactions = self.transactions.wrapping_add(1);
+        self.bytes_sent = self.bytes_sent.wrapping_add(sent as u32);
+        self.bytes_received = self.bytes_received.wrapping_add(received as u32);
+    }
+}
@@ -0,0 +1,267 @@
+//! Stepper motors on four expander pins.
+//!
+//! A [`Stepper`] drives the four coil pins of a unipolar (ULN2003) or
+//! bipolar (dual H-bridge) motor. Every step is a single `modify_port`
+//! write, so coils switch at the same instant and the other four pins keep
+//! their level; one MCP23S08 can run two motors. Moves accelerate from the
+//! start rate to the top rate and brake symmetrically before the target:
+//!
+//! ```
+//! use mcp23s08_io::mcp23s08::{Mcp23s08, NoDelay, Pin};
+//! use mcp23s08_io::sim::Mcp23s08Sim;
+//! use mcp23s08_io::stepper::{StepMode, Stepper};
+//!
+//! let sim = Mcp23s08Sim::new();
+//! let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+//! dev.set_port_direction(0x00).unwrap();
+//!
+//! let mut motor = Stepper::new([Pin::P0, Pin::P1, Pin::P2, Pin::P3], StepMode::Half)
+//!     .speed(800)
+//!     .ramp(100, 2_000);
+//! motor.move_steps(&mut dev, &mut NoDelay, 200).unwrap();
+//! assert_eq!(motor.position(), 200);
+//! ```
+
+use embedded_hal::delay::DelayNs;
+
+use crate::expander::{AsyncIoExpander, IoExpander};
+use crate::mcp23s08::{Pin, PinSet};
+
+/// Coil sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StepMode {
+    /// One coil at a time: least current, least torque.
+    Wave,
+    /// Two coils at a time: full torque.
+    Full,
+    /// Alternating one and two coils: twice the resolution.
+    Half,
+}
+
+impl StepMode {
+    /// Coil bits (coil 0 = bit 0) of each phase.
+    fn table(self) -> &'static [u8] {
+        match self {
+            StepMode::Wave => &[0b0001, 0b0010, 0b0100, 0b1000],
+            StepMode::Full => &[0b0011, 0b0110, 0b1100, 0b1001],
+            StepMode::Half => &[
+                0b0001, 0b0011, 0b0010, 0b0110, 0b0100, 0b1100, 0b1000, 0b1001,
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// One stepper motor on four pins.
+#[derive(Clone, Copy, Debug)]
+pub struct Stepper {
+    coils: [Pin; 4],
+    mode: StepMode,
+    /// Current phase, counted in half steps.
+    phase: u8,
+    position: i32,
+    max_sps: u32,
+    start_sps: u32,
+    accel: u32,
+    hold: bool,
+}
+
+impl Stepper {
+    /// Motor with coils A, B, C, D (or A+, B+, A-, B- for a bipolar motor)
+    /// on `coils`. Defaults: 200 steps/s, no ramp, coils released on idle.
+    pub fn new(coils: [Pin; 4], mode: StepMode) -> Self {
+        Self {
+            coils,
+            mode,
+            phase: 0,
+            position: 0,
+            max_sps: 200,
+            start_sps: 1,
+            accel: 0,
+            hold: false,
+        }
+    }
+
+    /// Top step rate in steps per second (at least 1).
+    pub fn speed(mut self, steps_per_sec: u32) -> Self {
+        self.max_sps = steps_per_sec.max(1);
+        self.start_sps = self.start_sps.min(self.max_sps);
+        self
+    }
+
+    /// Starts moves at `start_sps` and accelerates by `accel` steps/s².
+    /// `accel == 0` runs at the top rate from the first step.
+    pub fn ramp(mut self, start_sps: u32, accel: u32) -> Self {
+        self.start_sps = start_sps.clamp(1, self.max_sps);
+        self.accel = accel;
+        self
+    }
+
+    /// Keep the coils energised after a move (holding torque) instead of
+    /// releasing them.
+    pub fn hold(mut self, hold: bool) -> Self {
+        self.hold = hold;
+        self
+    }
+
+    pub fn set_mode(&mut self, mode: StepMode) {
+        self.mode = mode;
+    }
+
+    pub fn mode(&self) -> StepMode {
+        self.mode
+    }
+
+    /// Steps taken forward minus steps taken in reverse.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+    }
+
+    /// The four coil pins.
+    pub fn pins(&self) -> PinSet {
+        self.coils.iter().copied().collect()
+    }
+
+    /// Latch bits of the coil pins for the current phase.
+    pub fn pattern(&self) -> u8 {
+        let table = self.mode.table();
+        let bits = table[self.phase as usize * table.len() / 8];
+        self.coils
+            .iter()
+            .enumerate()
+            .filter(|&(coil, _)| bits & (1 << coil) != 0)
+            .fold(0, |acc, (_, pin)| acc | pin.mask())
+    }
+
+    /// Time between step `index` and the next in a move of `total` steps.
+    pub fn interval_us(&self, index: u32, total: u32) -> u32 {
+        if self.accel == 0 {
+            return 1_000_000 / self.max_sps;
+        }
+        let ramp_steps = index.min(total.saturating_sub(index + 1));
+        let start = u64::from(self.start_sps);
+        let sps = (start * start + 2 * u64::from(self.accel) * u64::from(ramp_steps)).isqrt();
+        let sps = sps.clamp(1, u64::from(self.max_sps));
+        (1_000_000 / sps) as u32
+    }
+
+    /// One step in `dir`, without waiting.
+    pub fn step<X>(&mut self, dev: &mut X, dir: Direction) -> Result<(), X::Error>
+    where
+        X: IoExpander,
+    {
+        let next = self.advanced(dir);
+        dev.modify_port(self.pins(), next.pattern())?;
+        *self = next;
+        Ok(())
+    }
+
+    /// Moves `steps` (negative = reverse) with the configured ramp, then
+    /// releases the coils unless holding.
+    pub fn move_steps<X, T>(
+        &mut self,
+        dev: &mut X,
+        delay: &mut T,
+        steps: i32,
+    ) -> Result<(), X::Error>
+    where
+        X: IoExpander,
+        T: DelayNs,
+    {
+        let dir = direction(steps);
+        let total = steps.unsigned_abs();
+        for index in 0..total {
+            self.step(dev, dir)?;
+            delay.delay_us(self.interval_us(index, total));
+        }
+        if !self.hold {
+            self.release(dev)?;
+        }
+        Ok(())
+    }
+
+    /// De-energises all coils. The phase is kept, so the next step continues
+    /// the sequence.
+    pub fn release<X>(&mut self, dev: &mut X) -> Result<(), X::Error>
+    where
+        X: IoExpander,
+    {
+        dev.modify_port(self.pins(), 0x00)
+    }
+
+    /// Async counterpart of [`step`](Self::step).
+    pub async fn step_async<X>(&mut self, dev: &mut X, dir: Direction) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+    {
+        let next = self.advanced(dir);
+        dev.modify_port(self.pins(), next.pattern()).await?;
+        *self = next;
+        Ok(())
+    }
+
+    /// Async counterpart of [`move_steps`](Self::move_steps).
+    pub async fn move_steps_async<X, T>(
+        &mut self,
+        dev: &mut X,
+        delay: &mut T,
+        steps: i32,
+    ) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+        T: embedded_hal_async::delay::DelayNs,
+    {
+        let dir = direction(steps);
+        let total = steps.unsigned_abs();
+        for index in 0..total {
+            self.step_async(dev, dir).await?;
+            delay.delay_us(self.interval_us(index, total)).await;
+        }
+        if !self.hold {
+            self.release_async(dev).await?;
+        }
+        Ok(())
+    }
+
+    /// Async counterpart of [`release`](Self::release).
+    pub async fn release_async<X>(&mut self, dev: &mut X) -> Result<(), X::Error>
+    where
+        X: AsyncIoExpander,
+    {
+        dev.modify_port(self.pins(), 0x00).await
+    }
+
+    /// The motor after one step in `dir`.
+    fn advanced(&self, dir: Direction) -> Stepper {
+        // Full and wave sequences move two half steps at a time.
+        let stride = if self.mode == StepMode::Half { 1 } else { 2 };
+        let (phase, position) = match dir {
+            Direction::Forward => (self.phase + stride, self.position.wrapping_add(1)),
+            Direction::Reverse => (self.phase + 8 - stride, self.position.wrapping_sub(1)),
+        };
+        Stepper {
+            phase: phase % 8,
+            position,
+            ..*self
+        }
+    }
+}
+
+fn direction(steps: i32) -> Direction {
+    if steps < 0 {
+        Direction::Reverse
+    } else {
+        Direction::Forward
+    }
+}
@@ -0,0 +1,315 @@
+//! Register access over SPI or I²C.
+//!
+//! The MCP23S08/MCP23008 and MCP23S17/MCP23017 pairs share their register
+//! maps; only the framing differs. The drivers talk to the chip through
+//! [`Transport`] (or [`AsyncTransport`]): every `SpiDevice` is one, and an
+//! I²C bus becomes one when wrapped in [`I2cBus`].
+//!
+//! | Bus | Frame |
+//! |-----|-------|
+//! | SPI | `0x40 \| hw_addr << 1 \| R/W`, register address, data |
+//! | I²C | device address `0x20 \| hw_addr`, register address, data |
+
+use core::fmt::Debug;
+
+use embedded_hal::i2c::{Error as _, NoAcknowledgeSource};
+use embedded_hal::spi::{Error as _, Operation};
+
+/// Category of a failed SPI or I²C transaction, for the retry policy and
+/// the statistics. Keeps the kinds of both buses apart, so an I²C
+/// arbitration loss is not lumped in with every other fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusErrorKind {
+    /// Data was lost because it was not read in time (SPI and I²C).
+    Overrun,
+    /// SPI: another master drove the bus.
+    ModeFault,
+    /// SPI: the controller rejected the word size or frame format.
+    FrameFormat,
+    /// SPI: the chip-select line misbehaved.
+    ChipSelectFault,
+    /// I²C: the chip did not acknowledge its address or a data byte.
+    NoAcknowledge(NoAcknowledgeSource),
+    /// I²C: another master won the arbitration.
+    ArbitrationLoss,
+    /// I²C: misplaced START or STOP condition.
+    Bus,
+    Other,
+}
+
+impl From<embedded_hal::spi::ErrorKind> for BusErrorKind {
+    fn from(kind: embedded_hal::spi::ErrorKind) -> Self {
+        use embedded_hal::spi::ErrorKind;
+        match kind {
+            ErrorKind::Overrun => BusErrorKind::Overrun,
+            ErrorKind::ModeFault => BusErrorKind::ModeFault,
+            ErrorKind::FrameFormat => BusErrorKind::FrameFormat,
+            ErrorKind::ChipSelectFault => BusErrorKind::ChipSelectFault,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+impl From<embedded_hal::i2c::ErrorKind> for BusErrorKind {
+    fn from(kind: embedded_hal::i2c::ErrorKind) -> Self {
+        use embedded_hal::i2c::ErrorKind;
+        match kind {
+            ErrorKind::Overrun => BusErrorKind::Overrun,
+            ErrorKind::NoAcknowledge(source) => BusErrorKind::NoAcknowledge(source),
+            ErrorKind::ArbitrationLoss => BusErrorKind::ArbitrationLoss,
+            ErrorKind::Bus => BusErrorKind::Bus,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+/// Access to consecutive registers of the chip at `hw_addr`.
+pub trait Transport {
+    type Error: Debug;
+
+    /// Chips on this bus only answer to their hardware address once
+    /// `IOCON.HAEN` is set (SPI parts).
+    const NEEDS_HAEN: bool;
+
+    /// Writes `data` (at most two bytes) starting at register address
+    /// `addr`.
+    fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Fills `buf` from consecutive registers starting at `addr`.
+    fn read_regs(&mut self, hw_addr: u8, addr: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Category of `error` for the retry policy and the statistics.
+    fn error_kind(error: &Self::Error) -> BusErrorKind;
+
+    /// Bytes sent and received by one access to `len` registers.
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize);
+}
+
+/// Async counterpart of [`Transport`].
+#[allow(async_fn_in_trait)]
+pub trait AsyncTransport {
+    type Error: Debug;
+
+    /// See [`Transport::NEEDS_HAEN`].
+    const NEEDS_HAEN: bool;
+
+    async fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    async fn read_regs(&mut self, hw_addr: u8, addr: u8, buf: &mut [u8])
+    -> Result<(), Self::Error>;
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind;
+
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize);
+}
+
+#[inline]
+fn spi_opcode(hw_addr: u8, read: bool) -> u8 {
+    0x40 | ((hw_addr & 0x07) << 1) | read as u8
+}
+
+/// Opcode and register address, then the data, in one transaction.
+fn spi_frame(hw_addr: u8, addr: u8, data: &[u8]) -> ([u8; 4], usize) {
+    let mut frame = [spi_opcode(hw_addr, false), addr, 0, 0];
+    frame[2..2 + data.len()].copy_from_slice(data);
+    (frame, 2 + data.len())
+}
+
+fn spi_frame_sizes(read: bool, len: usize) -> (usize, usize) {
+    if read { (2, len) } else { (2 + len, 0) }
+}
+
+impl<SPI: embedded_hal::spi::SpiDevice> Transport for SPI {
+    type Error = SPI::Error;
+    const NEEDS_HAEN: bool = true;
+
+    fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let (frame, len) = spi_frame(hw_addr, addr, data);
+        self.transaction(&mut [Operation::Write(&frame[..len])])
+    }
+
+    fn read_regs(&mut self, hw_addr: u8, addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let cmd = [spi_opcode(hw_addr, true), addr];
+        self.transaction(&mut [Operation::Write(&cmd), Operation::Read(buf)])
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        error.kind().into()
+    }
+
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize) {
+        spi_frame_sizes(read, len)
+    }
+}
+
+impl<SPI: embedded_hal_async::spi::SpiDevice> AsyncTransport for SPI {
+    type Error = SPI::Error;
+    const NEEDS_HAEN: bool = true;
+
+    async fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let (frame, len) = spi_frame(hw_addr, addr, data);
+        self.transaction(&mut [Operation::Write(&frame[..len])])
+            .await
+    }
+
+    async fn read_regs(
+        &mut self,
+        hw_addr: u8,
+        addr: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let cmd = [spi_opcode(hw_addr, true), addr];
+        self.transaction(&mut [Operation::Write(&cmd), Operation::Read(buf)])
+            .await
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        error.kind().into()
+    }
+
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize) {
+        spi_frame_sizes(read, len)
+    }
+}
+
+/// First 7-bit I²C address of the MCP23008/MCP23017; `A2..A0` add 0..=7.
+pub const I2C_BASE_ADDRESS: u8 = 0x20;
+
+/// I²C bus carrying MCP23008/MCP23017 chips.
+///
+/// The wrapper only selects the I²C framing; the chip address is given to
+/// the driver constructor.
+#[derive(Clone, Copy, Debug)]
+pub struct I2cBus<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> I2cBus<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Self { i2c }
+    }
+
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+}
+
+/// Register address followed by the data, as one I²C write.
+fn i2c_frame(addr: u8, data: &[u8]) -> ([u8; 3], usize) {
+    let mut frame = [addr, 0, 0];
+    frame[1..1 + data.len()].copy_from_slice(data);
+    (frame, 1 + data.len())
+}
+
+/// Address byte and register address, plus a repeated-start address byte
+/// for reads.
+fn i2c_frame_sizes(read: bool, len: usize) -> (usize, usize) {
+    if read { (3, len) } else { (2 + len, 0) }
+}
+
+impl<I2C: embedded_hal::i2c::I2c> Transport for I2cBus<I2C> {
+    type Error = I2C::Error;
+    const NEEDS_HAEN: bool = false;
+
+    fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let (frame, len) = i2c_frame(addr, data);
+        self.i2c.write(I2C_BASE_ADDRESS | hw_addr, &frame[..len])
+    }
+
+    fn read_regs(&mut self, hw_addr: u8, addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c
+            .write_read(I2C_BASE_ADDRESS | hw_addr, &[addr], buf)
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        error.kind().into()
+    }
+
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize) {
+        i2c_frame_sizes(read, len)
+    }
+}
+
+impl<I2C: embedded_hal_async::i2c::I2c> AsyncTransport for I2cBus<I2C> {
+    type Error = I2C::Error;
+    const NEEDS_HAEN: bool = false;
+
+    async fn write_regs(&mut self, hw_addr: u8, addr: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let (frame, len) = i2c_frame(addr, data);
+        self.i2c
+            .write(I2C_BASE_ADDRESS | hw_addr, &frame[..len])
+            .await
+    }
+
+    async fn read_regs(
+        &mut self,
+        hw_addr: u8,
+        addr: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.i2c
+            .write_read(I2C_BASE_ADDRESS | hw_addr, &[addr], buf)
+            .await
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        error.kind().into()
+    }
+
+    fn frame_sizes(read: bool, len: usize) -> (usize, usize) {
+        i2c_frame_sizes(read, len)
+    }
+}
+
+/// Hardware address (`A2..A0`) of the 7-bit I²C `address`, if it lies in
+/// 0x20..=0x27.
+pub(crate) fn i2c_hw_addr(address: u8) -> Option<u8> {
+    (I2C_BASE_ADDRESS..=I2C_BASE_ADDRESS + 7)
+        .contains(&address)
+        .then(|| address - I2C_BASE_ADDRESS)
+}
+
+/// One register access, repeated as a whole by the retry loop.
+pub(crate) enum RegAccess<'a> {
+    Write(&'a [u8]),
+    Read(&'a mut [u8]),
+}
+
+impl RegAccess<'_> {
+    #[inline]
+    pub(crate) fn is_read(&self) -> bool {
+        matches!(self, RegAccess::Read(_))
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            RegAccess::Write(data) => data.len(),
+            RegAccess::Read(buf) => buf.len(),
+        }
+    }
+
+    pub(crate) fn run<B: Transport>(
+        &mut self,
+        bus: &mut B,
+        hw_addr: u8,
+        addr: u8,
+    ) -> Result<(), B::Error> {
+        match self {
+            RegAccess::Write(data) => bus.write_regs(hw_addr, addr, data),
+            RegAccess::Read(buf) => bus.read_regs(hw_addr, addr, buf),
+        }
+    }
+
+    pub(crate) async fn run_async<B: AsyncTransport>(
+        &mut self,
+        bus: &mut B,
+        hw_addr: u8,
+        addr: u8,
+    ) -> Result<(), B::Error> {
+        match self {
+            RegAccess::Write(data) => bus.write_regs(hw_addr, addr, data).await,
+            RegAccess::Read(buf) => bus.read_regs(hw_addr, addr, buf).await,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Read-back verification of register writes.
+//!
+//! With verification on, every covered write is followed by a read of the
+//! same register. A mismatch is retried up to [`VerifyPolicy::retries`]
+//! times and then reported as
+//! [`Error::VerifyFailed`](crate::mcp23s08::Error::VerifyFailed).
+
+use crate::mcp23s08::Reg;
+
+/// Which writes are read back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VerifyMode {
+    #[default]
+    Off,
+    /// Every register write.
+    All,
+    /// Only writes that change outputs: `IODIR`, `OLAT` and `GPIO`.
+    Outputs,
+}
+
+/// Verification settings of a driver.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VerifyPolicy {
+    pub mode: VerifyMode,
+    /// Extra write attempts after a mismatch.
+    pub retries: u8,
+}
+
+impl VerifyPolicy {
+    pub const OFF: VerifyPolicy = VerifyPolicy {
+        mode: VerifyMode::Off,
+        retries: 0,
+    };
+
+    pub const fn new(mode: VerifyMode, retries: u8) -> Self {
+        Self { mode, retries }
+    }
+
+    /// Register to read back after writing `reg`, and the mask of bits that
+    /// read back as written. `iocon_bits` are the `IOCON` bits the chip
+    /// implements. `None` if the write is not verified.
+    pub(crate) fn readback(&self, reg: Reg, iocon_bits: u8) -> Option<(Reg, u8)> {
+        let covered = match self.mode {
+            VerifyMode::Off => false,
+            VerifyMode::All => true,
+            VerifyMode::Outputs => matches!(reg, Reg::IODIR | Reg::OLAT | Reg::GPIO),
+        };
+        if !covered {
+            return None;
+        }
+        match reg {
+            // GPIO reads the pins, the written value lands in OLAT.
+            Reg::GPIO => Some((Reg::OLAT, 0xFF)),
+            // Unimplemented bits read as 0.
+            Reg::IOCON => Some((Reg::IOCON, iocon_bits)),
+            Reg::INTF | Reg::INTCAP => None,
+            _ => Some((reg, 0xFF)),
+        }
+    }
+}
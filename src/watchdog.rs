@@ -0,0 +1,127 @@
+//! Fail-safe output state on communication loss.
+//!
+//! The application calls `feed(now_ms)` while it is alive and something
+//! periodic (a timer task, the main loop) calls `poll_watchdog(now_ms)`. If
+//! feeding stops for longer than the timeout, or the bus fails too many
+//! times in a row, the driver writes the safe pattern to `OLAT` (and
+//! optionally `IODIR`) and latches a [`WatchdogFault`]. While latched, every
+//! output write is refused with
+//! [`Error::WatchdogFault`](crate::mcp23s08::Error::WatchdogFault) until
+//! `clear_watchdog_fault()` is called.
+//!
+//! Time is whatever monotonic millisecond counter the application has; the
+//! driver only compares differences.
+
+use crate::mcp23s08::Reg;
+
+/// Watchdog settings. `P` is the port width: `u8` for the MCP23S08, `u16`
+/// for the MCP23S17 family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WatchdogConfig<P = u8> {
+    /// Longest allowed gap between two `feed()` calls.
+    pub timeout_ms: u32,
+    /// Output latch written when the watchdog trips.
+    pub safe_olat: P,
+    /// Direction written after the latch when the watchdog trips (`1` =
+    /// input). `None` leaves `IODIR` alone.
+    pub safe_iodir: Option<P>,
+    /// Consecutive failed transactions that trip the watchdog. `0` disables
+    /// the check.
+    pub max_bus_errors: u8,
+}
+
+impl<P: Copy> WatchdogConfig<P> {
+    pub const fn new(timeout_ms: u32, safe_olat: P) -> Self {
+        Self {
+            timeout_ms,
+            safe_olat,
+            safe_iodir: None,
+            max_bus_errors: 0,
+        }
+    }
+
+    pub const fn safe_iodir(mut self, iodir: P) -> Self {
+        self.safe_iodir = Some(iodir);
+        self
+    }
+
+    pub const fn max_bus_errors(mut self, count: u8) -> Self {
+        self.max_bus_errors = count;
+        self
+    }
+}
+
+/// Why the watchdog tripped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WatchdogFault {
+    /// `feed()` was not called within the timeout.
+    Timeout,
+    /// Too many consecutive SPI failures.
+    BusErrors,
+}
+
+/// Watchdog state kept by a driver.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Watchdog<P = u8> {
+    pub(crate) config: Option<WatchdogConfig<P>>,
+    pub(crate) last_feed: u64,
+    pub(crate) consecutive_errors: u8,
+    pub(crate) fault: Option<WatchdogFault>,
+}
+
+impl<P> Default for Watchdog<P> {
+    fn default() -> Self {
+        Self::new(None, 0)
+    }
+}
+
+impl<P> Watchdog<P> {
+    /// Fresh state for `config`, counting the timeout from `now_ms`.
+    pub(crate) fn new(config: Option<WatchdogConfig<P>>, now_ms: u64) -> Self {
+        Self {
+            config,
+            last_feed: now_ms,
+            consecutive_errors: 0,
+            fault: None,
+        }
+    }
+
+    /// Unlatches the fault and restarts the timeout at `now_ms`.
+    pub(crate) fn clear(&mut self, now_ms: u64) {
+        self.fault = None;
+        self.consecutive_errors = 0;
+        self.last_feed = now_ms;
+    }
+
+    pub(crate) fn expired(&self, now_ms: u64) -> bool {
+        match &self.config {
+            Some(cfg) => now_ms.saturating_sub(self.last_feed) > u64::from(cfg.timeout_ms),
+            None => false,
+        }
+    }
+
+    pub(crate) fn record_result(&mut self, ok: bool) {
+        self.consecutive_errors = if ok {
+            0
+        } else {
+            self.consecutive_errors.saturating_add(1)
+        };
+    }
+
+    /// The error threshold was reached and nothing is latched yet.
+    pub(crate) fn bus_errors_exceeded(&self) -> bool {
+        match &self.config {
+            Some(cfg) if self.fault.is_none() && cfg.max_bus_errors > 0 => {
+                self.consecutive_errors >= cfg.max_bus_errors
+            }
+            _ => false,
+        }
+    }
+
+    /// A latched fault blocks writes that would drive outputs.
+    pub(crate) fn blocks(&self, reg: Reg) -> bool {
+        self.fault.is_some() && matches!(reg, Reg::IODIR | Reg::OLAT | Reg::GPIO)
+    }
+}
@@ -0,0 +1,109 @@
+use embedded_hal::digital::{OutputPin, StatefulOutputPin};
+use embedded_hal::spi::ErrorKind;
+use mcp23s08_io::bank::{BankError, ExpanderBank};
+use mcp23s08_io::mcp23s08::{Access, Error, Mcp23s08, Pin, Reg};
+use mcp23s08_io::sim::{Mcp23s08Sim, SimDevice};
+
+type Bank<'a> = ExpanderBank<Mcp23s08<SimDevice<'a>>, 4>;
+
+/// Four MCP23S08s sharing one chip-select, all pins outputs.
+fn output_bank(sim: &Mcp23s08Sim) -> Bank<'_> {
+    let mut bank = Bank::try_new(|i| Mcp23s08::new(sim.device(), i as u8)).unwrap();
+    bank.set_port_direction(0).unwrap();
+    bank
+}
+
+#[test]
+fn global_indices_map_to_chip_and_pin() {
+    assert_eq!(Bank::PINS, 32);
+    assert_eq!(Bank::locate(0), (0, Pin::P0));
+    assert_eq!(Bank::locate(17), (2, Pin::P1));
+    assert_eq!(Bank::locate(31), (3, Pin::P7));
+
+    let sim = Mcp23s08Sim::with_chips(&[0, 1, 2, 3]);
+    let mut bank = output_bank(&sim);
+    bank.write_pin(31, true).unwrap();
+    let mut pin = bank.pin(9);
+    pin.set_high().unwrap();
+    assert!(pin.is_set_high().unwrap());
+    assert_eq!(bank.output_latch(), 0x8000_0200);
+    assert_eq!(sim.reg(1, Reg::OLAT), 0x02);
+    assert_eq!(sim.reg(3, Reg::OLAT), 0x80);
+}
+
+#[test]
+#[should_panic(expected = "pin 32 outside a 32-pin bank")]
+fn out_of_range_index_panics() {
+    Bank::locate(32);
+}
+
+#[test]
+fn bulk_writes_touch_only_changed_chips() {
+    let sim = Mcp23s08Sim::with_chips(&[0, 1, 2, 3]);
+    let mut bank = output_bank(&sim);
+
+    let before = sim.transactions();
+    bank.write_olat(0x0000_A500).unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+    assert_eq!(sim.reg(1, Reg::OLAT), 0xA5);
+
+    // Same value again: nothing to do.
+    let before = sim.transactions();
+    bank.write_olat(0x0000_A500).unwrap();
+    assert_eq!(sim.transactions(), before);
+
+    // A mask straddling chips 1 and 2, where chip 1 keeps its latch.
+    bank.modify_port(0x00FF_FF00, 0x0042_A500).unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+    assert_eq!(bank.output_latch(), 0x0042_A500);
+    assert_eq!(sim.reg(2, Reg::OLAT), 0x42);
+
+    let before = sim.transactions();
+    bank.modify_port_direction(0xF000_0000, 0xF000_0000)
+        .unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+    assert_eq!(sim.reg(3, Reg::IODIR), 0xF0);
+}
+
+#[test]
+fn reads_assemble_one_wide_port() {
+    let sim = Mcp23s08Sim::with_chips(&[0, 1, 2, 3]);
+    let mut bank = Bank::try_new(|i| Mcp23s08::new(sim.device(), i as u8)).unwrap();
+    for (chip, levels) in [0x01, 0x23, 0x45, 0x67].into_iter().enumerate() {
+        sim.set_inputs(chip as u8, levels);
+    }
+    assert_eq!(bank.read_port().unwrap(), 0x6745_2301);
+    assert!(bank.read_pin(25).unwrap());
+    assert!(!bank.read_pin(27).unwrap());
+
+    bank.set_port_pullups(0x0100_0000).unwrap();
+    assert_eq!(sim.reg(3, Reg::GPPU), 0x01);
+}
+
+#[test]
+fn errors_name_the_failing_chip() {
+    let sim = Mcp23s08Sim::with_chips(&[0, 1, 2, 3]);
+    let mut bank = output_bank(&sim);
+
+    sim.fail_next(1);
+    assert_eq!(
+        bank.write_pin(20, true),
+        Err(BankError {
+            chip: 2,
+            error: Error::Bus {
+                reg: Reg::GPIO,
+                access: Access::Write,
+                source: ErrorKind::Other,
+            },
+        })
+    );
+
+    let result = ExpanderBank::<_, 2>::try_new(|i| Mcp23s08::new(sim.device(), [0, 4][i]));
+    assert!(matches!(
+        result,
+        Err(BankError {
+            chip: 1,
+            error: Error::BadAddress
+        })
+    ));
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use core::cell::RefCell;
+
+use common::{Peripheral, Wired};
+use embedded_hal::i2c::{ErrorKind as I2cErrorKind, I2c, NoAcknowledgeSource};
+use embedded_hal::spi::{MODE_0, SpiBus};
+use mcp23s08_io::bitbang::{BitbangI2c, BitbangSpi};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, Reg};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+const SCK: Pin = Pin::P0;
+const MOSI: Pin = Pin::P1;
+const MISO: Pin = Pin::P2;
+
+/// Mode 0 SPI slave that answers with `tx` and records what it receives.
+#[derive(Default)]
+struct ShiftRegister {
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+    shift: u8,
+    sampled: usize,
+    out: usize,
+    sck: bool,
+}
+
+impl Peripheral for ShiftRegister {
+    fn update(&mut self, levels: u8) -> u8 {
+        let sck = levels & SCK.mask() != 0;
+        if sck && !self.sck {
+            self.shift = (self.shift << 1) | u8::from(levels & MOSI.mask() != 0);
+            self.sampled += 1;
+            if self.sampled.is_multiple_of(8) {
+                self.rx.push(self.shift);
+            }
+        } else if !sck && self.sck {
+            self.out = self.sampled;
+        }
+        self.sck = sck;
+        let byte = self.tx.get(self.out / 8).copied().unwrap_or(0xFF);
+        let bit = byte & (0x80 >> (self.out % 8)) != 0;
+        if bit { 0xFF } else { !MISO.mask() }
+    }
+}
+
+#[test]
+fn spi_transfers_full_duplex_in_mode_0() {
+    let sim = Mcp23s08Sim::new();
+    let slave = RefCell::new(ShiftRegister {
+        tx: vec![0x5A, 0xC3],
+        // SCK floats high (pull-up) until the master takes it.
+        sck: true,
+        ..Default::default()
+    });
+    let mut dev = Mcp23s08::new(Wired::new(&sim, &slave), 0).unwrap();
+    dev.write_pin(Pin::P7, true).unwrap();
+    let mut spi = BitbangSpi::new(&mut dev, SCK, MOSI, Some(MISO), MODE_0).unwrap();
+
+    let before = sim.transactions();
+    let mut read = [0; 2];
+    spi.transfer(&mut read, &[0x12, 0x34]).unwrap();
+
+    assert_eq!(read, [0x5A, 0xC3]);
+    assert_eq!(slave.borrow().rx, [0x12, 0x34]);
+    // Two latch writes and one read per bit, plus the final clock idle.
+    assert_eq!(sim.transactions() - before, 2 * 8 * 3 + 1);
+    // Clock back at idle, unrelated outputs untouched.
+    assert_eq!(sim.reg(0, Reg::OLAT) & (SCK.mask() | Pin::P7.mask()), 0x80);
+}
+
+const SCL: Pin = Pin::P6;
+const SDA: Pin = Pin::P7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    Idle,
+    Address,
+    Write,
+    Read,
+    /// Acknowledge clock driven by us, then continue with the phase.
+    Ack(bool),
+    /// Acknowledge clock driven by the master after a read byte.
+    MasterAck,
+}
+
+/// 24C02-style EEPROM at address 0x50.
+struct Eeprom {
+    mem: [u8; 256],
+    ptr: u8,
+    phase: Phase,
+    shift: u8,
+    bits: u8,
+    pointer_set: bool,
+    master_acked: bool,
+    pull_low: bool,
+    scl: bool,
+    sda: bool,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            mem: [0xFF; 256],
+            ptr: 0,
+            phase: Phase::Idle,
+            shift: 0,
+            bits: 0,
+            pointer_set: false,
+            master_acked: false,
+            pull_low: false,
+            scl: true,
+            sda: true,
+        }
+    }
+
+    fn present_bit(&mut self) {
+        self.pull_low = self.mem[self.ptr as usize] & (0x80 >> self.bits) == 0;
+    }
+
+    fn falling_edge(&mut self) {
+        match self.phase {
+            Phase::Address | Phase::Write if self.bits == 8 => {
+                let byte = self.shift;
+                (self.shift, self.bits) = (0, 0);
+                if self.phase == Phase::Address {
+                    if byte >> 1 != 0x50 {
+                        self.phase = Phase::Idle;
+                        return;
+                    }
+                    self.pointer_set = false;
+                    self.phase = Phase::Ack(byte & 1 != 0);
+                } else {
+                    if self.pointer_set {
+                        self.mem[self.ptr as usize] = byte;
+                        self.ptr = self.ptr.wrapping_add(1);
+                    } else {
+                        self.ptr = byte;
+                        self.pointer_set = true;
+                    }
+                    self.phase = Phase::Ack(false);
+                }
+                self.pull_low = true;
+            }
+            Phase::Ack(read) => {
+                self.pull_low = false;
+                if read {
+                    self.phase = Phase::Read;
+                    self.present_bit();
+                } else {
+                    self.phase = Phase::Write;
+                }
+            }
+            Phase::Read => {
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.bits = 0;
+                    self.ptr = self.ptr.wrapping_add(1);
+                    self.pull_low = false;
+                    self.phase = Phase::MasterAck;
+                } else {
+                    self.present_bit();
+                }
+            }
+            Phase::MasterAck => {
+                if self.master_acked {
+                    self.phase = Phase::Read;
+                    self.present_bit();
+                } else {
+                    self.phase = Phase::Idle;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Peripheral for Eeprom {
+    fn update(&mut self, levels: u8) -> u8 {
+        let scl = levels & SCL.mask() != 0;
+        let sda = levels & SDA.mask() != 0;
+        if scl && self.scl && sda != self.sda {
+            // SDA moving while SCL is high: start or stop.
+            self.phase = if sda { Phase::Idle } else { Phase::Address };
+            (self.shift, self.bits) = (0, 0);
+            self.pull_low = false;
+        } else if scl && !self.scl {
+            match self.phase {
+                Phase::Address | Phase::Write => {
+                    self.shift = (self.shift << 1) | u8::from(sda);
+                    self.bits += 1;
+                }
+                Phase::MasterAck => self.master_acked = !sda,
+                _ => {}
+            }
+        } else if !scl && self.scl {
+            self.falling_edge();
+        }
+        (self.scl, self.sda) = (scl, sda);
+        if self.pull_low { !SDA.mask() } else { 0xFF }
+    }
+}
+
+#[test]
+fn i2c_writes_and_reads_an_eeprom() {
+    let sim = Mcp23s08Sim::new();
+    let eeprom = RefCell::new(Eeprom::new());
+    let mut dev = Mcp23s08::new(Wired::new(&sim, &eeprom), 0).unwrap();
+    let mut i2c = BitbangI2c::new(&mut dev, SCL, SDA).unwrap();
+
+    i2c.write(0x50, &[0x10, 0xAA, 0xBB]).unwrap();
+    assert_eq!(eeprom.borrow().mem[0x10..0x12], [0xAA, 0xBB]);
+
+    let mut buf = [0; 3];
+    i2c.write_read(0x50, &[0x10], &mut buf).unwrap();
+    assert_eq!(buf, [0xAA, 0xBB, 0xFF]);
+    assert_eq!(eeprom.borrow().phase, Phase::Idle);
+
+    // Lines left released, latch bits low for open-drain operation.
+    assert_eq!(sim.reg(0, Reg::IODIR) & 0xC0, 0xC0);
+    assert_eq!(sim.reg(0, Reg::OLAT) & 0xC0, 0x00);
+}
+
+#[test]
+fn i2c_reports_a_missing_device() {
+    let sim = Mcp23s08Sim::new();
+    let eeprom = RefCell::new(Eeprom::new());
+    let mut dev = Mcp23s08::new(Wired::new(&sim, &eeprom), 0).unwrap();
+    let mut i2c = BitbangI2c::new(&mut dev, SCL, SDA).unwrap();
+
+    let err = i2c.write(0x51, &[0x00]).unwrap_err();
+    assert_eq!(
+        embedded_hal::i2c::Error::kind(&err),
+        I2cErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+    );
+    // The bus is usable again after the stop.
+    i2c.write(0x50, &[0x00, 0x42]).unwrap();
+    assert_eq!(eeprom.borrow().mem[0], 0x42);
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "cli", target_os = "linux"))]
+
+use std::process::{Command, Output};
+
+/// Runs `mcp23s08-cli --bus sim` with `args`.
+fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mcp23s08-cli"))
+        .args(["--bus", "sim"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = cli(args);
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn dump_and_read_show_the_simulated_pins() {
+    let dump = stdout(&["--addr", "2", "--inputs", "0xa0", "dump"]);
+    assert!(dump.contains("IOCON   0x08  HAEN\n"));
+    assert!(dump.contains("GPIO    0xa0  high: P5 P7\n"));
+
+    assert_eq!(
+        stdout(&["--inputs", "0b1010_0000", "read"]),
+        "0xa0 0b10100000\n"
+    );
+    assert_eq!(stdout(&["--inputs", "160", "read", "P5"]), "1\n");
+    assert_eq!(stdout(&["--inputs", "0xa0", "read", "gp6"]), "0\n");
+}
+
+#[test]
+fn output_commands_succeed_silently() {
+    for args in [
+        &["write", "0x05", "--mask", "0x0f"][..],
+        &["set-dir", "0xf0"],
+        &["pullup", "0xf0"],
+    ] {
+        assert_eq!(stdout(args), "");
+    }
+    assert_eq!(stdout(&["pulse", "3", "1"]), "P3 high for 1 ms\n");
+}
+
+#[test]
+fn watch_prints_the_initial_levels() {
+    let out = stdout(&[
+        "--inputs",
+        "0x81",
+        "watch",
+        "--samples",
+        "3",
+        "--interval-ms",
+        "1",
+    ]);
+    assert_eq!(out.lines().count(), 1);
+    assert!(out.trim_end().ends_with("ms  0x81 0b10000001"), "{out}");
+}
+
+#[test]
+fn bad_arguments_and_buses_fail() {
+    let output = cli(&["read", "8"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`8` is not a pin 0..=7"));
+
+    let output = cli(&["--addr", "4", "dump"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mcp23s08-cli"))
+        .args(["--bus", "/dev/spidev-missing", "dump"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .starts_with("error: cannot open /dev/spidev-missing")
+    );
+}
@@ -0,0 +1,73 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Reg};
+use mcp23s08_io::sim::{Mcp23s08Sim, SimDevice};
+
+/// Runs a future to completion on the current thread.
+///
+/// The simulator never returns `Pending`, so a busy poll is enough.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
+    }
+}
+
+/// Driver for chip 0 of `sim` with every pin an output.
+pub fn output_device(sim: &Mcp23s08Sim) -> Mcp23s08<SimDevice<'_>> {
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev
+}
+
+/// Something wired to the expander pins. Gets the line levels after every
+/// expander transaction and returns the levels it drives (`0` = pulls low).
+pub trait Peripheral {
+    fn update(&mut self, levels: u8) -> u8;
+}
+
+/// Expander bus that lets `P` react to every pin change.
+pub struct Wired<'a, P> {
+    sim: &'a Mcp23s08Sim,
+    periph: &'a RefCell<P>,
+    drive: u8,
+}
+
+impl<'a, P: Peripheral> Wired<'a, P> {
+    pub fn new(sim: &'a Mcp23s08Sim, periph: &'a RefCell<P>) -> Self {
+        sim.set_inputs(0, 0xFF);
+        Self {
+            sim,
+            periph,
+            drive: 0xFF,
+        }
+    }
+}
+
+impl<P> ErrorType for Wired<'_, P> {
+    type Error = ErrorKind;
+}
+
+impl<P: Peripheral> SpiDevice for Wired<'_, P> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.sim.device().transaction(operations)?;
+        let iodir = self.sim.reg(0, Reg::IODIR);
+        let olat = self.sim.reg(0, Reg::OLAT);
+        // Outputs show the latch, inputs are pulled up unless driven low.
+        let levels = (olat & !iodir) | (self.drive & iodir);
+        self.drive = self.periph.borrow_mut().update(levels);
+        self.sim.set_inputs(0, self.drive);
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+#![cfg(feature = "console")]
+
+mod common;
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+use common::block_on;
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+use mcp23s08_io::console::Console;
+use mcp23s08_io::mcp23s08::{Mcp23s08, NoDelay, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+/// Serial port that plays back a script and records everything written.
+struct Port {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+    /// `read_ready` calls that report nothing pending even if there is.
+    quiet_polls: u32,
+}
+
+impl Port {
+    fn new(script: &str) -> Self {
+        Port {
+            input: script.bytes().collect(),
+            output: Vec::new(),
+            quiet_polls: 0,
+        }
+    }
+
+    /// Output with the line endings normalised.
+    fn text(&self) -> String {
+        String::from_utf8(self.output.clone())
+            .unwrap()
+            .replace("\r\n", "\n")
+    }
+}
+
+impl ErrorType for Port {
+    type Error = Infallible;
+}
+
+impl Read for Port {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        match self.input.pop_front() {
+            Some(byte) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl ReadReady for Port {
+    fn read_ready(&mut self) -> Result<bool, Infallible> {
+        if self.quiet_polls > 0 {
+            self.quiet_polls -= 1;
+            return Ok(false);
+        }
+        Ok(!self.input.is_empty())
+    }
+}
+
+impl Write for Port {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl embedded_io_async::Read for Port {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        Read::read(self, buf)
+    }
+}
+
+impl embedded_io_async::Write for Port {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        Write::write(self, buf)
+    }
+}
+
+/// Delay that changes the simulated inputs on every call and keeps track
+/// of the time it was asked to wait.
+struct Stimulus<'a> {
+    sim: &'a Mcp23s08Sim,
+    levels: VecDeque<u8>,
+    waited_ns: u64,
+    /// OLAT while the delay was running.
+    olat: Vec<u8>,
+}
+
+impl<'a> Stimulus<'a> {
+    fn new(sim: &'a Mcp23s08Sim, levels: &[u8]) -> Self {
+        Stimulus {
+            sim,
+            levels: levels.iter().copied().collect(),
+            waited_ns: 0,
+            olat: Vec::new(),
+        }
+    }
+}
+
+impl embedded_hal::delay::DelayNs for Stimulus<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.waited_ns += u64::from(ns);
+        self.olat.push(self.sim.reg(0, Reg::OLAT));
+        if let Some(levels) = self.levels.pop_front() {
+            self.sim.set_inputs(0, levels);
+        }
+    }
+}
+
+impl embedded_hal_async::delay::DelayNs for Stimulus<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        embedded_hal::delay::DelayNs::delay_ns(self, ns);
+    }
+}
+
+fn run(sim: &Mcp23s08Sim, port: &mut Port, delay: &mut Stimulus<'_>) {
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    Console::<64>::new().run(port, &mut dev, delay).unwrap();
+}
+
+#[test]
+fn runs_commands_and_reports_errors() {
+    let sim = Mcp23s08Sim::new();
+    sim.set_inputs(0, 0x05);
+    let mut port =
+        Port::new("io dir 0x0f\rio set 4 1\r\nio read\rio read P4\rio set 9 1\rio frob\r");
+    run(&sim, &mut port, &mut Stimulus::new(&sim, &[]));
+
+    assert_eq!(
+        port.text(),
+        "> io dir 0x0f\nok\n\
+         > io set 4 1\nok\n\
+         > io read\n0x15 0b00010101\n\
+         > io read P4\n1\n\
+         > io set 9 1\nerror: no pin 9 (pins are 0..=7)\n\
+         > io frob\nerror: unknown command, try `help`\n\
+         > "
+    );
+    assert_eq!(sim.reg(0, Reg::IODIR), 0x0f);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x10);
+
+    let mut port = Port::new("io read\r");
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    sim.fail_next(1);
+    Console::<64>::new()
+        .run(&mut port, &mut dev, &mut NoDelay)
+        .unwrap();
+    assert_eq!(
+        port.text(),
+        "> io read\nerror: bus error reading GPIO: Other\n> "
+    );
+}
+
+#[test]
+fn edits_lines_as_they_are_typed() {
+    let sim = Mcp23s08Sim::new();
+    // Backspace, an arrow key, Ctrl-C and Ctrl-U.
+    let mut port = Port::new("io reax\x7fd\x1b[A 3\rio dump\x03io set\x15\r");
+    run(&sim, &mut port, &mut Stimulus::new(&sim, &[]));
+
+    assert_eq!(
+        port.text(),
+        "> io reax\x08 \x08d 3\n0\n\
+         > io dump^C\n\
+         > io set\r\x1b[K> \n\
+         > "
+    );
+
+    // A full line rings the bell instead of growing.
+    let mut port = Port::new("io read 12345\r");
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    Console::<9>::new()
+        .run(&mut port, &mut dev, &mut NoDelay)
+        .unwrap();
+    assert_eq!(port.text(), "> io read 1\x07\x07\x07\x07\n0\n> ");
+}
+
+#[test]
+fn prints_help_and_register_dump() {
+    let sim = Mcp23s08Sim::new();
+    let mut port = Port::new("help\rio dump\r");
+    run(&sim, &mut port, &mut Stimulus::new(&sim, &[]));
+
+    let text = port.text();
+    assert!(text.contains("\nio pulse <pin> <ms>  invert an output for <ms>\n"));
+    assert!(text.contains("\nIODIR   0xff  inputs: P0 P1 P2 P3 P4 P5 P6 P7\n"));
+    assert!(text.ends_with("\nOLAT    0x00  high: -\n> "));
+}
+
+#[test]
+fn watch_prints_changes_until_a_key_arrives() {
+    let sim = Mcp23s08Sim::new();
+    sim.set_inputs(0, 0xf0);
+    let mut port = Port::new("io watch 5\rq");
+    port.quiet_polls = 3;
+    let mut delay = Stimulus::new(&sim, &[0xf0, 0xe0, 0x60]);
+    run(&sim, &mut port, &mut delay);
+
+    assert_eq!(
+        port.text(),
+        "> io watch 5\nwatching, press any key to stop\n\
+         0xf0 0b11110000\n\
+         0xe0 0b11100000  P4=0\n\
+         0x60 0b01100000  P7=0\n\
+         > "
+    );
+    assert_eq!(delay.waited_ns, 3 * 5_000_000);
+}
+
+#[test]
+fn pulse_inverts_an_output_for_the_given_time() {
+    let sim = Mcp23s08Sim::new();
+    let mut port = Port::new("io pulse 4 250\r");
+    let mut delay = Stimulus::new(&sim, &[]);
+    run(&sim, &mut port, &mut delay);
+
+    assert_eq!(port.text(), "> io pulse 4 250\nok\n> ");
+    assert_eq!(delay.waited_ns, 250_000_000);
+    assert_eq!(delay.olat, [0x10]);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0xef);
+}
+
+#[test]
+fn async_console_runs_the_same_commands() {
+    let sim = Mcp23s08Sim::new();
+    sim.set_inputs(0, 0x05);
+    let mut port = Port::new("io dir 0x0f\rio set 4 1\rio read\rio pulse 4 20\rio watch\rq");
+    port.quiet_polls = 1;
+    let mut delay = Stimulus::new(&sim, &[0x04]);
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        Console::<64>::new()
+            .run_async(&mut port, &mut dev, &mut delay)
+            .await
+            .unwrap();
+    });
+
+    assert_eq!(
+        port.text(),
+        "> io dir 0x0f\nok\n\
+         > io set 4 1\nok\n\
+         > io read\n0x15 0b00010101\n\
+         > io pulse 4 20\nok\n\
+         > io watch\nwatching, press any key to stop\n\
+         0x14 0b00010100\n\
+         > "
+    );
+    assert_eq!(delay.olat, [0x00, 0x10]);
+    assert_eq!(delay.waited_ns, 20_000_000 + 20_000_000);
+}
@@ -0,0 +1,317 @@
+mod common;
+
+use core::convert::Infallible;
+
+use common::block_on;
+use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::spi::{MODE_0, SpiBus};
+use mcp23s08_io::bitbang::BitbangSpi;
+use mcp23s08_io::expander::{AsyncExpanderPin, AsyncIoExpander, ExpanderPin, IoExpander};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, PinSet, Reg};
+use mcp23s08_io::mcp23s17::{Mcp23s17, Port};
+use mcp23s08_io::mcp23s17async::Mcp23s17async;
+use mcp23s08_io::mcp23s18::Mcp23s18;
+use mcp23s08_io::pwm::SoftPwm;
+use mcp23s08_io::scheduler::Scheduler;
+use mcp23s08_io::shiftreg::Hc595Chain;
+use mcp23s08_io::sim::{Mcp23s08Sim, Mcp23s17Sim};
+
+/// Four-pin expander in memory. Records every latch value written.
+struct FakeExpander {
+    olat: u8,
+    iodir: u8,
+    pullups: u8,
+    inputs: u8,
+    flags: u8,
+    /// Input pin that reads back the latch of an output pin.
+    loopback: Option<(Pin, Pin)>,
+    latches: Vec<u8>,
+}
+
+impl FakeExpander {
+    fn new() -> Self {
+        Self {
+            olat: 0,
+            iodir: 0x0F,
+            pullups: 0,
+            inputs: 0,
+            flags: 0,
+            loopback: None,
+            latches: Vec::new(),
+        }
+    }
+}
+
+impl IoExpander for FakeExpander {
+    type Error = Infallible;
+
+    const WIDTH: u8 = 4;
+
+    fn read_port(&mut self) -> Result<u8, Infallible> {
+        let mut inputs = self.inputs;
+        if let Some((from, to)) = self.loopback {
+            inputs &= !to.mask();
+            if self.olat & from.mask() != 0 {
+                inputs |= to.mask();
+            }
+        }
+        Ok(((inputs & self.iodir) | (self.olat & !self.iodir)) & 0x0F)
+    }
+
+    fn output_latch(&self) -> u8 {
+        self.olat
+    }
+
+    fn write_olat(&mut self, value: u8) -> Result<(), Infallible> {
+        self.olat = value & 0x0F;
+        self.latches.push(self.olat);
+        Ok(())
+    }
+
+    fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Infallible> {
+        let mask = mask.into().bits();
+        self.write_olat((self.olat & !mask) | (value & mask))
+    }
+
+    fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Infallible> {
+        self.iodir = inputs.into().bits() & 0x0F;
+        Ok(())
+    }
+
+    fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Infallible> {
+        let mask = mask.into().bits();
+        self.set_port_direction((self.iodir & !mask) | (inputs & mask))
+    }
+
+    fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Infallible> {
+        self.pullups = mask.into().bits() & 0x0F;
+        Ok(())
+    }
+
+    fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Infallible> {
+        if enable {
+            self.pullups |= pin.mask();
+        } else {
+            self.pullups &= !pin.mask();
+        }
+        Ok(())
+    }
+
+    fn read_interrupt_flags(&mut self) -> Result<u8, Infallible> {
+        Ok(self.flags)
+    }
+
+    fn read_interrupt_capture(&mut self) -> Result<u8, Infallible> {
+        self.flags = 0;
+        self.read_port()
+    }
+}
+
+/// [`FakeExpander`] behind the async trait.
+struct AsyncFake(FakeExpander);
+
+impl AsyncIoExpander for AsyncFake {
+    type Error = Infallible;
+
+    const WIDTH: u8 = 4;
+
+    async fn read_port(&mut self) -> Result<u8, Infallible> {
+        self.0.read_port()
+    }
+
+    fn output_latch(&self) -> u8 {
+        self.0.olat
+    }
+
+    async fn write_olat(&mut self, value: u8) -> Result<(), Infallible> {
+        self.0.write_olat(value)
+    }
+
+    async fn modify_port(&mut self, mask: impl Into<PinSet>, value: u8) -> Result<(), Infallible> {
+        self.0.modify_port(mask, value)
+    }
+
+    async fn set_port_direction(&mut self, inputs: impl Into<PinSet>) -> Result<(), Infallible> {
+        self.0.set_port_direction(inputs)
+    }
+
+    async fn modify_port_direction(
+        &mut self,
+        mask: impl Into<PinSet>,
+        inputs: u8,
+    ) -> Result<(), Infallible> {
+        self.0.modify_port_direction(mask, inputs)
+    }
+
+    async fn set_port_pullups(&mut self, mask: impl Into<PinSet>) -> Result<(), Infallible> {
+        self.0.set_port_pullups(mask)
+    }
+
+    async fn set_pin_pullup(&mut self, pin: Pin, enable: bool) -> Result<(), Infallible> {
+        self.0.set_pin_pullup(pin, enable)
+    }
+
+    async fn read_interrupt_flags(&mut self) -> Result<u8, Infallible> {
+        self.0.read_interrupt_flags()
+    }
+
+    async fn read_interrupt_capture(&mut self) -> Result<u8, Infallible> {
+        self.0.read_interrupt_capture()
+    }
+}
+
+#[test]
+fn expander_pin_drives_a_fake() {
+    let mut fake = FakeExpander::new();
+    fake.set_pin_direction(Pin::P1, false).unwrap();
+    assert_eq!(fake.iodir, 0x0D);
+
+    let mut pin = ExpanderPin::new(&mut fake, Pin::P1);
+    assert_eq!(pin.pin(), Pin::P1);
+    pin.set_high().unwrap();
+    assert!(pin.is_set_high().unwrap());
+    assert!(pin.is_high().unwrap());
+    pin.toggle().unwrap();
+    assert!(pin.is_low().unwrap());
+    assert_eq!(fake.latches, [0x02, 0x00]);
+}
+
+#[test]
+fn higher_level_modules_run_on_a_fake() {
+    let mut fake = FakeExpander::new();
+    fake.set_port_direction(0x00).unwrap();
+
+    let mut sched = Scheduler::new();
+    sched.pulse(Pin::P3, 0, 10);
+    sched.poll(&mut fake, 0).unwrap();
+    assert_eq!(fake.olat, 0x08);
+    sched.poll(&mut fake, 10).unwrap();
+    assert_eq!(fake.olat, 0x00);
+
+    let mut chain: Hc595Chain<'_, _, 1> =
+        Hc595Chain::new(&mut fake, Pin::P0, Pin::P1, Pin::P2).unwrap();
+    chain.write([0x80]).unwrap();
+    // MSB first: data with the clock low, then the rising clock edge.
+    let bits = fake.latches.len() - 18;
+    assert_eq!(fake.latches[bits..bits + 4], [0x01, 0x03, 0x00, 0x02]);
+}
+
+#[test]
+fn bitbang_spi_loops_back_through_a_fake() {
+    let mut fake = FakeExpander::new();
+    fake.loopback = Some((Pin::P1, Pin::P2));
+
+    let mut spi = BitbangSpi::new(&mut fake, Pin::P0, Pin::P1, Some(Pin::P2), MODE_0).unwrap();
+    let mut buf = [0xA5, 0x3C];
+    spi.transfer_in_place(&mut buf).unwrap();
+    assert_eq!(buf, [0xA5, 0x3C]);
+    assert_eq!(fake.iodir, 0x0C);
+}
+
+#[test]
+fn async_modules_run_on_a_fake() {
+    let mut fake = AsyncFake(FakeExpander::new());
+    let pwm = SoftPwm::new(Pin::P0 | Pin::P2, 2);
+    pwm.set_duty(Pin::P0, 1);
+    pwm.set_duty(Pin::P2, 2);
+    block_on(async {
+        fake.set_port_direction(0x00).await.unwrap();
+        pwm.tick_async(&mut fake).await.unwrap();
+        assert_eq!(fake.0.olat, 0x05);
+        pwm.tick_async(&mut fake).await.unwrap();
+        assert_eq!(fake.0.olat, 0x04);
+
+        let mut pin = AsyncExpanderPin::new(&mut fake, Pin::P3);
+        pin.set_high().await.unwrap();
+        assert!(pin.is_set_high().await.unwrap());
+        pin.toggle().await.unwrap();
+        assert!(pin.is_low().await.unwrap());
+    });
+}
+
+/// Arms a pull-up input and reports its interrupt on any expander.
+fn arm_button<X: IoExpander>(dev: &mut X, pin: Pin) -> Result<bool, X::Error> {
+    dev.set_pin_direction(pin, true)?;
+    dev.set_pin_pullup(pin, true)?;
+    Ok(dev.read_interrupt_flags()? & pin.mask() != 0)
+}
+
+#[test]
+fn mcp23s08_implements_the_trait() {
+    assert_eq!(
+        <Mcp23s08<mcp23s08_io::sim::SimDevice<'_>> as IoExpander>::WIDTH,
+        8
+    );
+
+    let sim = Mcp23s08Sim::new();
+    sim.set_inputs(0, 0xFF);
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_interrupt_enable(Pin::P5).unwrap();
+    assert!(!arm_button(&mut dev, Pin::P5).unwrap());
+    assert_eq!(sim.reg(0, Reg::GPPU), 0x20);
+
+    sim.set_inputs(0, 0xDF);
+    assert!(arm_button(&mut dev, Pin::P5).unwrap());
+    assert_eq!(IoExpander::read_interrupt_capture(&mut dev).unwrap(), 0xDF);
+
+    let mut fake = FakeExpander::new();
+    fake.flags = 0x01;
+    assert!(arm_button(&mut fake, Pin::P0).unwrap());
+    assert_eq!(fake.pullups, 0x01);
+}
+
+#[test]
+fn each_port_of_a_16_bit_driver_is_an_expander() {
+    let sim = Mcp23s17Sim::new();
+    sim.set_inputs(0, 0xFFFF);
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    dev.set_port_interrupt_enable(Port::B, Pin::P5).unwrap();
+    assert!(!arm_button(&mut dev.port(Port::B), Pin::P5).unwrap());
+    assert_eq!(sim.reg(0, Port::B, Reg::GPPU), 0x20);
+    assert_eq!(sim.reg(0, Port::A, Reg::GPPU), 0x00);
+
+    let mut sched = Scheduler::new();
+    sched.pulse(Pin::P3, 0, 10);
+    let mut port_a = dev.port(Port::A);
+    port_a.set_port_direction(0x00).unwrap();
+    sched.poll(&mut port_a, 0).unwrap();
+    assert_eq!(port_a.output_latch(), 0x08);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x08);
+
+    let mut pin = dev.pin(Port::A, Pin::P3);
+    assert!(pin.is_set_high().unwrap());
+    pin.toggle().unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x00);
+
+    // On the MCP23S18 the same view drives open-drain outputs.
+    let sim = Mcp23s17Sim::mcp23s18();
+    let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    let mut port_b = dev.port(Port::B);
+    port_b.set_pin_direction(Pin::P1, false).unwrap();
+    ExpanderPin::new(&mut port_b, Pin::P1).set_low().unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x00);
+    assert!(!port_b.read_pin(Pin::P1).unwrap());
+
+    let sim = Mcp23s17Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s17async::new(sim.device(), 0).await.unwrap();
+        let pwm = SoftPwm::new(Pin::P0 | Pin::P2, 2);
+        pwm.set_duty(Pin::P0, 1);
+        pwm.set_duty(Pin::P2, 2);
+        let mut port_b = dev.port(Port::B);
+        port_b.set_port_direction(0x00).await.unwrap();
+        pwm.tick_async(&mut port_b).await.unwrap();
+        assert_eq!(port_b.output_latch(), 0x05);
+
+        let mut pin = dev.pin(Port::B, Pin::P2);
+        pin.set_low().await.unwrap();
+        assert!(pin.is_set_low().await.unwrap());
+    });
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x01);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+}
@@ -0,0 +1,177 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod common;
+
+use common::block_on;
+use mcp23s08_io::mcp23s08::{Error, HealthCheck, Mcp23s08, Pin, ResetDetect, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::mcp23s17::{Mcp23s17, Port};
+use mcp23s08_io::mcp23s17async::Mcp23s17async;
+use mcp23s08_io::sim::{Mcp23s08Sim, Mcp23s17Sim};
+
+#[test]
+fn check_detects_reset_and_restores_configuration() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0xF0).unwrap();
+    dev.set_port_pullups(0xF0).unwrap();
+    dev.write_olat(0x05).unwrap();
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir)))
+        .unwrap();
+
+    assert_eq!(dev.check().unwrap(), false);
+
+    sim.power_on_reset();
+    assert_eq!(sim.reg(0, Reg::IODIR), 0xFF);
+
+    assert_eq!(dev.check().unwrap(), true);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0xF0);
+    assert_eq!(sim.reg(0, Reg::GPPU), 0xF0);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x05);
+    assert!(dev.take_reset_event());
+    assert!(!dev.take_reset_event());
+    assert_eq!(dev.reset_count(), 1);
+}
+
+#[test]
+fn canary_on_access_recovers_before_the_next_write() {
+    let sim = Mcp23s08Sim::with_chips(&[2]);
+    let mut dev = Mcp23s08::new(sim.device(), 2).unwrap();
+    dev.set_port_direction(0xFF).unwrap(); // all inputs: IODIR cannot tell
+    dev.set_health_check(Some(
+        HealthCheck::new(ResetDetect::Canary(0x3C)).on_access(true),
+    ))
+    .unwrap();
+    assert_eq!(sim.reg(2, Reg::DEFVAL), 0x3C);
+
+    sim.power_on_reset();
+    dev.set_pin_direction(Pin::P0, false).unwrap();
+
+    // HAEN re-enabled through address 0, canary and direction written back.
+    assert_ne!(sim.reg(2, Reg::IOCON) & 0x08, 0);
+    assert_eq!(sim.reg(2, Reg::DEFVAL), 0x3C);
+    assert_eq!(sim.reg(2, Reg::IODIR), 0xFE);
+    assert_eq!(dev.reset_count(), 1);
+}
+
+#[test]
+fn reset_without_recovery_is_reported_until_restore() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x0F).unwrap();
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir).recover(false)))
+        .unwrap();
+
+    sim.power_on_reset();
+    assert!(matches!(dev.check(), Err(Error::UnexpectedReset)));
+    assert!(matches!(dev.check(), Err(Error::UnexpectedReset)));
+
+    dev.restore().unwrap();
+    assert_eq!(dev.check().unwrap(), false);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0x0F);
+}
+
+#[test]
+fn async_check_restores_configuration() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        dev.write_olat(0xA0).await.unwrap();
+        dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir)))
+            .await
+            .unwrap();
+
+        sim.power_on_reset();
+        assert_eq!(dev.check().await.unwrap(), true);
+    });
+    assert_eq!(sim.reg(0, Reg::IODIR), 0x00);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0xA0);
+}
+
+#[test]
+fn default_compare_cannot_overwrite_the_canary() {
+    let canary = Some(HealthCheck::new(ResetDetect::Canary(0x3C)));
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_health_check(canary).unwrap();
+    assert!(matches!(
+        dev.set_port_default_compare(0x10),
+        Err(Error::CanaryInUse)
+    ));
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x3C);
+    assert_eq!(dev.check().unwrap(), false);
+
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir)))
+        .unwrap();
+    dev.set_port_default_compare(0x10).unwrap();
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x10);
+
+    let sim17 = Mcp23s17Sim::new();
+    let mut dev17 = Mcp23s17::new(sim17.device(), 0).unwrap();
+    dev17.set_health_check(canary).unwrap();
+    assert!(matches!(
+        dev17.set_port_default_compare(Port::B, 0x10),
+        Err(Error::CanaryInUse)
+    ));
+    assert!(matches!(
+        dev17.set_port_default_compare16(0x1010),
+        Err(Error::CanaryInUse)
+    ));
+    assert_eq!(sim17.reg(0, Port::B, Reg::DEFVAL), 0x3C);
+
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_health_check(canary).await.unwrap();
+        assert!(matches!(
+            dev.set_port_default_compare(0x10).await,
+            Err(Error::CanaryInUse)
+        ));
+
+        let mut dev17 = Mcp23s17async::new(sim17.device(), 0).await.unwrap();
+        dev17.set_health_check(canary).await.unwrap();
+        assert!(matches!(
+            dev17.set_port_default_compare(Port::A, 0x10).await,
+            Err(Error::CanaryInUse)
+        ));
+        assert!(matches!(
+            dev17.set_port_default_compare16(0x1010).await,
+            Err(Error::CanaryInUse)
+        ));
+    });
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x3C);
+    assert_eq!(sim17.reg(0, Port::A, Reg::DEFVAL), 0x3C);
+}
+
+#[test]
+fn probe_puts_the_canary_back() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x0F).unwrap();
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Canary(0x3C))))
+        .unwrap();
+
+    dev.probe().unwrap();
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x3C);
+    assert_eq!(dev.check().unwrap(), false);
+
+    // A probe after a reset finds DEFVAL cleared and recovers the chip.
+    sim.power_on_reset();
+    dev.probe().unwrap();
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x3C);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0x0F);
+    assert_eq!(dev.reset_count(), 1);
+    assert_eq!(dev.check().unwrap(), false);
+
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_health_check(Some(HealthCheck::new(ResetDetect::Canary(0x5A))))
+            .await
+            .unwrap();
+        sim.power_on_reset();
+        dev.probe().await.unwrap();
+        assert_eq!(dev.reset_count(), 1);
+    });
+    assert_eq!(sim.reg(0, Reg::DEFVAL), 0x5A);
+}
@@ -0,0 +1,170 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod common;
+
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+use mcp23s08_io::mcp23s08::{Access, Error, Mcp23008, Pin, Reg, RetryPolicy};
+use mcp23s08_io::mcp23s08async::Mcp23008async;
+use mcp23s08_io::mcp23s17::{Bank, Mcp23017, Port};
+
+use common::block_on;
+
+/// `new_i2c()` traffic: IOCON cleared (no HAEN on I²C), then IODIR and OLAT
+/// read into the shadow.
+fn init_expectations(address: u8, iodir: u8, olat: u8) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::write(address, vec![0x05, 0x00]),
+        I2cTransaction::write_read(address, vec![0x00], vec![iodir]),
+        I2cTransaction::write_read(address, vec![0x0A], vec![olat]),
+    ]
+}
+
+#[test]
+fn mcp23008_uses_register_address_framing() {
+    let mut expectations = init_expectations(0x23, 0xFF, 0x00);
+    expectations.extend([
+        I2cTransaction::write(0x23, vec![0x00, 0x0F]),
+        I2cTransaction::write(0x23, vec![0x0A, 0xA0]),
+        I2cTransaction::write(0x23, vec![0x09, 0xA1]),
+        I2cTransaction::write_read(0x23, vec![0x09], vec![0x5A]),
+    ]);
+    let i2c = I2cMock::new(&expectations);
+
+    let mut dev = Mcp23008::new_i2c(i2c, 0x23).unwrap();
+    dev.set_port_direction(0x0F).unwrap();
+    dev.write_olat(0xA0).unwrap();
+    dev.write_pin(Pin::P0, true).unwrap();
+    assert_eq!(dev.read_port().unwrap(), 0x5A);
+
+    dev.into_inner().into_inner().done();
+}
+
+#[test]
+fn i2c_address_must_be_in_0x20_to_0x27() {
+    for address in [0x00, 0x1F, 0x28, 0x40] {
+        let mut i2c = I2cMock::new(&[]);
+        assert!(matches!(
+            Mcp23008::new_i2c(i2c.clone(), address),
+            Err(Error::BadAddress)
+        ));
+        assert!(matches!(
+            Mcp23017::new_i2c(i2c.clone(), address),
+            Err(Error::BadAddress)
+        ));
+        i2c.done();
+    }
+}
+
+#[test]
+fn i2c_errors_carry_the_register() {
+    let mut expectations = init_expectations(0x20, 0xFF, 0x00);
+    expectations.push(I2cTransaction::write(0x20, vec![0x0A, 0x01]).with_error(ErrorKind::Other));
+    let mut i2c = I2cMock::new(&expectations);
+
+    let mut dev = Mcp23008::new_i2c(i2c.clone(), 0x20).unwrap();
+    assert_eq!(
+        dev.write_olat(0x01),
+        Err(Error::Bus {
+            reg: Reg::OLAT,
+            access: Access::Write,
+            source: ErrorKind::Other,
+        })
+    );
+    i2c.done();
+}
+
+#[test]
+fn missing_chip_is_not_retried() {
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut i2c = I2cMock::new(&[I2cTransaction::write(0x21, vec![0x05, 0x00]).with_error(nack)]);
+
+    let err = Mcp23008::new_i2c(i2c.clone(), 0x21).err().unwrap();
+    assert_eq!(err.bus_error(), Some(&nack));
+    i2c.done();
+
+    let mut expectations = init_expectations(0x21, 0xFF, 0x00);
+    expectations.push(I2cTransaction::write_read(0x21, vec![0x09], vec![0]).with_error(nack));
+    let mut i2c = I2cMock::new(&expectations);
+    let mut dev = Mcp23008::new_i2c(i2c.clone(), 0x21).unwrap();
+    dev.set_retry_policy(RetryPolicy::new(3));
+    assert!(dev.read_port().is_err());
+    assert_eq!(dev.retry_stats().retries, 0);
+    i2c.done();
+}
+
+#[test]
+fn probe_reports_an_unacknowledged_address_as_not_present() {
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut expectations = init_expectations(0x22, 0xFF, 0x00);
+    expectations
+        .push(I2cTransaction::write_read(0x22, vec![Reg::DEFVAL as u8], vec![0]).with_error(nack));
+    let mut i2c = I2cMock::new(&expectations);
+
+    let mut dev = Mcp23008::new_i2c(i2c.clone(), 0x22).unwrap();
+    assert_eq!(dev.probe(), Err(Error::NotPresent));
+    i2c.done();
+}
+
+#[test]
+fn arbitration_loss_is_retried_and_counted_on_its_own() {
+    let mut expectations = init_expectations(0x20, 0xFF, 0x00);
+    expectations.extend([
+        I2cTransaction::write_read(0x20, vec![0x09], vec![0])
+            .with_error(ErrorKind::ArbitrationLoss),
+        I2cTransaction::write_read(0x20, vec![0x09], vec![0x81]),
+    ]);
+    let mut i2c = I2cMock::new(&expectations);
+
+    let mut dev = Mcp23008::new_i2c(i2c.clone(), 0x20).unwrap();
+    dev.set_retry_policy(RetryPolicy::new(3));
+    assert_eq!(dev.read_port().unwrap(), 0x81);
+    assert_eq!(dev.retry_stats().recovered, 1);
+    #[cfg(feature = "stats")]
+    {
+        let errors = dev.stats().errors;
+        assert_eq!(errors.arbitration_loss, 1);
+        assert_eq!(errors.total(), 1);
+    }
+    i2c.done();
+}
+
+#[test]
+fn mcp23017_moves_16_bits_in_one_transfer() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write(0x27, vec![0x0A, 0x00]),
+        I2cTransaction::write_read(0x27, vec![0x00], vec![0xFF, 0xFF]),
+        I2cTransaction::write_read(0x27, vec![0x14], vec![0x00, 0x00]),
+        I2cTransaction::write(0x27, vec![0x14, 0xEF, 0xBE]),
+        I2cTransaction::write_read(0x27, vec![0x12], vec![0x01, 0x80]),
+        // Separate layout: OLATB at 0x1A.
+        I2cTransaction::write(0x27, vec![0x0A, 0x80]),
+        I2cTransaction::write(0x27, vec![0x1A, 0x42]),
+    ]);
+
+    let mut dev = Mcp23017::new_i2c(i2c, 0x27).unwrap();
+    dev.write_olat16(0xBEEF).unwrap();
+    assert_eq!(dev.read_port16().unwrap(), 0x8001);
+    dev.set_bank(Bank::Separate).unwrap();
+    dev.write_olat(Port::B, 0x42).unwrap();
+
+    dev.into_inner().into_inner().done();
+}
+
+#[test]
+fn async_mcp23008_matches_blocking_frames() {
+    let mut expectations = init_expectations(0x21, 0xFF, 0x00);
+    expectations.extend([
+        I2cTransaction::write(0x21, vec![0x0A, 0x80]),
+        I2cTransaction::write_read(0x21, vec![0x09], vec![0x80]),
+    ]);
+    let mut i2c = I2cMock::new(&expectations);
+
+    block_on(async {
+        let mut dev = Mcp23008async::new_i2c(i2c.clone(), 0x21).await.unwrap();
+        dev.write_olat(0x80).await.unwrap();
+        assert_eq!(dev.read_pin(Pin::P7).await.unwrap(), true);
+    });
+    i2c.done();
+}
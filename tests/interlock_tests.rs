@@ -0,0 +1,140 @@
+mod common;
+
+use core::cell::RefCell;
+
+use common::block_on;
+use mcp23s08_io::mcp23s08::{Error, Interlock, Mcp23s08, Pin, PinSet, Reg, Rule, Violation};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+const FWD_REV: PinSet = PinSet::EMPTY.with(Pin::P0).with(Pin::P1);
+
+static RULES: [Rule; 3] = [
+    Rule::Exclusive(FWD_REV),
+    Rule::Requires {
+        pin: Pin::P2,
+        needs: Pin::P3,
+    },
+    Rule::MaxActive(3),
+];
+
+/// Delay that records the latch of chip 0 at the moment it is asked to wait.
+struct LatchProbe<'a> {
+    sim: &'a Mcp23s08Sim,
+    seen: &'a RefCell<Vec<(u8, u32)>>,
+}
+
+impl embedded_hal::delay::DelayNs for LatchProbe<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.seen
+            .borrow_mut()
+            .push((self.sim.reg(0, Reg::OLAT), ns / 1_000));
+    }
+}
+
+impl embedded_hal_async::delay::DelayNs for LatchProbe<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.seen
+            .borrow_mut()
+            .push((self.sim.reg(0, Reg::OLAT), ns / 1_000));
+    }
+}
+
+#[test]
+fn check_reports_the_broken_rule() {
+    let interlock = Interlock::new(&RULES);
+    assert_eq!(interlock.check(0b0000_1101), Ok(()));
+    assert_eq!(
+        interlock.check(0b0000_0011),
+        Err(Violation::Exclusive { active: FWD_REV })
+    );
+    assert_eq!(
+        interlock.check(0b0000_0100),
+        Err(Violation::Requires {
+            pin: Pin::P2,
+            needs: Pin::P3,
+        })
+    );
+    assert_eq!(
+        interlock.check(0b1111_0000),
+        Err(Violation::MaxActive { active: 4, max: 3 })
+    );
+    // Active-low relays: a cleared bit is "on".
+    let low = Interlock::new(&RULES).active_low(FWD_REV);
+    assert!(low.check(0b0000_0000).is_err());
+    assert_eq!(low.check(0b0000_0010), Ok(()));
+}
+
+#[test]
+fn forbidden_writes_are_refused_before_the_bus() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev.set_interlock(Some(Interlock::new(&RULES)));
+
+    dev.write_pin(Pin::P0, true).unwrap();
+    let before = sim.transactions();
+    let refused = Err(Error::Interlock(Violation::Exclusive { active: FWD_REV }));
+    assert_eq!(dev.write_pin(Pin::P1, true), refused);
+    assert_eq!(dev.write_port(0b0000_0011), refused);
+    assert_eq!(dev.write_olat(0b0000_0011), refused);
+    assert_eq!(dev.modify_port(FWD_REV, 0xFF), refused);
+    assert_eq!(sim.transactions(), before);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b0000_0001);
+
+    dev.modify_port(FWD_REV, 0b0000_0010).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b0000_0010);
+}
+
+#[test]
+fn break_before_make_releases_first_and_waits() {
+    let sim = Mcp23s08Sim::new();
+    let seen = RefCell::new(Vec::new());
+    let mut dev = Mcp23s08::new(sim.device(), 0)
+        .unwrap()
+        .with_delay(LatchProbe {
+            sim: &sim,
+            seen: &seen,
+        });
+    dev.set_port_direction(0x00).unwrap();
+    dev.write_olat(0b1000_1001).unwrap();
+    dev.set_interlock(Some(Interlock::new(&RULES).break_before_make(50)));
+
+    // Reverse: P0 off, dead-time, then P1 on. P3/P7 stay on throughout.
+    dev.modify_port(FWD_REV, 0b0000_0010).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b1000_1010);
+    assert_eq!(*seen.borrow(), [(0b1000_1000, 50)]);
+
+    // Outside an exclusive group nothing is staged.
+    dev.write_pin(Pin::P7, false).unwrap();
+    assert_eq!(seen.borrow().len(), 1);
+}
+
+#[test]
+fn async_driver_applies_the_same_rules() {
+    let sim = Mcp23s08Sim::new();
+    let seen = RefCell::new(Vec::new());
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0)
+            .await
+            .unwrap()
+            .with_delay(LatchProbe {
+                sim: &sim,
+                seen: &seen,
+            });
+        dev.set_port_direction(0x00).await.unwrap();
+        dev.set_interlock(Some(Interlock::new(&RULES).break_before_make(10)));
+
+        dev.write_olat(0b0000_1101).await.unwrap();
+        assert_eq!(
+            dev.write_pin(Pin::P3, false).await,
+            Err(Error::Interlock(Violation::Requires {
+                pin: Pin::P2,
+                needs: Pin::P3,
+            }))
+        );
+        dev.modify_port(FWD_REV, 0b0000_0010).await.unwrap();
+    });
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b0000_1110);
+    assert_eq!(*seen.borrow(), [(0b0000_1100, 10)]);
+}
@@ -0,0 +1,23 @@
+#![cfg(all(feature = "linux", target_os = "linux"))]
+
+use std::io;
+
+use embedded_hal::spi::{Error, ErrorKind};
+use mcp23s08_io::linux::SpidevError;
+
+fn kind(errno: i32) -> ErrorKind {
+    SpidevError(io::Error::from_raw_os_error(errno)).kind()
+}
+
+#[test]
+fn spidev_errors_map_to_spi_error_kinds() {
+    // EOVERFLOW, EINVAL, EOPNOTSUPP and EIO on Linux.
+    assert_eq!(kind(75), ErrorKind::Overrun);
+    assert_eq!(kind(22), ErrorKind::FrameFormat);
+    assert_eq!(kind(95), ErrorKind::FrameFormat);
+    assert_eq!(kind(5), ErrorKind::Other);
+    assert_eq!(
+        SpidevError(io::Error::other("no errno")).kind(),
+        ErrorKind::Other
+    );
+}
@@ -4,16 +4,12 @@
 
 use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
 
-// Bring the driver under test into scope.
-// If your file is at `src/mcp23s08.rs` (typical), keep this as-is.
-// Otherwise, adjust the relative path below.
-#[path = "../src/mcp23s08.rs"]
-mod mcp23s08;
-use mcp23s08::{Error, Mcp23s08, Pin, Polarity};
+use mcp23s08_io::mcp23s08::{Access, Error, InvalidPin, Mcp23s08, Pin, PinSet, Polarity, Reg};
+use mcp23s08_io::sim::Mcp23s08Sim;
 
 // Helpers
 fn init_expectations_for_new(hw_addr: u8, iodir: u8, olat: u8) -> Vec<SpiTransaction<u8>> {
-    let op_wr = 0x40 | ((hw_addr & 0x03) << 1) | 0; // write opcode
+    let op_wr = 0x40 | ((hw_addr & 0x03) << 1); // write opcode
     let op_rd = 0x40 | ((hw_addr & 0x03) << 1) | 1; // read opcode
 
     vec![
@@ -34,6 +30,19 @@ fn init_expectations_for_new(hw_addr: u8, iodir: u8, olat: u8) -> Vec<SpiTransac
     ]
 }
 
+/// `HAEN` set through address 0, where `IOCON` reads `iocon`.
+fn haen_expectations(iocon: u8) -> Vec<SpiTransaction<u8>> {
+    vec![
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x41, 0x05]),
+        SpiTransaction::read_vec(vec![iocon]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x40, 0x05, iocon | 0x08]),
+        SpiTransaction::transaction_end(),
+    ]
+}
+
 #[test]
 fn new_ok_initializes_cached_state() {
     let expectations = init_expectations_for_new(0, 0xFF, 0x00);
@@ -62,7 +71,9 @@ fn new_rejects_bad_hw_address() {
 #[test]
 fn new_accepts_addr_3() {
     // начальные значения регистров, которые драйвер читает
-    let expectations = init_expectations_for_new(3, 0xFF, 0x00);
+    // IOCON is left to the HAEN read-modify-write on address 0.
+    let mut expectations = haen_expectations(0x00);
+    expectations.extend(init_expectations_for_new(3, 0xFF, 0x00).split_off(3));
     let mut spi = SpiMock::new(&expectations);
 
     let dev = Mcp23s08::new(spi.clone(), 3).expect("addr 3 is valid");
@@ -233,3 +244,203 @@ fn set_int_open_drain_and_polarity() {
     drop(dev);
     spi.done();
 }
+
+#[test]
+fn pin_index_conversions_round_trip() {
+    for (i, pin) in Pin::ALL.iter().enumerate() {
+        assert_eq!(u8::from(*pin), i as u8);
+        assert_eq!(Pin::try_from(i as u8), Ok(*pin));
+        assert_eq!(pin.mask(), 1 << i);
+    }
+    assert_eq!(Pin::try_from(8), Err(InvalidPin(8)));
+}
+
+#[test]
+fn pinset_algebra_iteration_and_debug() {
+    let low = PinSet::from(0x0F);
+    let set = Pin::P1 | Pin::P4;
+
+    assert_eq!(u8::from(low | set), 0b0001_1111);
+    assert_eq!((low & set).bits(), 0b0000_0010);
+    assert_eq!((low - set).bits(), 0b0000_1101);
+    assert_eq!((!low).bits(), 0xF0);
+    assert!(set.contains(Pin::P4) && !set.contains(Pin::P0));
+    assert_eq!(set.len(), 2);
+
+    let pins: Vec<Pin> = set.into_iter().collect();
+    assert_eq!(pins, [Pin::P1, Pin::P4]);
+    assert_eq!(set.iter().next_back(), Some(Pin::P4));
+    assert_eq!(PinSet::from_iter(pins), set);
+    assert_eq!(format!("{set:?}"), "{P1, P4}");
+}
+
+#[test]
+fn port_mask_methods_accept_pinset() {
+    let mut expectations = init_expectations_for_new(0, 0xFF, 0x00);
+    let op_wr = 0x40;
+    expectations.extend([
+        // IODIR from a PinSet
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![op_wr, 0x00, 0b1111_0000]),
+        SpiTransaction::transaction_end(),
+        // GPPU from a plain u8 mask
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![op_wr, 0x06, 0b1111_0000]),
+        SpiTransaction::transaction_end(),
+        // GPINTEN from a single pin
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![op_wr, 0x02, 0b1000_0000]),
+        SpiTransaction::transaction_end(),
+    ]);
+
+    let mut spi = SpiMock::new(&expectations);
+    let mut dev = Mcp23s08::new(spi.clone(), 0).unwrap();
+
+    let inputs: PinSet = [Pin::P4, Pin::P5, Pin::P6, Pin::P7].into();
+    dev.set_port_direction(inputs).unwrap();
+    dev.set_port_pullups(0b1111_0000).unwrap();
+    dev.set_port_interrupt_enable(Pin::P7).unwrap();
+
+    drop(dev);
+    spi.done();
+}
+
+/// SPI device whose every transaction fails.
+struct FailingSpi;
+
+impl embedded_hal::spi::ErrorType for FailingSpi {
+    type Error = embedded_hal::spi::ErrorKind;
+}
+
+impl embedded_hal::spi::SpiDevice for FailingSpi {
+    fn transaction(
+        &mut self,
+        _operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        Err(embedded_hal::spi::ErrorKind::ModeFault)
+    }
+}
+
+#[test]
+fn spi_error_carries_register_and_access() {
+    let err = Mcp23s08::new(FailingSpi, 0).err().unwrap();
+
+    assert_eq!(
+        err,
+        Error::Bus {
+            reg: Reg::IOCON,
+            access: Access::Write,
+            source: embedded_hal::spi::ErrorKind::ModeFault,
+        }
+    );
+    assert_eq!(err.reg(), Some(Reg::IOCON));
+    assert_eq!(
+        embedded_hal::spi::Error::kind(&err),
+        embedded_hal::spi::ErrorKind::ModeFault
+    );
+    assert_eq!(err.to_string(), "bus error writing IOCON: ModeFault");
+
+    let boxed: Box<dyn core::error::Error> = Box::new(Error::<()>::BadAddress);
+    assert_eq!(boxed.to_string(), "hardware address out of range");
+}
+
+/// `probe()` traffic for a chip at `hw_addr`; an absent chip reads `0xFF`.
+fn probe_expectations(hw_addr: u8, present: bool) -> Vec<SpiTransaction<u8>> {
+    let op_wr = 0x40 | (hw_addr << 1);
+    let op_rd = op_wr | 1;
+    let read_defval = |value: u8| {
+        [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![op_rd, 0x03]), // Reg::DEFVAL
+            SpiTransaction::read_vec(vec![value]),
+            SpiTransaction::transaction_end(),
+        ]
+    };
+    let write_defval = |value: u8| {
+        [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![op_wr, 0x03, value]),
+            SpiTransaction::transaction_end(),
+        ]
+    };
+
+    let saved = if present { 0x12 } else { 0xFF };
+    let mut v = Vec::new();
+    v.extend(read_defval(saved));
+    if present {
+        v.extend(write_defval(0xA5));
+        v.extend(read_defval(0xA5));
+        v.extend(write_defval(0x5A));
+        v.extend(read_defval(0x5A));
+    } else {
+        v.extend(write_defval(0xA5));
+        v.extend(read_defval(0xFF));
+    }
+    v.extend(write_defval(saved));
+    v
+}
+
+#[test]
+fn probe_detects_present_and_missing_chip() {
+    let mut expectations = init_expectations_for_new(0, 0xFF, 0x00);
+    expectations.extend(probe_expectations(0, true));
+    expectations.extend(probe_expectations(0, false));
+
+    let mut spi = SpiMock::new(&expectations);
+    let mut dev = Mcp23s08::new(spi.clone(), 0).unwrap();
+
+    dev.probe().unwrap();
+    assert!(matches!(dev.probe(), Err(Error::NotPresent)));
+
+    drop(dev);
+    spi.done();
+}
+
+#[test]
+fn scan_enables_haen_and_reports_responding_addresses() {
+    // The chip at address 0 keeps its open-drain INT output.
+    let mut expectations = haen_expectations(0x04);
+    expectations.extend(probe_expectations(0, true));
+    expectations.extend(probe_expectations(1, false));
+    expectations.extend(probe_expectations(2, true));
+    expectations.extend(probe_expectations(3, false));
+
+    let mut spi = SpiMock::new(&expectations);
+    let found = Mcp23s08::scan(&mut spi).unwrap();
+    assert_eq!(found, [true, false, true, false]);
+
+    spi.done();
+}
+
+#[test]
+fn haen_broadcast_keeps_the_configuration_at_address_0() {
+    let sim = Mcp23s08Sim::with_chips(&[0, 2]);
+    let mut first = Mcp23s08::new(sim.device(), 0).unwrap();
+    first.set_int_open_drain(true).unwrap();
+    // The chip at address 2 kept HAEN across an MCU-only reset.
+    sim.set_reg(2, Reg::IOCON, 0x08);
+
+    Mcp23s08::new(sim.device(), 2).unwrap();
+    assert_eq!(sim.reg(0, Reg::IOCON), 0x0C);
+    assert_eq!(sim.reg(2, Reg::IOCON), 0x08);
+}
+
+#[test]
+fn dump_reads_and_decodes_every_register() {
+    let sim = Mcp23s08Sim::with_chips(&[1]);
+    sim.set_inputs(1, 0xA0);
+    let mut dev = Mcp23s08::new(sim.device(), 1).unwrap();
+    dev.set_port_direction(0xF0).unwrap();
+    dev.write_olat(0x05).unwrap();
+
+    let dump = dev.dump().unwrap();
+    assert_eq!(dump.get(Reg::IODIR), 0xF0);
+    assert_eq!(dump.get(Reg::IOCON), 0x08);
+    assert_eq!(dump.get(Reg::GPIO), 0xA5);
+    let text = dump.to_string();
+    assert_eq!(text.lines().count(), Reg::ALL.len());
+    assert!(text.starts_with("IODIR   0xf0  inputs: P4 P5 P6 P7\n"));
+    assert!(text.contains("\nIOCON   0x08  HAEN\n"));
+    assert!(text.contains("\nINTF    0x00  pending: -\n"));
+    assert!(text.ends_with("OLAT    0x05  high: P0 P2\n"));
+}
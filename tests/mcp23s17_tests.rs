@@ -0,0 +1,363 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod common;
+
+use embedded_hal::digital::{InputPin, StatefulOutputPin};
+use embedded_hal::spi::SpiDevice;
+
+use mcp23s08_io::mcp23s08::{Error, Pin, PinSet, Reg};
+use mcp23s08_io::mcp23s17::{
+    Bank, HealthCheck, Interlock, Mcp23s17, Port, ResetDetect, Rule, VerifyMode, VerifyPolicy,
+    Violation, WatchdogConfig, WatchdogFault,
+};
+use mcp23s08_io::mcp23s17async::Mcp23s17async;
+use mcp23s08_io::sim::Mcp23s17Sim;
+
+use common::block_on;
+
+const IOCON_BANK: u8 = 0x80;
+
+#[test]
+fn register_addresses_follow_datasheet() {
+    assert_eq!(Bank::Paired.addr(Reg::IODIR, Port::B), 0x01);
+    assert_eq!(Bank::Paired.addr(Reg::IOCON, Port::A), 0x0A);
+    assert_eq!(Bank::Paired.addr(Reg::GPIO, Port::A), 0x12);
+    assert_eq!(Bank::Paired.addr(Reg::OLAT, Port::B), 0x15);
+    assert_eq!(Bank::Separate.addr(Reg::IOCON, Port::A), 0x05);
+    assert_eq!(Bank::Separate.addr(Reg::GPIO, Port::B), 0x19);
+    assert_eq!(Bank::Separate.addr(Reg::OLAT, Port::B), 0x1A);
+
+    // Raw frames against the simulator, independent of the driver.
+    let sim = Mcp23s17Sim::new();
+    let mut spi = sim.device();
+    spi.write(&[0x40, 0x14, 0x11, 0x22]).unwrap(); // OLATA, OLATB
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x11);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x22);
+
+    spi.write(&[0x40, 0x0A, IOCON_BANK]).unwrap();
+    spi.write(&[0x40, 0x1A, 0x33]).unwrap(); // OLATB
+    spi.write(&[0x40, 0x0A, 0x44]).unwrap(); // OLATA
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x44);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x33);
+    assert_eq!(sim.reg(0, Port::B, Reg::IOCON), IOCON_BANK);
+}
+
+#[test]
+fn paired_layout_moves_16_bits_in_one_transaction() {
+    let sim = Mcp23s17Sim::new();
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    assert_eq!(dev.bank(), Bank::Paired);
+    dev.set_port_direction16(0xF00F).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IODIR), 0x0F);
+    assert_eq!(sim.reg(0, Port::B, Reg::IODIR), 0xF0);
+
+    let before = sim.transactions();
+    dev.write_olat16(0x0AA0).unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xA0);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x0A);
+
+    sim.set_inputs(0, 0x5005);
+    let before = sim.transactions();
+    assert_eq!(dev.read_port16().unwrap(), 0x5AA5);
+    assert_eq!(sim.transactions() - before, 1);
+
+    // Only port B changes.
+    let before = sim.transactions();
+    dev.modify_port16(0x0F00, 0x0500).unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xA0);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x05);
+}
+
+#[test]
+fn separate_layout_addresses_each_port() {
+    let sim = Mcp23s17Sim::new();
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    dev.write_olat16(0x1234).unwrap();
+    dev.set_bank(Bank::Separate).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON) & IOCON_BANK, IOCON_BANK);
+    // Switching the layout leaves the latches alone.
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x34);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x12);
+
+    dev.set_port_direction(Port::A, 0x00).unwrap();
+    dev.set_port_pullups(Port::B, 0xFF).unwrap();
+    dev.write_olat(Port::B, 0x81).unwrap();
+    dev.write_pin(Port::A, Pin::P7, true).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IODIR), 0x00);
+    assert_eq!(sim.reg(0, Port::B, Reg::IODIR), 0xFF);
+    assert_eq!(sim.reg(0, Port::B, Reg::GPPU), 0xFF);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xB4);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x81);
+
+    let before = sim.transactions();
+    dev.write_olat16(0xBEEF).unwrap();
+    assert_eq!(sim.transactions() - before, 2);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xEF);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0xBE);
+
+    sim.set_inputs(0, 0x4200);
+    assert_eq!(dev.read_port(Port::B).unwrap(), 0x42);
+    assert_eq!(dev.read_port16().unwrap(), 0x42EF);
+
+    // Back to the power-on layout.
+    dev.set_bank(Bank::Paired).unwrap();
+    dev.write_olat(Port::B, 0x01).unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x01);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0xEF);
+}
+
+#[test]
+fn new_in_bank_keeps_a_separate_layout() {
+    let sim = Mcp23s17Sim::with_chips(&[0, 5]);
+    sim.set_reg(5, Port::A, Reg::IOCON, IOCON_BANK | 0x08);
+    sim.set_reg(5, Port::B, Reg::IOCON, IOCON_BANK | 0x08);
+    sim.set_reg(5, Port::B, Reg::IODIR, 0x00);
+    sim.set_reg(5, Port::B, Reg::OLAT, 0x3C);
+
+    let mut dev = Mcp23s17::new_in_bank(sim.device(), 5, Bank::Separate).unwrap();
+    assert_eq!(sim.reg(5, Port::A, Reg::IOCON), IOCON_BANK | 0x08);
+    let mut pin = dev.pin(Port::B, Pin::P2);
+    assert_eq!(pin.is_set_high().unwrap(), true);
+    assert_eq!(pin.is_high().unwrap(), true);
+
+    dev.write_olat(Port::A, 0x77).unwrap();
+    assert_eq!(sim.reg(5, Port::A, Reg::OLAT), 0x77);
+    // The chip at address 0 was only touched by the HAEN broadcast.
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+
+    assert!(matches!(
+        Mcp23s17::new(sim.device(), 8),
+        Err(Error::BadAddress)
+    ));
+}
+
+#[test]
+fn haen_broadcast_keeps_the_configuration_at_address_0() {
+    let sim = Mcp23s17Sim::with_chips(&[0, 6]);
+    let mut first = Mcp23s17::new(sim.device(), 0).unwrap();
+    first.set_mirror(true).unwrap();
+    first.set_port_pullups(Port::A, 0x0F).unwrap();
+    sim.set_reg(6, Port::A, Reg::IOCON, 0x08);
+    sim.set_reg(6, Port::B, Reg::IOCON, 0x08);
+
+    let mut second = Mcp23s17::new(sim.device(), 6).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0x48);
+    assert_eq!(sim.reg(0, Port::A, Reg::GPPU), 0x0F);
+
+    // The chip at address 6 is reset and gets HAEN back the same way.
+    sim.set_reg(6, Port::A, Reg::IOCON, 0x00);
+    sim.set_reg(6, Port::B, Reg::IOCON, 0x00);
+    second.restore().unwrap();
+    assert_eq!(sim.reg(6, Port::A, Reg::IOCON), 0x08);
+}
+
+#[test]
+fn restoring_a_chip_leaves_a_separate_layout_chip_0_alone() {
+    let sim = Mcp23s17Sim::with_chips(&[0, 1]);
+    let mut first = Mcp23s17::new(sim.device(), 0).unwrap();
+    first.set_bank(Bank::Separate).unwrap();
+    first.set_port_direction(Port::A, 0x00).unwrap();
+    first.write_olat(Port::A, 0x5A).unwrap();
+    let iocon = sim.reg(0, Port::A, Reg::IOCON);
+
+    let mut second = Mcp23s17::new(sim.device(), 1).unwrap();
+    second.set_port_direction16(0x0000).unwrap();
+    second.write_olat16(0x1234).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x5A);
+
+    // Only chip 1 loses power.
+    for port in [Port::A, Port::B] {
+        sim.set_reg(1, port, Reg::IOCON, 0x00);
+        sim.set_reg(1, port, Reg::IODIR, 0xFF);
+        sim.set_reg(1, port, Reg::OLAT, 0x00);
+    }
+    second.restore().unwrap();
+    assert_eq!(sim.reg(1, Port::A, Reg::IOCON), 0x08);
+    assert_eq!(sim.reg(1, Port::A, Reg::OLAT), 0x34);
+    assert_eq!(sim.reg(1, Port::B, Reg::OLAT), 0x12);
+
+    // 0x0A, IOCON of a reset chip, is OLATA in chip 0's layout.
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x5A);
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), iocon);
+    assert_eq!(first.read_port(Port::A).unwrap(), 0x5A);
+}
+
+#[test]
+fn mirror_ties_both_interrupt_outputs_together() {
+    for bank in [Bank::Paired, Bank::Separate] {
+        let sim = Mcp23s17Sim::new();
+        let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+        dev.set_bank(bank).unwrap();
+        sim.set_inputs(0, 0xFFFF);
+        dev.set_port_interrupt_enable16(0xFFFF).unwrap();
+
+        sim.set_inputs(0, 0xFEFF);
+        assert!(!sim.interrupt_pending(0, Port::A));
+        assert!(sim.interrupt_pending(0, Port::B));
+        assert_eq!(dev.read_interrupt_flags16().unwrap(), 0x0100);
+        assert_eq!(dev.read_interrupt_capture(Port::B).unwrap(), 0xFE);
+        assert!(!sim.interrupt_pending(0, Port::B));
+
+        dev.set_mirror(true).unwrap();
+        sim.set_inputs(0, 0x7FFF);
+        assert!(sim.interrupt_pending(0, Port::A));
+        assert!(sim.interrupt_pending(0, Port::B));
+        assert_eq!(dev.clear_interrupts16().unwrap(), 0x7FFF);
+        assert!(!sim.interrupt_pending(0, Port::A));
+    }
+}
+
+#[test]
+fn async_driver_matches_blocking_layouts() {
+    let sim = Mcp23s17Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s17async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction16(0x0000).await.unwrap();
+        dev.write_olat16(0x8001).await.unwrap();
+        dev.set_bank(Bank::Separate).await.unwrap();
+        dev.modify_port(Port::B, Pin::P0, 0x01).await.unwrap();
+        let mut pin = dev.pin(Port::A, Pin::P0);
+        pin.toggle().await.unwrap();
+        assert_eq!(dev.read_port16().await.unwrap(), 0x8100);
+    });
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x81);
+}
+
+const FWD_REV: PinSet = PinSet::EMPTY.with(Pin::P0).with(Pin::P1);
+
+static RULES: [Rule; 1] = [Rule::Exclusive(FWD_REV)];
+
+#[test]
+fn check_restores_separate_layout_after_reset() {
+    let sim = Mcp23s17Sim::with_chips(&[3]);
+    let mut dev = Mcp23s17::new(sim.device(), 3).unwrap();
+    dev.set_port_direction16(0x0FF0).unwrap();
+    dev.write_olat16(0x8001).unwrap();
+    dev.set_bank(Bank::Separate).unwrap();
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir)))
+        .unwrap();
+    assert_eq!(dev.check().unwrap(), false);
+
+    sim.power_on_reset();
+    assert_eq!(dev.check().unwrap(), true);
+    assert_eq!(dev.bank(), Bank::Separate);
+    assert_eq!(sim.reg(3, Port::A, Reg::IOCON) & IOCON_BANK, IOCON_BANK);
+    assert_eq!(sim.reg(3, Port::A, Reg::IODIR), 0xF0);
+    assert_eq!(sim.reg(3, Port::B, Reg::IODIR), 0x0F);
+    assert_eq!(sim.reg(3, Port::A, Reg::OLAT), 0x01);
+    assert_eq!(sim.reg(3, Port::B, Reg::OLAT), 0x80);
+    assert_eq!(dev.reset_count(), 1);
+
+    dev.write_pin(Port::B, Pin::P0, true).unwrap();
+    assert_eq!(sim.reg(3, Port::B, Reg::OLAT), 0x81);
+}
+
+#[test]
+fn verify_catches_a_lost_bank_or_mirror_write() {
+    let sim = Mcp23s17Sim::new();
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    dev.set_verify(VerifyPolicy::new(VerifyMode::All, 0));
+
+    sim.drop_next_writes(1);
+    assert_eq!(
+        dev.set_bank(Bank::Separate),
+        Err(Error::VerifyFailed {
+            reg: Reg::IOCON,
+            wrote: IOCON_BANK,
+            read: 0x00,
+        })
+    );
+    // The chip kept the paired layout and so does the driver.
+    assert_eq!(dev.bank(), Bank::Paired);
+    sim.drop_next_writes(1);
+    assert_eq!(
+        dev.set_mirror(true),
+        Err(Error::VerifyFailed {
+            reg: Reg::IOCON,
+            wrote: 0x40,
+            read: 0x00,
+        })
+    );
+
+    dev.set_verify(VerifyPolicy::new(VerifyMode::All, 1));
+    sim.drop_next_writes(1);
+    dev.set_bank(Bank::Separate).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), IOCON_BANK);
+    dev.write_olat(Port::B, 0x42).unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x42);
+}
+
+#[test]
+fn verify_watchdog_and_interlock_cover_both_ports() {
+    let sim = Mcp23s17Sim::new();
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    dev.set_port_direction16(0x0000).unwrap();
+
+    dev.set_verify(VerifyPolicy::new(VerifyMode::Outputs, 1));
+    sim.drop_next_writes(1);
+    dev.write_olat16(0xA55A).unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0xA5);
+    sim.drop_next_writes(2);
+    assert_eq!(
+        dev.write_olat(Port::B, 0x01),
+        Err(Error::VerifyFailed {
+            reg: Reg::OLAT,
+            wrote: 0x01,
+            read: 0xA5,
+        })
+    );
+    dev.set_verify(VerifyPolicy::OFF);
+
+    dev.set_interlock(Port::B, Some(Interlock::new(&RULES)));
+    assert_eq!(
+        dev.write_olat16(0x0300),
+        Err(Error::Interlock(Violation::Exclusive { active: FWD_REV }))
+    );
+    // The same pins of port A are not covered.
+    dev.write_olat16(0x0103).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x03);
+
+    dev.set_watchdog(Some(WatchdogConfig::new(100, 0x8000).safe_iodir(0x7FFF)), 0);
+    assert_eq!(dev.poll_watchdog(50).unwrap(), false);
+    assert_eq!(dev.poll_watchdog(150).unwrap(), true);
+    assert_eq!(dev.watchdog_fault(), Some(WatchdogFault::Timeout));
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x80);
+    assert_eq!(sim.reg(0, Port::A, Reg::IODIR), 0xFF);
+    assert_eq!(sim.reg(0, Port::B, Reg::IODIR), 0x7F);
+    assert_eq!(
+        dev.write_pin(Port::A, Pin::P2, true),
+        Err(Error::WatchdogFault(WatchdogFault::Timeout))
+    );
+
+    dev.clear_watchdog_fault(200);
+    dev.write_pin(Port::A, Pin::P2, true).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x04);
+}
+
+#[test]
+fn async_driver_checks_and_trips_like_blocking() {
+    let sim = Mcp23s17Sim::with_chips(&[1]);
+    block_on(async {
+        let mut dev = Mcp23s17async::new(sim.device(), 1).await.unwrap();
+        dev.set_port_direction16(0xFF00).await.unwrap();
+        dev.write_olat16(0x0042).await.unwrap();
+        dev.set_health_check(Some(HealthCheck::new(ResetDetect::Canary(0x5A))))
+            .await
+            .unwrap();
+        assert_eq!(sim.reg(1, Port::B, Reg::DEFVAL), 0x5A);
+
+        sim.power_on_reset();
+        assert_eq!(dev.check().await.unwrap(), true);
+        assert_eq!(sim.reg(1, Port::A, Reg::DEFVAL), 0x5A);
+        assert_eq!(sim.reg(1, Port::A, Reg::OLAT), 0x42);
+        assert_eq!(sim.reg(1, Port::B, Reg::IODIR), 0xFF);
+
+        dev.set_watchdog(Some(WatchdogConfig::new(10, 0x0000)), 0);
+        assert_eq!(dev.poll_watchdog(20).await.unwrap(), true);
+        assert_eq!(sim.reg(1, Port::A, Reg::OLAT), 0x00);
+    });
+}
@@ -0,0 +1,155 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod common;
+
+use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::spi::SpiDevice;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+use mcp23s08_io::mcp23s08::{Error, Pin, Reg};
+use mcp23s08_io::mcp23s17::{Bank, Mcp23s17, Port, VerifyMode, VerifyPolicy};
+use mcp23s08_io::mcp23s18::{InterruptClear, Mcp23s18, Mcp23018};
+use mcp23s08_io::mcp23s18async::Mcp23s18async;
+use mcp23s08_io::sim::Mcp23s17Sim;
+
+use common::block_on;
+
+#[test]
+fn mcp23s18_has_no_address_decoding() {
+    let sim = Mcp23s17Sim::mcp23s18();
+    let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    assert_eq!(dev.hw_addr(), 0);
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0x00);
+    dev.set_bank(Bank::Separate).unwrap();
+    dev.write_olat(Port::B, 0x5A).unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x5A);
+
+    // HAEN and bit 4 are not implemented; other opcodes go unanswered.
+    let mut spi = sim.device();
+    spi.write(&[0x40, 0x05, 0xFF]).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0xE7);
+    spi.write(&[0x42, 0x1A, 0x00]).unwrap();
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x5A);
+}
+
+#[test]
+fn open_drain_pin_sinks_and_releases() {
+    let sim = Mcp23s17Sim::mcp23s18();
+    let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    let mut led = dev.open_drain(Port::A, Pin::P3).unwrap();
+    assert_eq!(led.is_set_high().unwrap(), true);
+
+    led.set_low().unwrap();
+    assert_eq!(led.is_high().unwrap(), false);
+    led.set_high().unwrap();
+    assert_eq!(led.is_high().unwrap(), true);
+
+    // Released, but another device holds the line low.
+    sim.set_inputs(0, 0xFFF7);
+    assert_eq!(led.is_set_high().unwrap(), true);
+    assert_eq!(led.is_high().unwrap(), false);
+    led.toggle().unwrap();
+    assert_eq!(led.is_set_low().unwrap(), true);
+
+    assert_eq!(sim.reg(0, Port::A, Reg::IODIR), 0xF7);
+    assert_eq!(sim.reg(0, Port::A, Reg::OLAT), 0x00);
+}
+
+#[test]
+fn intcc_selects_the_register_that_clears_interrupts() {
+    let sim = Mcp23s17Sim::mcp23s18();
+    let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    assert_eq!(dev.interrupt_clear(), InterruptClear::Gpio);
+    dev.set_port_interrupt_enable(Port::A, 0xFF).unwrap();
+
+    sim.set_inputs(0, 0xFFFE);
+    assert!(sim.interrupt_pending(0, Port::A));
+    assert_eq!(dev.read_port(Port::A).unwrap(), 0xFE);
+    assert!(!sim.interrupt_pending(0, Port::A));
+
+    dev.set_interrupt_clear(InterruptClear::Capture).unwrap();
+    assert_eq!(dev.interrupt_clear(), InterruptClear::Capture);
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0x01);
+    sim.set_inputs(0, 0xFFFF);
+    assert_eq!(dev.read_port(Port::A).unwrap(), 0xFF);
+    assert!(sim.interrupt_pending(0, Port::A));
+    assert_eq!(dev.read_interrupt_capture(Port::A).unwrap(), 0xFF);
+    assert!(!sim.interrupt_pending(0, Port::A));
+}
+
+#[test]
+fn verify_covers_intcc() {
+    let sim = Mcp23s17Sim::mcp23s18();
+    let mut dev = Mcp23s18::new(sim.device()).unwrap();
+    dev.set_verify(VerifyPolicy::new(VerifyMode::All, 0));
+
+    sim.drop_next_writes(1);
+    assert_eq!(
+        dev.set_interrupt_clear(InterruptClear::Capture),
+        Err(Error::VerifyFailed {
+            reg: Reg::IOCON,
+            wrote: 0x01,
+            read: 0x00,
+        })
+    );
+    dev.set_interrupt_clear(InterruptClear::Capture).unwrap();
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0x01);
+}
+
+#[test]
+fn mcp23s17_moves_to_another_hardware_address() {
+    let sim = Mcp23s17Sim::with_chips(&[0, 3]);
+    let mut dev = Mcp23s17::new(sim.device(), 0).unwrap();
+    dev.set_hw_addr(3).unwrap();
+    assert_eq!(dev.hw_addr(), 3);
+    assert_eq!(sim.reg(3, Port::A, Reg::IOCON), 0x08);
+    dev.write_olat16(0x1234).unwrap();
+    assert_eq!(sim.reg(3, Port::B, Reg::OLAT), 0x12);
+    assert_eq!(sim.reg(0, Port::B, Reg::OLAT), 0x00);
+
+    assert!(matches!(dev.set_hw_addr(8), Err(Error::BadAddress)));
+}
+
+#[test]
+fn mcp23018_uses_i2c_framing() {
+    let mut i2c = I2cMock::new(&[
+        I2cTransaction::write(0x24, vec![0x0A, 0x00]),
+        I2cTransaction::write_read(0x24, vec![0x00], vec![0xFF, 0xFF]),
+        I2cTransaction::write_read(0x24, vec![0x14], vec![0x00, 0x00]),
+        I2cTransaction::write(0x24, vec![0x0A, 0x01]),
+        // open_drain(): latch released first, then the output enabled.
+        I2cTransaction::write(0x24, vec![0x15, 0x80]),
+        I2cTransaction::write(0x24, vec![0x01, 0x7F]),
+        I2cTransaction::write(0x24, vec![0x13, 0x00]),
+    ]);
+
+    let mut dev = Mcp23018::new_i2c(i2c.clone(), 0x24).unwrap();
+    dev.set_interrupt_clear(InterruptClear::Capture).unwrap();
+    let mut pin = dev.open_drain(Port::B, Pin::P7).unwrap();
+    pin.set_low().unwrap();
+
+    assert!(matches!(
+        Mcp23018::new_i2c(i2c.clone(), 0x28),
+        Err(Error::BadAddress)
+    ));
+    i2c.done();
+}
+
+#[test]
+fn async_driver_matches_blocking() {
+    let sim = Mcp23s17Sim::mcp23s18();
+    block_on(async {
+        let mut dev = Mcp23s18async::new(sim.device()).await.unwrap();
+        dev.set_interrupt_clear(InterruptClear::Capture)
+            .await
+            .unwrap();
+        let mut pin = dev.open_drain(Port::B, Pin::P0).await.unwrap();
+        pin.set_low().await.unwrap();
+        assert_eq!(pin.is_low().await.unwrap(), true);
+        pin.toggle().await.unwrap();
+        assert_eq!(pin.is_high().await.unwrap(), true);
+        assert_eq!(dev.read_port16().await.unwrap(), 0xFFFF);
+    });
+    assert_eq!(sim.reg(0, Port::A, Reg::IOCON), 0x01);
+    assert_eq!(sim.reg(0, Port::B, Reg::IODIR), 0xFE);
+}
@@ -0,0 +1,145 @@
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Reg};
+use mcp23s08_io::parallel::{ParallelBus, Timing};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+/// Latch-style peripheral on the data port.
+#[derive(Default)]
+struct Peripheral {
+    reading: bool,
+    written: Vec<u8>,
+    to_read: Vec<u8>,
+    /// Bus contention seen: both sides driving at a strobe.
+    contention: bool,
+}
+
+/// Native MCU pin wired to the peripheral.
+struct Line<'a> {
+    sim: &'a Mcp23s08Sim,
+    periph: &'a RefCell<Peripheral>,
+    strobe: bool,
+    log: &'a RefCell<Vec<&'static str>>,
+}
+
+impl ErrorType for Line<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for Line<'_> {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        let mut p = self.periph.borrow_mut();
+        if self.strobe {
+            self.log.borrow_mut().push("strobe low");
+        } else {
+            p.reading = false;
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        let mut p = self.periph.borrow_mut();
+        if !self.strobe {
+            p.reading = true;
+            return Ok(());
+        }
+        self.log.borrow_mut().push("strobe high");
+        let outputs = self.sim.reg(0, Reg::IODIR) != 0xFF;
+        if p.reading {
+            p.contention |= outputs;
+            let byte = p.to_read.remove(0);
+            self.sim.set_inputs(0, byte);
+        } else {
+            let byte = self.sim.reg(0, Reg::OLAT);
+            p.written.push(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Records the requested waits.
+struct Waits<'a>(&'a RefCell<Vec<&'static str>>);
+
+impl embedded_hal::delay::DelayNs for Waits<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(match ns {
+            10 => "setup",
+            20 => "strobe",
+            30 => "hold",
+            _ => "other",
+        });
+    }
+}
+
+#[test]
+fn writes_cost_one_transaction_per_byte() {
+    let sim = Mcp23s08Sim::new();
+    let periph = RefCell::new(Peripheral::default());
+    let log = RefCell::new(Vec::new());
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    let strobe = Line {
+        sim: &sim,
+        periph: &periph,
+        strobe: true,
+        log: &log,
+    };
+    let mut bus = ParallelBus::new(&mut dev, strobe).unwrap();
+
+    let before = sim.transactions();
+    bus.write_bytes(&[0x48, 0x49, 0x21]).unwrap();
+    // Direction set once, then one OLAT write per byte.
+    assert_eq!(sim.transactions() - before, 4);
+    assert_eq!(periph.borrow().written, [0x48, 0x49, 0x21]);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0x00);
+}
+
+#[test]
+fn reads_turn_the_bus_around_with_timing() {
+    let sim = Mcp23s08Sim::new();
+    let periph = RefCell::new(Peripheral {
+        to_read: vec![0x5A, 0xA5],
+        ..Default::default()
+    });
+    let log = RefCell::new(Vec::new());
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    let strobe = Line {
+        sim: &sim,
+        periph: &periph,
+        strobe: true,
+        log: &log,
+    };
+    let rw = Line {
+        sim: &sim,
+        periph: &periph,
+        strobe: false,
+        log: &log,
+    };
+    let timing = Timing {
+        setup_ns: 10,
+        strobe_ns: 20,
+        hold_ns: 30,
+    };
+    let mut bus = ParallelBus::new(&mut dev, strobe)
+        .unwrap()
+        .with_rw(rw)
+        .with_delay(Waits(&log), timing);
+
+    bus.write_byte(0x01).unwrap();
+    log.borrow_mut().clear();
+    assert_eq!(bus.read_byte().unwrap(), 0x5A);
+    assert_eq!(
+        *log.borrow(),
+        ["setup", "strobe high", "strobe", "strobe low", "hold"]
+    );
+    assert_eq!(sim.reg(0, Reg::IODIR), 0xFF);
+
+    let before = sim.transactions();
+    assert_eq!(bus.read_byte().unwrap(), 0xA5);
+    assert_eq!(sim.transactions() - before, 1);
+
+    bus.write_byte(0x02).unwrap();
+    assert_eq!(periph.borrow().written, [0x01, 0x02]);
+    assert!(!periph.borrow().contention);
+}
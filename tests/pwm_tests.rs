@@ -0,0 +1,113 @@
+mod common;
+
+use std::sync::mpsc;
+use std::thread;
+
+use common::block_on;
+use embedded_hal::pwm::SetDutyCycle;
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, PinSet, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::pwm::SoftPwm;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[test]
+fn pattern_follows_duty_cycles() {
+    let pwm = SoftPwm::new(PinSet::from_bits(0x0F), 4);
+    pwm.set_duty(Pin::P0, 0);
+    pwm.set_duty(Pin::P1, 1);
+    pwm.set_duty(Pin::P2, 3);
+    pwm.set_duty(Pin::P3, 9); // clamped to 4
+    assert_eq!(pwm.duty(Pin::P3), 4);
+
+    let patterns: Vec<u8> = (0..4).map(|phase| pwm.pattern(phase)).collect();
+    assert_eq!(patterns, [0b1110, 0b1100, 0b1100, 0b1000]);
+}
+
+#[test]
+fn writes_only_when_the_pattern_changes() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev.write_pin(Pin::P7, true).unwrap();
+
+    let pwm = SoftPwm::new(Pin::P0 | Pin::P1, 100);
+    pwm.channel(Pin::P0).set_duty_cycle_percent(25).unwrap();
+    pwm.channel(Pin::P1).set_duty_cycle_fully_on().unwrap();
+
+    let before = sim.transactions();
+    let mut levels = Vec::new();
+    for _ in 0..200 {
+        pwm.tick(&mut dev).unwrap();
+        levels.push(sim.reg(0, Reg::OLAT));
+    }
+    // First tick, P0 off at 25, P0 on at 100 (next period), off at 125.
+    assert_eq!(sim.transactions() - before, 4);
+    assert_eq!(levels[0], 0x83);
+    assert_eq!(levels[24], 0x83);
+    assert_eq!(levels[25], 0x82);
+    assert_eq!(levels[100], 0x83);
+    assert_eq!(levels[199], 0x82);
+}
+
+#[test]
+fn failed_write_is_repeated_on_the_next_tick() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    let pwm = SoftPwm::new(Pin::P2, 10);
+    pwm.set_duty(Pin::P2, 5);
+
+    sim.fail_next(1);
+    assert!(pwm.tick(&mut dev).is_err());
+    pwm.tick(&mut dev).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x04);
+}
+
+#[test]
+fn duty_cycles_change_from_another_thread_while_ticking() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    let pwm = SoftPwm::new(Pin::P3, 10);
+    let (set, done) = mpsc::channel();
+
+    // The "timer" keeps ticking here while the other thread, like task
+    // code next to an ISR, changes the duty cycle through the same `&SoftPwm`.
+    thread::scope(|s| {
+        s.spawn(|| {
+            pwm.channel(Pin::P3).set_duty_cycle_fraction(3, 10).unwrap();
+            set.send(()).unwrap();
+        });
+        while done.try_recv().is_err() {
+            pwm.tick(&mut dev).unwrap();
+        }
+    });
+
+    // Any ten consecutive ticks cover one period.
+    let mut high = 0;
+    for _ in 0..10 {
+        pwm.tick(&mut dev).unwrap();
+        high += u32::from(sim.reg(0, Reg::OLAT) & 0x08 != 0);
+    }
+    assert_eq!(high, 3);
+}
+
+#[test]
+fn async_ticks_drive_the_same_patterns() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let pwm = SoftPwm::new(Pin::P5, 8);
+        let mut ch = pwm.channel(Pin::P5);
+        assert_eq!(ch.max_duty_cycle(), 8);
+        ch.set_duty_cycle_fraction(1, 2).unwrap();
+
+        let mut high = 0;
+        for _ in 0..8 {
+            pwm.tick_async(&mut dev).await.unwrap();
+            high += u32::from(sim.reg(0, Reg::OLAT) & 0x20 != 0);
+        }
+        assert_eq!(high, 4);
+    });
+}
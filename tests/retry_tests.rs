@@ -0,0 +1,99 @@
+mod common;
+
+use common::block_on;
+use embedded_hal::spi::ErrorKind;
+use mcp23s08_io::mcp23s08::{Access, Error, Mcp23s08, Reg, RetryPolicy, RetryStats};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::retry::any_error;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+/// Delay that records what it was asked to wait.
+#[derive(Default)]
+struct RecordingDelay(Vec<u32>);
+
+impl embedded_hal::delay::DelayNs for RecordingDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.push(ns / 1_000);
+    }
+}
+
+#[test]
+fn transient_errors_are_retried_with_backoff() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0)
+        .unwrap()
+        .with_delay(RecordingDelay::default());
+    dev.set_retry_policy(RetryPolicy::new(4).backoff_us(100));
+
+    sim.fail_next(2);
+    dev.write_olat(0x11).unwrap();
+
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x11);
+    assert_eq!(
+        dev.retry_stats(),
+        RetryStats {
+            retried: 1,
+            retries: 2,
+            recovered: 1,
+            exhausted: 0,
+        }
+    );
+    dev.reset_retry_stats();
+    assert_eq!(dev.retry_stats(), RetryStats::default());
+}
+
+#[test]
+fn backoff_doubles_between_attempts() {
+    let sim = Mcp23s08Sim::new();
+    let mut delay = RecordingDelay::default();
+    {
+        let mut dev = Mcp23s08::new(sim.device(), 0)
+            .unwrap()
+            .with_delay(&mut delay);
+        dev.set_retry_policy(RetryPolicy::new(3).backoff_us(50));
+
+        sim.fail_next(5);
+        let err = dev.read_port().err().unwrap();
+        assert_eq!(
+            err,
+            Error::Bus {
+                reg: Reg::GPIO,
+                access: Access::Read,
+                source: ErrorKind::Other,
+            }
+        );
+        assert_eq!(dev.retry_stats().exhausted, 1);
+    }
+    assert_eq!(delay.0, [50, 100]);
+}
+
+#[test]
+fn classifier_decides_what_is_retried() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_retry_policy(RetryPolicy::new(3));
+
+    // ModeFault is not transient by default.
+    sim.fail_next_with(1, ErrorKind::ModeFault);
+    assert!(dev.write_olat(0x01).is_err());
+    assert_eq!(dev.retry_stats().retries, 0);
+
+    dev.set_retry_policy(RetryPolicy::new(3).retryable(any_error));
+    sim.fail_next_with(1, ErrorKind::ModeFault);
+    dev.write_olat(0x01).unwrap();
+    assert_eq!(dev.retry_stats().recovered, 1);
+}
+
+#[test]
+fn async_driver_retries_transient_errors() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_retry_policy(RetryPolicy::new(2));
+
+        sim.fail_next(1);
+        dev.write_olat(0x77).await.unwrap();
+        assert_eq!(dev.retry_stats().recovered, 1);
+    });
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x77);
+}
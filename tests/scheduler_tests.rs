@@ -0,0 +1,149 @@
+mod common;
+
+use core::cell::{Cell, RefCell};
+
+use common::{block_on, output_device};
+use mcp23s08_io::mcp23s08::{Pin, PinSet, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::scheduler::Scheduler;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[test]
+fn pulse_sets_and_clears_on_time() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut sched = Scheduler::new();
+
+    sched.pulse(Pin::P4, 1_000, 250);
+    assert_eq!(sched.poll(&mut dev, 1_000).unwrap(), Some(1_250));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x10);
+
+    let before = sim.transactions();
+    assert_eq!(sched.poll(&mut dev, 1_249).unwrap(), Some(1_250));
+    assert_eq!(sim.transactions(), before);
+
+    assert_eq!(sched.poll(&mut dev, 1_250).unwrap(), None);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+    assert!(sched.is_idle());
+}
+
+#[test]
+fn due_changes_are_merged_into_one_write() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    dev.write_olat(0x80).unwrap();
+    let mut sched = Scheduler::new();
+
+    sched.pulse(Pin::P0, 0, 100);
+    sched.pulse(Pin::P1, 0, 100);
+    sched.set_after(Pin::P2, true, 0, 100);
+    sched.set_after(Pin::P7, false, 0, 100);
+    assert_eq!(sched.pending().len(), 4);
+
+    sched.poll(&mut dev, 0).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x83);
+
+    let before = sim.transactions();
+    sched.poll(&mut dev, 100).unwrap();
+    assert_eq!(sim.transactions(), before + 1);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x04);
+    assert_eq!(sched.pending(), PinSet::EMPTY);
+}
+
+#[test]
+fn blink_keeps_its_grid_and_ends_low() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut sched = Scheduler::new();
+
+    sched.blink(Pin::P3, 0, 10, 30, 2);
+    let mut levels = Vec::new();
+    for now in [0, 12, 41, 50, 200] {
+        sched.poll(&mut dev, now).unwrap();
+        levels.push(sim.reg(0, Reg::OLAT) & 0x08 != 0);
+    }
+    // A late poll (12 instead of 10) does not shift the next edge from 40.
+    assert_eq!(levels, [true, false, true, false, false]);
+    assert!(sched.is_idle());
+
+    // A poll after several missed edges only writes the final level.
+    sched.blink(Pin::P3, 0, 10, 10, 3);
+    sched.poll(&mut dev, 25).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x08);
+    assert_eq!(sched.next_deadline(), Some(30));
+}
+
+#[test]
+fn failed_write_keeps_jobs_pending() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut sched = Scheduler::new();
+
+    sched.pulse(Pin::P0, 0, 5);
+    sim.fail_next(1);
+    assert!(sched.poll(&mut dev, 0).is_err());
+    assert_eq!(sched.next_deadline(), Some(0));
+    sched.poll(&mut dev, 1).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x01);
+
+    sched.cancel(Pin::P0);
+    assert!(sched.is_idle());
+}
+
+/// Delay that advances a virtual clock.
+struct VirtualClock<'a>(&'a RefCell<Vec<u32>>);
+
+impl embedded_hal_async::delay::DelayNs for VirtualClock<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(ns / 1_000_000);
+    }
+}
+
+#[test]
+fn async_run_sleeps_between_deadlines() {
+    let sim = Mcp23s08Sim::new();
+    let sleeps = RefCell::new(Vec::new());
+    let end = block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let mut sched = Scheduler::new();
+        sched.pulse(Pin::P4, 0, 250);
+        sched.blink(Pin::P5, 0, 100, 100, 2);
+        sched
+            .run(&mut dev, &mut VirtualClock(&sleeps), 0)
+            .await
+            .unwrap()
+    });
+    assert_eq!(end, 300);
+    assert_eq!(*sleeps.borrow(), [100, 100, 50, 50]);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+}
+
+/// Delay that adds up the time it was asked to wait.
+struct Stopwatch<'a>(&'a Cell<u64>);
+
+impl embedded_hal_async::delay::DelayNs for Stopwatch<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.set(self.0.get() + u64::from(ns));
+    }
+}
+
+#[test]
+fn async_run_waits_out_deadlines_beyond_u32_ms() {
+    let sim = Mcp23s08Sim::new();
+    let slept_ns = Cell::new(0);
+    let due = u64::from(u32::MAX) + 5_000;
+    let end = block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let mut sched = Scheduler::new();
+        sched.set_after(Pin::P1, true, due - 10, 10);
+        sched
+            .run(&mut dev, &mut Stopwatch(&slept_ns), 0)
+            .await
+            .unwrap()
+    });
+    assert_eq!(end, due);
+    assert_eq!(slept_ns.get(), due * 1_000_000);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x02);
+}
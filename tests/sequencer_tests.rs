@@ -0,0 +1,151 @@
+mod common;
+
+use core::cell::{Cell, RefCell};
+
+use common::{block_on, output_device};
+use mcp23s08_io::mcp23s08::{Pin, PinSet, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sequencer::{Playback, Sequencer, Step};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+static CHASE: [Step; 3] = [
+    Step::new(0, 0b001),
+    Step::new(100, 0b010),
+    Step::new(200, 0b100),
+];
+
+#[test]
+fn plays_once_and_finishes() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut seq = Sequencer::new(CHASE.iter().copied());
+
+    // Nothing happens before start.
+    assert_eq!(seq.tick(&mut dev, 0).unwrap(), None);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+
+    seq.start(1_000);
+    assert_eq!(seq.tick(&mut dev, 1_000).unwrap(), Some(1_100));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b001);
+    assert_eq!(seq.tick(&mut dev, 1_150).unwrap(), Some(1_200));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b010);
+    assert_eq!(seq.tick(&mut dev, 1_200).unwrap(), None);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b100);
+    assert_eq!(seq.playback(), Playback::Finished);
+}
+
+#[test]
+fn loops_and_merges_missed_steps() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut seq = Sequencer::new(CHASE.iter().copied()).looping(300);
+    seq.start(0);
+    seq.tick(&mut dev, 0).unwrap();
+
+    // 250 ms late: steps at 100 and 200 go out as one write.
+    let before = sim.transactions();
+    assert_eq!(seq.tick(&mut dev, 250).unwrap(), Some(300));
+    assert_eq!(sim.transactions(), before + 1);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b100);
+
+    assert_eq!(seq.tick(&mut dev, 310).unwrap(), Some(400));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b001);
+    assert_eq!(seq.position_ms(), 10);
+    assert_eq!(seq.playback(), Playback::Playing);
+}
+
+#[test]
+fn masked_steps_leave_other_pins_alone() {
+    static BLINK_LOW: [Step; 2] = [
+        Step::masked(0, 0x0F, PinSet::from_bits(0x0F)),
+        Step::masked(50, 0x00, PinSet::from_bits(0x0F)),
+    ];
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    dev.write_pin(Pin::P7, true).unwrap();
+
+    let mut seq = Sequencer::new(BLINK_LOW.iter().copied());
+    seq.start(0);
+    seq.tick(&mut dev, 0).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x8F);
+    seq.tick(&mut dev, 50).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x80);
+}
+
+#[test]
+fn speed_pause_and_stop() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let steps = (0..8u8).map(|i| Step::new(u32::from(i) * 100, 1 << i));
+    let mut seq = Sequencer::new(steps);
+
+    seq.set_speed_percent(200);
+    seq.start(0);
+    seq.tick(&mut dev, 0).unwrap();
+    assert_eq!(seq.tick(&mut dev, 50).unwrap(), Some(100));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b10);
+
+    seq.pause();
+    assert_eq!(seq.tick(&mut dev, 1_000).unwrap(), None);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b10);
+
+    seq.resume(1_000);
+    seq.set_speed_percent(50);
+    assert_eq!(seq.tick(&mut dev, 1_000).unwrap(), Some(1_200));
+    seq.tick(&mut dev, 1_200).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b100);
+
+    seq.stop();
+    assert_eq!(seq.playback(), Playback::Stopped);
+    assert_eq!(seq.position_ms(), 0);
+    assert_eq!(seq.tick(&mut dev, 5_000).unwrap(), None);
+}
+
+/// Delay that only records how long it was asked to wait.
+struct VirtualClock<'a>(&'a RefCell<Vec<u32>>);
+
+impl embedded_hal_async::delay::DelayNs for VirtualClock<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(ns / 1_000_000);
+    }
+}
+
+#[test]
+fn async_run_plays_the_whole_timeline() {
+    let sim = Mcp23s08Sim::new();
+    let sleeps = RefCell::new(Vec::new());
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let mut seq = Sequencer::new(CHASE.iter().copied());
+        seq.set_speed_percent(400);
+        seq.run(&mut dev, &mut VirtualClock(&sleeps)).await.unwrap();
+        assert_eq!(seq.playback(), Playback::Finished);
+    });
+    assert_eq!(*sleeps.borrow(), [25, 25]);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0b100);
+}
+
+/// Delay that adds up the time it was asked to wait.
+struct Stopwatch<'a>(&'a Cell<u64>);
+
+impl embedded_hal_async::delay::DelayNs for Stopwatch<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.set(self.0.get() + u64::from(ns));
+    }
+}
+
+#[test]
+fn async_run_waits_out_steps_beyond_u32_ms() {
+    let sim = Mcp23s08Sim::new();
+    let slept_ns = Cell::new(0);
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let mut seq = Sequencer::new([Step::new(u32::MAX, 0x01)].into_iter());
+        seq.set_speed_percent(50);
+        seq.run(&mut dev, &mut Stopwatch(&slept_ns)).await.unwrap();
+    });
+    assert_eq!(slept_ns.get(), 2 * u64::from(u32::MAX) * 1_000_000);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x01);
+}
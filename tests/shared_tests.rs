@@ -0,0 +1,108 @@
+#![cfg(all(feature = "critical-section", feature = "embassy-sync"))]
+
+mod common;
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use common::block_on;
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::spi::{ErrorType, Operation};
+use mcp23s08_io::expander::{AsyncIoExpander, IoExpander};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::shared::{AsyncSharedExpander, SharedExpander};
+use mcp23s08_io::sim::{Mcp23s08Sim, SimDevice};
+
+/// Async bus that returns `Pending` once before every transaction, so that
+/// concurrent tasks get a chance to interleave.
+struct Yielding<'a>(SimDevice<'a>);
+
+impl ErrorType for Yielding<'_> {
+    type Error = <SimDevice<'static> as ErrorType>::Error;
+}
+
+impl embedded_hal_async::spi::SpiDevice for Yielding<'_> {
+    async fn transaction(&mut self, ops: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        embedded_hal_async::spi::SpiDevice::transaction(&mut self.0, ops).await
+    }
+}
+
+#[test]
+fn blocking_handles_and_pins_share_one_driver() {
+    let sim = Mcp23s08Sim::new();
+    let shared = SharedExpander::new(Mcp23s08::new(sim.device(), 0).unwrap());
+    let mut relays = shared.handle();
+    let mut inputs = relays;
+    relays.set_port_direction(0xF0).unwrap();
+
+    let mut relay = relays.pin(Pin::P1);
+    let mut copy = relay;
+    relay.set_high().unwrap();
+    inputs.write_pin(Pin::P2, true).unwrap();
+    assert!(copy.is_set_high().unwrap());
+    copy.toggle().unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x04);
+    assert_eq!(inputs.output_latch(), 0x04);
+
+    sim.set_inputs(0, 0x80);
+    assert!(inputs.pin(Pin::P7).is_high().unwrap());
+
+    // Several calls as one step.
+    relays
+        .with(|dev| {
+            dev.write_olat(0x00)?;
+            dev.modify_port(Pin::P3, 0xFF)
+        })
+        .unwrap();
+    assert_eq!(shared.into_inner().output_latch(), 0x08);
+}
+
+#[test]
+fn async_tasks_do_not_lose_each_others_writes() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(Yielding(sim.device()), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let shared: AsyncSharedExpander<NoopRawMutex, _> = AsyncSharedExpander::new(dev);
+        let io = shared.handle();
+
+        // Both tasks read the cached latch, yield inside the SPI write and
+        // only then store the new latch.
+        let (mut relay, mut led) = (io.pin(Pin::P0), io.pin(Pin::P5));
+        let (a, b) = join(relay.set_high(), led.set_high()).await;
+        a.unwrap();
+        b.unwrap();
+        assert_eq!(sim.reg(0, Reg::OLAT), 0x21);
+        assert_eq!(io.output_latch(), 0x21);
+
+        let (a, b) = join(
+            relay.toggle(),
+            io.clone().modify_port_direction(Pin::P7, 0xFF),
+        )
+        .await;
+        a.unwrap();
+        b.unwrap();
+        assert_eq!(sim.reg(0, Reg::OLAT), 0x20);
+        assert_eq!(sim.reg(0, Reg::IODIR), 0x80);
+        assert!(led.is_set_high().await.unwrap());
+
+        let mut guard = io.lock().await;
+        guard.write_olat(0x02).await.unwrap();
+        drop(guard);
+        assert_eq!(io.output_latch(), 0x02);
+    });
+}
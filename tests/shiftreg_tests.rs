@@ -0,0 +1,117 @@
+mod common;
+
+use core::cell::RefCell;
+
+use common::{Peripheral, Wired};
+use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
+use mcp23s08_io::mcp23s08::{Mcp23s08, Pin, Reg};
+use mcp23s08_io::shiftreg::{Hc165Chain, Hc595Chain};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+const DATA: Pin = Pin::P0;
+const CLOCK: Pin = Pin::P1;
+const LATCH: Pin = Pin::P2;
+
+/// Chained 74HC595s: shift on clock rise, copy to outputs on latch rise.
+#[derive(Default)]
+struct Hc595s {
+    shift: u64,
+    outputs: u64,
+    clock: bool,
+    latch: bool,
+    clocks: u32,
+}
+
+impl Peripheral for Hc595s {
+    fn update(&mut self, levels: u8) -> u8 {
+        let clock = levels & CLOCK.mask() != 0;
+        let latch = levels & LATCH.mask() != 0;
+        if clock && !self.clock {
+            self.shift = (self.shift << 1) | u64::from(levels & DATA.mask());
+            self.clocks += 1;
+        }
+        if latch && !self.latch {
+            self.outputs = self.shift;
+        }
+        (self.clock, self.latch) = (clock, latch);
+        0xFF
+    }
+}
+
+#[test]
+fn hc595_chain_shifts_and_latches() {
+    let sim = Mcp23s08Sim::new();
+    let regs = RefCell::new(Hc595s {
+        clock: true,
+        latch: true,
+        ..Default::default()
+    });
+    let mut dev = Mcp23s08::new(Wired::new(&sim, &regs), 0).unwrap();
+    dev.set_port_direction(0x7F).unwrap();
+    dev.write_pin(Pin::P7, true).unwrap();
+    let mut chain: Hc595Chain<'_, _, 3> = Hc595Chain::new(&mut dev, DATA, CLOCK, LATCH).unwrap();
+
+    let before = sim.transactions();
+    chain.write([0x12, 0x34, 0x56]).unwrap();
+    // Two writes per bit and two for the latch pulse.
+    assert_eq!(sim.transactions() - before, 24 * 2 + 2);
+    assert_eq!(regs.borrow().outputs & 0xFF_FFFF, 0x56_3412);
+
+    chain.pin(9).set_high().unwrap();
+    assert_eq!(chain.state(), [0x12, 0x36, 0x56]);
+    assert!(chain.pin(9).is_set_high().unwrap());
+    chain.pin(4).set_low().unwrap();
+    assert_eq!(regs.borrow().outputs & 0xFF_FFFF, 0x56_3602);
+
+    // Pins outside the chain are untouched.
+    assert_eq!(sim.reg(0, Reg::OLAT) & 0x80, 0x80);
+}
+
+const LOAD: Pin = Pin::P2;
+
+/// Chained 74HC165s: parallel load while SH/LD is low, shift on clock rise.
+struct Hc165s {
+    inputs: [u8; 2],
+    order: Vec<bool>,
+    pos: usize,
+    clock: bool,
+}
+
+impl Peripheral for Hc165s {
+    fn update(&mut self, levels: u8) -> u8 {
+        let clock = levels & CLOCK.mask() != 0;
+        if levels & LOAD.mask() == 0 {
+            // QH of register 0 first, then the bits shifted in from register 1.
+            self.order = self
+                .inputs
+                .iter()
+                .flat_map(|&b| (0..8).rev().map(move |i| b & (1 << i) != 0))
+                .collect();
+            self.pos = 0;
+        } else if clock && !self.clock {
+            self.pos += 1;
+        }
+        self.clock = clock;
+        let qh = self.order.get(self.pos).copied().unwrap_or(true);
+        if qh { 0xFF } else { !DATA.mask() }
+    }
+}
+
+#[test]
+fn hc165_chain_loads_and_shifts_in() {
+    let sim = Mcp23s08Sim::new();
+    let regs = RefCell::new(Hc165s {
+        inputs: [0xA5, 0x3C],
+        order: Vec::new(),
+        pos: 0,
+        clock: false,
+    });
+    let mut dev = Mcp23s08::new(Wired::new(&sim, &regs), 0).unwrap();
+    let mut chain: Hc165Chain<'_, _, 2> = Hc165Chain::new(&mut dev, DATA, CLOCK, LOAD).unwrap();
+
+    assert_eq!(chain.read().unwrap(), [0xA5, 0x3C]);
+
+    regs.borrow_mut().inputs = [0x00, 0x10];
+    assert!(chain.pin(12).is_high().unwrap());
+    assert!(chain.pin(0).is_low().unwrap());
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "stats")]
+
+mod common;
+
+use common::block_on;
+use embedded_hal::spi::ErrorKind;
+use mcp23s08_io::mcp23s08::{
+    HealthCheck, Mcp23s08, ResetDetect, RetryPolicy, Stats, VerifyMode, VerifyPolicy,
+};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[test]
+fn counts_transactions_and_bytes() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    // new(): one write (3 bytes out) and two reads (2 out, 1 in each)
+    assert_eq!(dev.stats().transactions, 3);
+    assert_eq!(dev.stats().bytes_sent, 7);
+    assert_eq!(dev.stats().bytes_received, 2);
+
+    dev.reset_stats();
+    dev.write_olat(0x01).unwrap();
+    dev.read_port().unwrap();
+    assert_eq!(
+        dev.stats(),
+        Stats {
+            transactions: 2,
+            bytes_sent: 5,
+            bytes_received: 1,
+            ..Stats::default()
+        }
+    );
+}
+
+#[test]
+fn counts_errors_retries_verify_failures_resets_and_interrupts() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x0F).unwrap();
+    dev.set_health_check(Some(HealthCheck::new(ResetDetect::Iodir)))
+        .unwrap();
+    dev.reset_stats();
+
+    dev.set_retry_policy(RetryPolicy::new(2));
+    sim.fail_next_with(1, ErrorKind::Overrun);
+    dev.write_olat(0x02).unwrap();
+    sim.fail_next_with(2, ErrorKind::FrameFormat);
+    assert!(dev.write_olat(0x03).is_err());
+
+    dev.set_verify(VerifyPolicy::new(VerifyMode::Outputs, 1));
+    sim.drop_next_writes(1);
+    dev.write_olat(0x04).unwrap();
+
+    sim.power_on_reset();
+    assert!(dev.check().unwrap());
+
+    dev.clear_interrupts().unwrap();
+    dev.read_interrupt_capture().unwrap();
+
+    let stats = dev.stats();
+    assert_eq!(stats.errors.overrun, 1);
+    assert_eq!(stats.errors.frame_format, 2);
+    assert_eq!(stats.errors.total(), 3);
+    assert_eq!(stats.retries, 2);
+    assert_eq!(stats.verify_failures, 1);
+    assert_eq!(stats.resets, 1);
+    assert_eq!(stats.interrupts_serviced, 2);
+}
+
+#[test]
+fn async_driver_keeps_the_same_counters() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.reset_stats();
+        sim.fail_next(1);
+        assert!(dev.read_port().await.is_err());
+        let stats = dev.stats();
+        assert_eq!(stats.transactions, 1);
+        assert_eq!(stats.errors.other, 1);
+    });
+}
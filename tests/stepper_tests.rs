@@ -0,0 +1,119 @@
+mod common;
+
+use core::cell::RefCell;
+
+use common::{block_on, output_device};
+use mcp23s08_io::mcp23s08::{Pin, Reg};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+use mcp23s08_io::stepper::{Direction, StepMode, Stepper};
+
+const COILS: [Pin; 4] = [Pin::P4, Pin::P5, Pin::P6, Pin::P7];
+
+/// Delay that records the coil pattern on the bus and the requested wait.
+struct StepProbe<'a> {
+    sim: &'a Mcp23s08Sim,
+    log: &'a RefCell<Vec<(u8, u32)>>,
+}
+
+impl embedded_hal::delay::DelayNs for StepProbe<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        let coils = self.sim.reg(0, Reg::OLAT) >> 4;
+        self.log.borrow_mut().push((coils, ns / 1_000));
+    }
+}
+
+impl embedded_hal_async::delay::DelayNs for StepProbe<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        let coils = self.sim.reg(0, Reg::OLAT) >> 4;
+        self.log.borrow_mut().push((coils, ns / 1_000));
+    }
+}
+
+#[test]
+fn sequences_in_both_directions() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    dev.write_olat(0x05).unwrap(); // other pins must survive
+
+    let mut motor = Stepper::new(COILS, StepMode::Half);
+    let mut seen = Vec::new();
+    for _ in 0..8 {
+        motor.step(&mut dev, Direction::Forward).unwrap();
+        seen.push(sim.reg(0, Reg::OLAT));
+    }
+    assert_eq!(seen, [0x35, 0x25, 0x65, 0x45, 0xC5, 0x85, 0x95, 0x15]);
+    assert_eq!(motor.position(), 8);
+
+    motor.set_mode(StepMode::Full);
+    motor.step(&mut dev, Direction::Reverse).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x95);
+    motor.set_mode(StepMode::Wave);
+    motor.step(&mut dev, Direction::Reverse).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x45);
+    assert_eq!(motor.position(), 6);
+}
+
+#[test]
+fn each_step_is_one_write() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let mut motor = Stepper::new(COILS, StepMode::Full).hold(true);
+    let before = sim.transactions();
+    for _ in 0..10 {
+        motor.step(&mut dev, Direction::Forward).unwrap();
+    }
+    assert_eq!(sim.transactions() - before, 10);
+}
+
+#[test]
+fn move_ramps_up_and_down_then_releases() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = output_device(&sim);
+    let log = RefCell::new(Vec::new());
+    let mut delay = StepProbe {
+        sim: &sim,
+        log: &log,
+    };
+    let mut motor = Stepper::new(COILS, StepMode::Wave)
+        .speed(1_000)
+        .ramp(100, 50_000);
+
+    motor.move_steps(&mut dev, &mut delay, -7).unwrap();
+    assert_eq!(motor.position(), -7);
+
+    let waits: Vec<u32> = log.borrow().iter().map(|&(_, us)| us).collect();
+    // 100 sps, then sqrt(100² + 2·50000·n) capped at 1000 sps, mirrored.
+    assert_eq!(waits, [10_000, 3_021, 2_183, 1_798, 2_183, 3_021, 10_000]);
+    let coils: Vec<u8> = log.borrow().iter().map(|&(c, _)| c).collect();
+    assert_eq!(
+        coils,
+        [0b1000, 0b0100, 0b0010, 0b0001, 0b1000, 0b0100, 0b0010]
+    );
+    // Released after the move.
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+}
+
+#[test]
+fn async_move_holds_when_asked() {
+    let sim = Mcp23s08Sim::new();
+    let log = RefCell::new(Vec::new());
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        let mut motor = Stepper::new(COILS, StepMode::Full).speed(500).hold(true);
+        let mut delay = StepProbe {
+            sim: &sim,
+            log: &log,
+        };
+        motor
+            .move_steps_async(&mut dev, &mut delay, 3)
+            .await
+            .unwrap();
+        assert_eq!(sim.reg(0, Reg::OLAT), 0x90);
+        motor.release_async(&mut dev).await.unwrap();
+    });
+    assert_eq!(log.borrow().len(), 3);
+    assert!(log.borrow().iter().all(|&(_, us)| us == 2_000));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+}
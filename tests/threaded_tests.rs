@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use embedded_hal::digital::{OutputPin, StatefulOutputPin};
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+use mcp23s08_io::bank::BankError;
+use mcp23s08_io::expander::IoExpander;
+use mcp23s08_io::mcp23s08::{Access, Error, Mcp23s08, Pin, Reg};
+use mcp23s08_io::shared::{ArcPin, SharedMcp23s08};
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+/// Simulator reachable from several threads, like a spidev file.
+#[derive(Clone)]
+struct ThreadSim(Arc<Mutex<Mcp23s08Sim>>);
+
+impl ErrorType for ThreadSim {
+    type Error = ErrorKind;
+}
+
+impl SpiDevice for ThreadSim {
+    fn transaction(&mut self, ops: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        self.0.lock().unwrap().device().transaction(ops)
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn pins_toggle_from_many_threads_without_lost_updates() {
+    assert_send_sync::<SharedMcp23s08<ThreadSim>>();
+    assert_send_sync::<ArcPin<Mcp23s08<ThreadSim>>>();
+
+    let sim = ThreadSim(Arc::new(Mutex::new(Mcp23s08Sim::new())));
+    let mut io = SharedMcp23s08::new(Mcp23s08::new(sim.clone(), 0).unwrap());
+    io.set_port_direction(0x00).unwrap();
+
+    // Every thread flips its own pin an odd number of times; a lost
+    // read-modify-write would leave some other pin in the wrong state.
+    let threads: Vec<_> = [Pin::P0, Pin::P2, Pin::P5, Pin::P7]
+        .into_iter()
+        .map(|pin| {
+            let mut pin = io.pin(pin);
+            thread::spawn(move || {
+                for _ in 0..101 {
+                    pin.toggle().unwrap();
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(io.output_latch(), 0xA5);
+    assert_eq!(sim.0.lock().unwrap().reg(0, Reg::OLAT), 0xA5);
+
+    let mut led = io.pin(Pin::P1);
+    led.set_high().unwrap();
+    assert!(led.is_set_high().unwrap());
+    drop(led);
+    let Ok(dev) = io.try_into_inner() else {
+        panic!("pins still hold the driver");
+    };
+    assert_eq!(dev.output_latch(), 0xA7);
+}
+
+#[test]
+fn errors_implement_std_error() {
+    let spi = Error::Bus {
+        reg: Reg::OLAT,
+        access: Access::Write,
+        source: ErrorKind::Other,
+    };
+    let bank = BankError {
+        chip: 2,
+        error: Error::<ErrorKind>::NotPresent,
+    };
+    let boxed: Box<dyn std::error::Error> = Box::new(bank);
+    assert_eq!(boxed.to_string(), "chip 2: device not present");
+    assert_eq!(boxed.source().unwrap().to_string(), "device not present");
+    assert_eq!(spi.to_string(), "bus error writing OLAT: Other");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::block_on;
+use mcp23s08_io::mcp23s08::{Error, Mcp23s08, Reg, VerifyMode, VerifyPolicy};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[test]
+fn lost_write_is_retried_until_it_lands() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_verify(VerifyPolicy::new(VerifyMode::All, 2));
+
+    sim.drop_next_writes(2);
+    dev.write_olat(0x81).unwrap();
+
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x81);
+}
+
+#[test]
+fn mismatch_after_retries_reports_verify_failed() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_verify(VerifyPolicy::new(VerifyMode::Outputs, 1));
+
+    sim.drop_next_writes(2);
+    let err = dev.set_port_direction(0x0F).err().unwrap();
+
+    assert_eq!(
+        err,
+        Error::VerifyFailed {
+            reg: Reg::IODIR,
+            wrote: 0x0F,
+            read: 0xFF,
+        }
+    );
+}
+
+#[test]
+fn gpio_write_is_checked_through_olat() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0xFF).unwrap(); // inputs: GPIO reads pins, not the latch
+    dev.set_verify(VerifyPolicy::new(VerifyMode::Outputs, 0));
+
+    dev.write_port(0x3C).unwrap();
+
+    sim.drop_next_writes(1);
+    assert!(matches!(
+        dev.write_port(0x00),
+        Err(Error::VerifyFailed { reg: Reg::GPIO, .. })
+    ));
+}
+
+#[test]
+fn outputs_mode_does_not_read_back_configuration() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_verify(VerifyPolicy::new(VerifyMode::Outputs, 0));
+
+    let before = sim.transactions();
+    dev.set_port_pullups(0xF0).unwrap();
+    assert_eq!(sim.transactions() - before, 1);
+
+    let before = sim.transactions();
+    dev.write_olat(0x01).unwrap();
+    assert_eq!(sim.transactions() - before, 2);
+}
+
+#[test]
+fn async_driver_uses_the_same_policy() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_verify(VerifyPolicy::new(VerifyMode::All, 1));
+
+        sim.drop_next_writes(1);
+        dev.write_olat(0x42).await.unwrap();
+
+        sim.drop_next_writes(2);
+        assert!(matches!(
+            dev.write_olat(0x24).await,
+            Err(Error::VerifyFailed {
+                reg: Reg::OLAT,
+                wrote: 0x24,
+                read: 0x42,
+            })
+        ));
+    });
+}
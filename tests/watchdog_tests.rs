@@ -0,0 +1,102 @@
+#![allow(clippy::bool_assert_comparison)]
+
+mod common;
+
+use common::block_on;
+use mcp23s08_io::mcp23s08::{Error, Mcp23s08, Pin, Reg, WatchdogConfig, WatchdogFault};
+use mcp23s08_io::mcp23s08async::Mcp23s08async;
+use mcp23s08_io::sim::Mcp23s08Sim;
+
+#[test]
+fn missed_feed_drives_safe_state() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev.write_olat(0xFF).unwrap();
+    dev.set_watchdog(Some(WatchdogConfig::new(100, 0x00).safe_iodir(0xF0)), 0);
+
+    dev.feed(80);
+    assert_eq!(dev.poll_watchdog(150).unwrap(), false);
+    assert_eq!(sim.reg(0, Reg::OLAT), 0xFF);
+
+    assert_eq!(dev.poll_watchdog(181).unwrap(), true);
+    assert_eq!(dev.watchdog_fault(), Some(WatchdogFault::Timeout));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+    assert_eq!(sim.reg(0, Reg::IODIR), 0xF0);
+
+    // Latched: polling again does not rewrite anything.
+    assert_eq!(dev.poll_watchdog(500).unwrap(), false);
+}
+
+#[test]
+fn output_writes_are_refused_until_cleared() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev.set_watchdog(Some(WatchdogConfig::new(10, 0x00)), 0);
+    assert!(dev.poll_watchdog(20).unwrap());
+
+    assert_eq!(
+        dev.write_pin(Pin::P0, true),
+        Err(Error::WatchdogFault(WatchdogFault::Timeout))
+    );
+    assert_eq!(
+        dev.set_port_direction(0xFF),
+        Err(Error::WatchdogFault(WatchdogFault::Timeout))
+    );
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+    // Inputs and configuration stay usable.
+    sim.set_inputs(0, 0xFF);
+    dev.read_port().unwrap();
+    dev.set_port_pullups(0x0F).unwrap();
+
+    dev.clear_watchdog_fault(20);
+    dev.write_pin(Pin::P0, true).unwrap();
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x01);
+}
+
+#[test]
+fn consecutive_bus_errors_trip_the_watchdog() {
+    let sim = Mcp23s08Sim::new();
+    let mut dev = Mcp23s08::new(sim.device(), 0).unwrap();
+    dev.set_port_direction(0x00).unwrap();
+    dev.write_olat(0xAA).unwrap();
+    dev.set_watchdog(Some(WatchdogConfig::new(1_000, 0x00).max_bus_errors(3)), 0);
+
+    // A success in between resets the count.
+    sim.fail_next(2);
+    assert!(dev.read_port().is_err());
+    assert!(dev.read_port().is_err());
+    dev.read_port().unwrap();
+    assert_eq!(dev.watchdog_fault(), None);
+
+    sim.fail_next(3);
+    for _ in 0..3 {
+        assert!(matches!(dev.read_port(), Err(Error::Bus { .. })));
+    }
+    assert_eq!(dev.watchdog_fault(), Some(WatchdogFault::BusErrors));
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x00);
+}
+
+#[test]
+fn async_watchdog_trips_and_clears() {
+    let sim = Mcp23s08Sim::new();
+    block_on(async {
+        let mut dev = Mcp23s08async::new(sim.device(), 0).await.unwrap();
+        dev.set_port_direction(0x00).await.unwrap();
+        dev.write_olat(0x0F).await.unwrap();
+        dev.set_watchdog(Some(WatchdogConfig::new(50, 0x80)), 0);
+
+        assert_eq!(dev.poll_watchdog(40).await.unwrap(), false);
+        assert_eq!(dev.poll_watchdog(51).await.unwrap(), true);
+        assert_eq!(sim.reg(0, Reg::OLAT), 0x80);
+        assert_eq!(
+            dev.write_olat(0x01).await,
+            Err(Error::WatchdogFault(WatchdogFault::Timeout))
+        );
+
+        dev.clear_watchdog_fault(60);
+        dev.write_olat(0x01).await.unwrap();
+    });
+    assert_eq!(sim.reg(0, Reg::OLAT), 0x01);
+}